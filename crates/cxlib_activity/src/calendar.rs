@@ -0,0 +1,194 @@
+use crate::Activity;
+use std::{
+    fmt::{Display, Formatter},
+    time::{Duration, SystemTime},
+};
+
+fn ics_time_from_mills(mills: u64) -> String {
+    chrono::DateTime::<chrono::Utc>::from(std::time::UNIX_EPOCH + Duration::from_millis(mills))
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+/// 按 RFC 5545 转义文本值。
+fn escape_ics_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+/// 按 RFC 5545 折叠长度超过 75 字节的内容行，不会截断多字节字符。
+fn fold_ics_line(line: &str) -> String {
+    const MAX_LINE_OCTETS: usize = 75;
+    let mut folded = String::with_capacity(line.len() + line.len() / MAX_LINE_OCTETS * 3);
+    let mut line_octets = 0;
+    for c in line.chars() {
+        if line_octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            // 续行开头的空格也计入长度。
+            line_octets = 1;
+        }
+        folded.push(c);
+        line_octets += c.len_utf8();
+    }
+    folded
+}
+
+/// # ActivityCalendar
+///
+/// 将[活动](Activity)导出为 iCalendar (`.ics`) 格式，可供日历应用订阅。
+///
+/// 每个活动对应一个 `VEVENT`, 其 `UID` 由活动 ID 生成，故重复导出时日历应用会更新而非重复添加事件。
+pub struct ActivityCalendar {
+    name: String,
+    event_duration: Duration,
+    activities: Vec<Activity>,
+}
+impl ActivityCalendar {
+    /// 默认事件时长为 10 分钟。
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            event_duration: Duration::from_secs(600),
+            activities: Vec::new(),
+        }
+    }
+    /// 设置事件时长。活动本身只有开始时间，事件结束时间为开始时间加上该时长。
+    pub fn with_event_duration(mut self, event_duration: Duration) -> Self {
+        self.event_duration = event_duration;
+        self
+    }
+    /// 添加活动。没有开始时间的活动会被忽略。
+    pub fn add_activity(&mut self, activity: Activity) {
+        if activity.get_start_time_mills() > 0 {
+            self.activities.push(activity)
+        }
+    }
+    /// 事件的 `UID`.
+    pub fn get_uid(activity: &Activity) -> String {
+        format!("{}@cxlib", activity.get_id())
+    }
+    fn write_event(&self, ics: &mut String, activity: &Activity, dtstamp: &str) {
+        let course = activity.get_course();
+        let (name, category) = match activity {
+            Activity::RawSign(sign) => (&sign.name, "签到"),
+            Activity::Other(other) => (&other.name, "活动"),
+        };
+        let start = activity.get_start_time_mills();
+        let end = start + self.event_duration.as_millis() as u64;
+        let status = if activity.get_status() == crate::ACTIVITY_STATUS_ENDED {
+            "已结束"
+        } else {
+            "进行中"
+        };
+        let lines = [
+            "BEGIN:VEVENT".to_owned(),
+            format!("UID:{}", escape_ics_text(&Self::get_uid(activity))),
+            format!("DTSTAMP:{dtstamp}"),
+            format!("DTSTART:{}", ics_time_from_mills(start)),
+            format!("DTEND:{}", ics_time_from_mills(end)),
+            format!(
+                "SUMMARY:{}",
+                escape_ics_text(&format!("[{}] {name}", course.get_name()))
+            ),
+            format!(
+                "DESCRIPTION:{}",
+                escape_ics_text(&format!(
+                    "课程：{}\n任课教师：{}\n状态：{status}",
+                    course.get_name(),
+                    course.get_teacher()
+                ))
+            ),
+            format!("CATEGORIES:{category}"),
+            "END:VEVENT".to_owned(),
+        ];
+        for line in lines {
+            ics.push_str(&fold_ics_line(&line));
+            ics.push_str("\r\n");
+        }
+    }
+    /// 以 `now_mills` 作为 `DTSTAMP` 生成 iCalendar 文本。
+    pub fn to_ics_at(&self, now_mills: u64) -> String {
+        let dtstamp = ics_time_from_mills(now_mills);
+        let mut ics = String::new();
+        ics.push_str("BEGIN:VCALENDAR\r\n");
+        ics.push_str("VERSION:2.0\r\n");
+        ics.push_str("PRODID:-//worksoup//cxlib//CN\r\n");
+        ics.push_str("CALSCALE:GREGORIAN\r\n");
+        ics.push_str(&fold_ics_line(&format!(
+            "X-WR-CALNAME:{}",
+            escape_ics_text(&self.name)
+        )));
+        ics.push_str("\r\n");
+        let mut activities = self.activities.iter().collect::<Vec<_>>();
+        activities.sort_by_key(|a| (a.get_start_time_mills(), a.get_id().to_owned()));
+        for activity in activities {
+            self.write_event(&mut ics, activity, &dtstamp);
+        }
+        ics.push_str("END:VCALENDAR\r\n");
+        ics
+    }
+    /// 生成 iCalendar 文本。
+    pub fn to_ics(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        self.to_ics_at(now)
+    }
+}
+impl Extend<Activity> for ActivityCalendar {
+    fn extend<T: IntoIterator<Item = Activity>>(&mut self, iter: T) {
+        for activity in iter {
+            self.add_activity(activity)
+        }
+    }
+}
+impl Display for ActivityCalendar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_ics())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{calendar::fold_ics_line, Activity, ActivityCalendar, RawSign};
+    use cxlib_types::Course;
+
+    #[test]
+    fn test_fold_ics_line() {
+        let line = "SUMMARY:".to_owned() + &"签到".repeat(30);
+        let folded = fold_ics_line(&line);
+        for l in folded.split("\r\n") {
+            assert!(l.len() <= 75);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn test_to_ics() {
+        let mut calendar = ActivityCalendar::new("课程");
+        calendar.extend([Activity::RawSign(RawSign {
+            start_time_mills: 1_700_000_000_000,
+            active_id: "4000000".to_owned(),
+            name: "签到,位置".to_owned(),
+            course: Course::new(1, 2, "teacher", "", "高等数学"),
+            other_id: "4".to_owned(),
+            status_code: 1,
+        })]);
+        let ics = calendar.to_ics_at(1_700_000_000_000);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("UID:4000000@cxlib\r\n"));
+        assert!(ics.contains("DTSTART:20231114T221320Z\r\n"));
+        assert!(ics.contains("DTEND:20231114T222320Z\r\n"));
+        assert!(ics.contains("SUMMARY:[高等数学] 签到\\,位置\r\n"));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::Activity;
+use cxlib_store::Config;
+use cxlib_types::Course;
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    time::{Duration, SystemTime},
+};
+
+/// 课程被排除的原因，参见 [`ExclusionPolicy::explain`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ExclusionReason {
+    /// 课程被手动排除。
+    Manual,
+    /// 课程名匹配了排除模式。
+    NameMatched(String),
+    /// 本学期（自 `term_start_mills` 起）没有任何签到。
+    NoSignInTerm { term_start_mills: u64 },
+    /// `days` 天内没有任何签到。
+    NoRecentSign { days: i64 },
+}
+impl Display for ExclusionReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExclusionReason::Manual => write!(f, "已被手动排除"),
+            ExclusionReason::NameMatched(pattern) => {
+                write!(f, "课程名匹配排除模式 `{pattern}`")
+            }
+            ExclusionReason::NoSignInTerm { term_start_mills } => {
+                let start = chrono::DateTime::<chrono::Local>::from(
+                    std::time::UNIX_EPOCH + Duration::from_millis(*term_start_mills),
+                );
+                write!(f, "自学期开始（{}）以来没有签到", start.format("%Y-%m-%d"))
+            }
+            ExclusionReason::NoRecentSign { days } => write!(f, "{days} 天内没有签到"),
+        }
+    }
+}
+
+/// # ExclusionPolicy
+///
+/// 课程排除策略。规则按以下优先级依次判断：
+///
+/// 1. 固定（pin）的课程永不排除；
+/// 2. 手动排除的课程总是排除；
+/// 3. 课程名包含任一排除模式的课程排除；
+/// 4. 设置了学期开始时间时，学期内没有签到的课程排除；
+/// 5. 设置了最长不活跃天数时，该天数内没有签到的课程排除。
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExclusionPolicy {
+    /// 最长不活跃天数，为 `None` 时不按时间排除。默认为 [`DEFAULT_MAX_INACTIVE_DAYS`](Self::DEFAULT_MAX_INACTIVE_DAYS).
+    pub max_inactive_days: Option<i64>,
+    /// 学期开始时间（毫秒时间戳），此前的签到不计入。
+    pub term_start_mills: Option<u64>,
+    /// 课程名排除模式，课程名包含其中任一字符串即排除。
+    pub name_patterns: Vec<String>,
+    /// 固定的课程 ID, 这些课程永不排除。
+    pub pinned: HashSet<i64>,
+    /// 手动排除的课程 ID.
+    pub manual: HashSet<i64>,
+}
+impl Default for ExclusionPolicy {
+    fn default() -> Self {
+        Self {
+            max_inactive_days: Some(Self::DEFAULT_MAX_INACTIVE_DAYS),
+            term_start_mills: None,
+            name_patterns: Vec::new(),
+            pinned: HashSet::new(),
+            manual: HashSet::new(),
+        }
+    }
+}
+impl ExclusionPolicy {
+    /// 默认的最长不活跃天数。
+    pub const DEFAULT_MAX_INACTIVE_DAYS: i64 = 160;
+    /// 以配置中的最长不活跃天数创建排除策略，其余规则为默认值。
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_inactive_days: config.max_inactive_days,
+            ..Default::default()
+        }
+    }
+    /// 判断课程是否应被排除，若是，返回原因。
+    pub fn explain<'a, I: IntoIterator<Item = &'a Activity>>(
+        &self,
+        course: &Course,
+        activities: I,
+    ) -> Option<ExclusionReason> {
+        let now = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        self.explain_at(course, activities, now)
+    }
+    /// 同 [`explain`](Self::explain), 但以 `now_mills` 作为当前时间。
+    pub fn explain_at<'a, I: IntoIterator<Item = &'a Activity>>(
+        &self,
+        course: &Course,
+        activities: I,
+        now_mills: u64,
+    ) -> Option<ExclusionReason> {
+        let id = course.get_id();
+        if self.pinned.contains(&id) {
+            return None;
+        }
+        if self.manual.contains(&id) {
+            return Some(ExclusionReason::Manual);
+        }
+        if let Some(pattern) = self
+            .name_patterns
+            .iter()
+            .find(|pattern| !pattern.is_empty() && course.get_name().contains(pattern.as_str()))
+        {
+            return Some(ExclusionReason::NameMatched(pattern.clone()));
+        }
+        let latest_sign_mills = activities
+            .into_iter()
+            .filter_map(|activity| match activity {
+                Activity::RawSign(sign) => Some(sign.start_time_mills),
+                Activity::Other(_) => None,
+            })
+            .max();
+        if let Some(term_start_mills) = self.term_start_mills {
+            if !latest_sign_mills.is_some_and(|latest| latest >= term_start_mills) {
+                return Some(ExclusionReason::NoSignInTerm { term_start_mills });
+            }
+        }
+        if let Some(days) = self.max_inactive_days {
+            let inactive_days = latest_sign_mills
+                .map(|latest| (now_mills.saturating_sub(latest) / 86_400_000) as i64);
+            if !inactive_days.is_some_and(|inactive_days| inactive_days < days) {
+                return Some(ExclusionReason::NoRecentSign { days });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Activity, CourseExcludeInfoTrait, ExclusionPolicy, ExclusionReason, RawSign};
+    use cxlib_store::Config;
+    use cxlib_types::Course;
+    use std::{collections::HashSet, sync::Mutex, time::SystemTime};
+
+    const DAY: u64 = 86_400_000;
+
+    fn sign(course: &Course, start_time_mills: u64) -> Activity {
+        Activity::RawSign(RawSign {
+            start_time_mills,
+            active_id: "1".to_owned(),
+            name: "签到".to_owned(),
+            course: course.clone(),
+            other_id: "2".to_owned(),
+            status_code: 2,
+        })
+    }
+
+    #[test]
+    fn test_explain() {
+        let course = Course::new(1, 2, "teacher", "", "高等数学");
+        let now = 1000 * DAY;
+        let policy = ExclusionPolicy::default();
+        assert_eq!(
+            policy.explain_at(&course, &[sign(&course, now - DAY)], now),
+            None
+        );
+        assert_eq!(
+            policy.explain_at(&course, &[sign(&course, now - 200 * DAY)], now),
+            Some(ExclusionReason::NoRecentSign {
+                days: ExclusionPolicy::DEFAULT_MAX_INACTIVE_DAYS
+            })
+        );
+        assert_eq!(
+            policy.explain_at(&course, &[], now),
+            Some(ExclusionReason::NoRecentSign {
+                days: ExclusionPolicy::DEFAULT_MAX_INACTIVE_DAYS
+            })
+        );
+        let mut policy = ExclusionPolicy {
+            term_start_mills: Some(now - 10 * DAY),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.explain_at(&course, &[sign(&course, now - 20 * DAY)], now),
+            Some(ExclusionReason::NoSignInTerm {
+                term_start_mills: now - 10 * DAY
+            })
+        );
+        policy.name_patterns.push("数学".to_owned());
+        assert_eq!(
+            policy.explain_at(&course, &[sign(&course, now - DAY)], now),
+            Some(ExclusionReason::NameMatched("数学".to_owned()))
+        );
+        policy.manual.insert(1);
+        assert_eq!(
+            policy.explain_at(&course, &[], now),
+            Some(ExclusionReason::Manual)
+        );
+        policy.pinned.insert(1);
+        assert_eq!(policy.explain_at(&course, &[], now), None);
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = Config {
+            max_inactive_days: None,
+            ..Default::default()
+        };
+        let policy = ExclusionPolicy::from_config(&config);
+        assert_eq!(policy.max_inactive_days, None);
+        assert_eq!(
+            ExclusionPolicy {
+                max_inactive_days: Some(ExclusionPolicy::DEFAULT_MAX_INACTIVE_DAYS),
+                ..policy
+            },
+            ExclusionPolicy::default()
+        );
+    }
+
+    /// 使用默认排除策略的排除列表，不受配置影响。
+    struct DefaultPolicyExcludes(Mutex<HashSet<i64>>);
+    impl CourseExcludeInfoTrait for DefaultPolicyExcludes {
+        fn get_exclusion_policy(&self) -> ExclusionPolicy {
+            ExclusionPolicy::default()
+        }
+        fn is_excluded(&self, id: i64) -> bool {
+            self.0.is_excluded(id)
+        }
+        fn get_excludes(&self) -> HashSet<i64> {
+            self.0.get_excludes()
+        }
+        fn exclude(&self, id: i64) {
+            self.0.exclude(id)
+        }
+        fn cancel_exclude(&self, id: i64) {
+            self.0.cancel_exclude(id)
+        }
+        fn update_excludes<'a, I: IntoIterator<Item = &'a i64>>(&self, excludes: I) {
+            self.0.update_excludes(excludes)
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_if_should_exclude() {
+        let course = Course::new(1, 2, "teacher", "", "高等数学");
+        let now = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let table = DefaultPolicyExcludes(Mutex::new(HashSet::from([1])));
+        let recent = [sign(&course, now - DAY)];
+        assert!(table.if_should_exclude(&recent));
+        assert!(!table.should_exclude(&course, &recent));
+        let none: [Activity; 0] = [];
+        assert!(!table.if_should_exclude(&none));
+        assert!(table.should_exclude(&course, &none));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{Activity, OtherActivity};
+use cxlib_error::HomeworkError;
+use cxlib_types::{CloudDrive, CloudFile, Course};
+use cxlib_user::Session;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+
+/// # Homework
+///
+/// 课程作业，即课程[活动列表](Activity::get_list_from_course)中名称含有 [`KEYWORD`](Self::KEYWORD) 的[其他活动](OtherActivity)。
+///
+/// 活动列表只提供名称、状态及发布时间，题目与作答请在网页端查看。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Homework {
+    activity: OtherActivity,
+}
+impl Homework {
+    /// 作业活动名称中的关键字。
+    pub const KEYWORD: &'static str = "作业";
+    /// 活动为作业时返回对应的作业。
+    pub fn from_activity(activity: &Activity) -> Option<Self> {
+        match activity {
+            Activity::Other(other) if other.name.contains(Self::KEYWORD) => Some(Self {
+                activity: other.clone(),
+            }),
+            _ => None,
+        }
+    }
+    /// 从活动列表中筛选出作业。
+    pub fn from_activities<'a, I: IntoIterator<Item = &'a Activity>>(activities: I) -> Vec<Self> {
+        activities
+            .into_iter()
+            .filter_map(Self::from_activity)
+            .collect()
+    }
+    /// 获取课程的作业列表。
+    pub fn list(session: &Session, course: &Course) -> Result<Vec<Self>, HomeworkError> {
+        let activities = Activity::get_list_from_course(session, course)?;
+        let list = Self::from_activities(&activities);
+        debug!("课程[{}]共有 {} 个作业。", course.get_name(), list.len());
+        Ok(list)
+    }
+    /// 活动 ID.
+    pub fn get_id(&self) -> &str {
+        &self.activity.id
+    }
+    pub fn get_title(&self) -> &str {
+        &self.activity.name
+    }
+    pub fn get_course(&self) -> &Course {
+        &self.activity.course
+    }
+    /// 发布时间（毫秒时间戳）。
+    pub fn get_start_time_mills(&self) -> u64 {
+        self.activity.start_time_mills
+    }
+    /// 是否已结束，即活动状态码为 `2`.
+    pub fn is_ended(&self) -> bool {
+        self.activity.status == 2
+    }
+    pub fn as_activity(&self) -> &OtherActivity {
+        &self.activity
+    }
+}
+
+/// 作业附件，即上传至网盘的文件。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HomeworkAttachment {
+    #[serde(rename = "objectId")]
+    object_id: String,
+    name: String,
+    size: u64,
+}
+impl HomeworkAttachment {
+    /// 上传文件至网盘并作为附件。
+    pub fn upload(drive: &CloudDrive, file: &File, file_name: &str) -> Result<Self, HomeworkError> {
+        let size = file
+            .metadata()
+            .map_err(cxlib_error::CloudDriveError::from)?
+            .len();
+        let object_id = drive.upload(file, file_name)?;
+        Ok(Self {
+            object_id,
+            name: file_name.to_owned(),
+            size,
+        })
+    }
+    /// 使用网盘中已有的文件作为附件。文件夹返回 `None`.
+    pub fn from_cloud_file(file: &CloudFile) -> Option<Self> {
+        Some(Self {
+            object_id: file.get_object_id()?.to_owned(),
+            name: file.get_name().to_owned(),
+            size: file.get_size(),
+        })
+    }
+    pub fn get_object_id(&self) -> &str {
+        &self.object_id
+    }
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Activity, Homework, OtherActivity, RawSign};
+    use cxlib_types::Course;
+
+    #[test]
+    fn test_from_activities() {
+        let course = Course::new(1, 2, "teacher", "", "高等数学");
+        let other = |id: &str, name: &str, status: i32| {
+            Activity::Other(OtherActivity {
+                id: id.to_owned(),
+                name: name.to_owned(),
+                course: course.clone(),
+                status,
+                start_time_mills: 0,
+            })
+        };
+        let activities = [
+            other("1", "第一次作业", 2),
+            other("2", "课程通知", 1),
+            Activity::RawSign(RawSign {
+                start_time_mills: 0,
+                active_id: "3".to_owned(),
+                name: "作业讲评签到".to_owned(),
+                course: course.clone(),
+                other_id: "0".to_owned(),
+                status_code: 1,
+            }),
+            other("4", "作业：第二章", 1),
+        ];
+        let homework = Homework::from_activities(&activities);
+        assert_eq!(
+            homework.iter().map(Homework::get_id).collect::<Vec<_>>(),
+            vec!["1", "4"]
+        );
+        assert!(homework[0].is_ended());
+        assert!(!homework[1].is_ended());
+        assert_eq!(homework[1].get_course(), &course);
+    }
+}
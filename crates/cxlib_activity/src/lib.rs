@@ -1,30 +1,35 @@
+mod calendar;
+mod exclusion;
+mod homework;
+mod notice;
 mod raw;
+mod utils;
+mod watcher;
 
+pub use calendar::*;
 pub use cxlib_error::ActivityError;
+pub use exclusion::*;
+pub use homework::*;
+pub use notice::*;
 pub use raw::*;
+pub use watcher::*;
 
-use cxlib_error::{CxlibResultUtils, MaybeFatalError};
-use cxlib_protocol::collect::activity as protocol;
+use cxlib_error::MaybeFatalError;
+use cxlib_protocol::{collect::activity as protocol, ProtocolItem};
 use cxlib_types::Course;
 use cxlib_user::Session;
-use log::debug;
+use cxlib_utils::{host_of, WorkerPool};
+use log::{debug, info};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
-    sync::{Arc, Mutex},
-    time::{Duration, SystemTime},
+    sync::Mutex,
 };
 
-fn time_delta_from_mills(mills: u64) -> chrono::TimeDelta {
-    let start_time = std::time::UNIX_EPOCH + Duration::from_millis(mills);
-    let now = SystemTime::now();
-    let duration = now.duration_since(start_time).log_unwrap();
-    chrono::TimeDelta::from_std(duration).log_unwrap()
-}
 /// # Activity
 ///
 /// 活动类型，是一个枚举，可能是一个[暂未被分类的课程签到](RawSign)，也可能是[其他活动](OtherActivity)，如通知、作业等。
-#[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Hash)]
+#[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Hash, Clone)]
 pub enum Activity {
     RawSign(RawSign),
     Other(OtherActivity),
@@ -32,16 +37,40 @@ pub enum Activity {
 /// # CourseExcludeInfoTrait
 /// 课程排除列表特型。在获取[活动](Activity)列表时排除部分课程的活动，以此提高加载速度。
 pub trait CourseExcludeInfoTrait {
-    /// 默认排除逻辑，即 160 天内没有任何签到即排除。
+    /// 获取排除策略。默认为 [`ExclusionPolicy::from_config`], 即按[配置](cxlib_store::Config)中的最长不活跃天数排除。
+    fn get_exclusion_policy(&self) -> ExclusionPolicy {
+        ExclusionPolicy::from_config(&cxlib_store::Config::get())
+    }
+    /// 判断课程是否应被排除，若是，返回原因。参见 [`ExclusionPolicy::explain`].
+    fn explain_exclusion<'a, I: IntoIterator<Item = &'a Activity>>(
+        &self,
+        course: &Course,
+        activities: I,
+    ) -> Option<ExclusionReason> {
+        self.get_exclusion_policy().explain(course, activities)
+    }
+    /// 课程是否应被排除。
+    fn should_exclude<'a, I: IntoIterator<Item = &'a Activity>>(
+        &self,
+        course: &Course,
+        activities: I,
+    ) -> bool {
+        self.explain_exclusion(course, activities).is_some()
+    }
+    /// 旧的排除逻辑，返回 `true` 表示课程**不应**被排除，与 [`should_exclude`](Self::should_exclude) 相反。
+    ///
+    /// 由于没有课程信息，只按排除策略中与签到时间相关的规则判断，不考虑固定、手动排除及课程名。
+    #[deprecated(note = "请使用 `should_exclude` 或 `explain_exclusion`, 注意返回值的含义相反。")]
     fn if_should_exclude<'a, I: IntoIterator<Item = &'a Activity>>(&self, activities: I) -> bool {
-        for activity in activities {
-            if let Activity::RawSign(sign) = activity {
-                if time_delta_from_mills(sign.start_time_mills).num_days() < 160 {
-                    return true;
-                }
-            }
-        }
-        false
+        let policy = ExclusionPolicy {
+            name_patterns: Vec::new(),
+            pinned: HashSet::new(),
+            manual: HashSet::new(),
+            ..self.get_exclusion_policy()
+        };
+        policy
+            .explain(&Course::new(0, 0, "", "", ""), activities)
+            .is_none()
     }
     /// 课程是否被排除，参数为课程 ID.
     fn is_excluded(&self, id: i64) -> bool;
@@ -78,33 +107,88 @@ impl CourseExcludeInfoTrait for Mutex<HashSet<i64>> {
     }
 }
 impl Activity {
+    /// 活动 ID.
+    pub fn get_id(&self) -> &str {
+        match self {
+            Activity::RawSign(sign) => &sign.active_id,
+            Activity::Other(other) => &other.id,
+        }
+    }
+    /// 活动所属的课程。
+    pub fn get_course(&self) -> &Course {
+        match self {
+            Activity::RawSign(sign) => &sign.course,
+            Activity::Other(other) => &other.course,
+        }
+    }
+    /// 活动状态码，`1` 为进行中，`2` 为已结束。
+    pub fn get_status(&self) -> i32 {
+        match self {
+            Activity::RawSign(sign) => sign.status_code,
+            Activity::Other(other) => other.status,
+        }
+    }
+    /// 活动开始时间（毫秒时间戳）。
+    pub fn get_start_time_mills(&self) -> u64 {
+        match self {
+            Activity::RawSign(sign) => sign.start_time_mills,
+            Activity::Other(other) => other.start_time_mills,
+        }
+    }
     /// 获取指定的**单个**课程的活动，并决定是否将该课程加入到排除列表中。
     ///
-    /// 具体逻辑参见 [`CourseExcludeInfoTrait::if_should_exclude`].
+    /// 具体逻辑参见 [`CourseExcludeInfoTrait::explain_exclusion`].
     pub fn get_course_activities(
         table: &impl CourseExcludeInfoTrait,
         session: &Session,
         course: &Course,
         set_excludes: bool,
+    ) -> Result<Vec<Activity>, ActivityError> {
+        let policy = table.get_exclusion_policy();
+        Self::get_course_activities_with_policy(table, &policy, session, course, set_excludes)
+    }
+    fn get_course_activities_with_policy(
+        table: &impl CourseExcludeInfoTrait,
+        policy: &ExclusionPolicy,
+        session: &Session,
+        course: &Course,
+        set_excludes: bool,
     ) -> Result<Vec<Activity>, ActivityError> {
         let activities = Self::get_list_from_course(session, course)?;
         if set_excludes {
             let id = course.get_id();
-            let dont_exclude = table.if_should_exclude(&activities);
+            let reason = policy.explain(course, &activities);
             let excluded = table.is_excluded(id);
-            if dont_exclude && excluded {
-                table.cancel_exclude(id);
-            } else if !dont_exclude && !excluded {
-                table.exclude(id);
+            match reason {
+                None if excluded => {
+                    info!("课程[{}]不再被排除。", course.get_name());
+                    table.cancel_exclude(id);
+                }
+                Some(reason) if !excluded => {
+                    info!("课程[{}]将被排除：{reason}.", course.get_name());
+                    table.exclude(id);
+                }
+                _ => (),
             }
         }
         Ok(activities)
     }
+    /// 获取指定课程的活动，并返回该课程应被排除的原因。若不应排除，返回 `None`.
+    ///
+    /// 该函数不会改变排除列表。
+    pub fn explain_course_exclusion(
+        table: &impl CourseExcludeInfoTrait,
+        session: &Session,
+        course: &Course,
+    ) -> Result<Option<ExclusionReason>, ActivityError> {
+        let activities = Self::get_list_from_course(session, course)?;
+        Ok(table.explain_exclusion(course, &activities))
+    }
     /// 获取指定课程集合的活动，并决定是否将这些课程加入到排除列表中。
     ///
     /// 当 `set_excludes` 为 `true` 时，该函数会获取所有这些课程的活动，并根据结果改变排除列表。
     ///
-    /// 具体逻辑参见 [`CourseExcludeInfoTrait::if_should_exclude`].
+    /// 具体逻辑参见 [`CourseExcludeInfoTrait::explain_exclusion`].
     ///
     /// 反之，则会根据排除列表排除部分课程，以此提高获取速度。
     ///
@@ -114,6 +198,24 @@ impl Activity {
         set_excludes: bool,
         courses: HashMap<Course, Vec<Session>>,
     ) -> Result<HashMap<Activity, Vec<Session>>, ActivityError> {
+        let mut valid_signs = HashMap::new();
+        for (activities, sessions) in
+            Self::get_activities_by_course(table, set_excludes, courses)?.into_values()
+        {
+            for activity in activities {
+                valid_signs.insert(activity, sessions.clone());
+            }
+        }
+        Ok(valid_signs)
+    }
+    /// 同 [`get_activities`](Self::get_activities), 但按课程分组。
+    ///
+    /// 结果包含所有成功获取的课程（包括没有活动的课程），获取失败的课程不在其中。
+    fn get_activities_by_course(
+        table: &impl CourseExcludeInfoTrait,
+        set_excludes: bool,
+        courses: HashMap<Course, Vec<Session>>,
+    ) -> Result<HashMap<Course, (Vec<Activity>, Vec<Session>)>, ActivityError> {
         let excludes = table.get_excludes();
         let set_excludes = set_excludes || excludes.is_empty();
         let mut course_sessions_map = courses;
@@ -126,76 +228,57 @@ impl Activity {
                 .cloned()
                 .collect()
         };
-        let excludes = Arc::new(Mutex::new(excludes));
-        let mut valid_signs = HashMap::new();
-        let thread_count = 256;
-        let len = courses.len();
-        let chunk_rest = len % thread_count;
-        let chunk_count = len / thread_count + if chunk_rest == 0 { 0 } else { 1 };
-        for i in 0..chunk_count {
-            let courses = &courses[i * thread_count..if i != chunk_count - 1 {
-                (i + 1) * thread_count
-            } else {
-                len
-            }];
-            let mut handles = Vec::new();
-            for course in courses {
+        let excludes = Mutex::new(excludes);
+        let policy = table.get_exclusion_policy();
+        let tasks = courses
+            .into_iter()
+            .filter_map(|course| {
+                course_sessions_map[&course]
+                    .first()
+                    .cloned()
+                    .map(|session| (course, session))
+            })
+            .collect::<Vec<_>>();
+        let host = ProtocolItem::ActiveList.to_string();
+        let results = WorkerPool::get_global().map(
+            host_of(&host),
+            tasks,
+            |(course, session)| -> Result<(Vec<Activity>, Course), ActivityError> {
                 debug!("加载课程{course}的签到。");
-                if let Some(session) = &course_sessions_map[course].first() {
-                    let course = course.clone();
-                    let session = (*session).clone();
-                    let excludes = excludes.clone();
-                    let handle = std::thread::spawn(
-                        move || -> Result<(Vec<Activity>, Course), ActivityError> {
-                            let activities = Self::get_course_activities(
-                                &*excludes,
-                                &session,
-                                &course,
-                                set_excludes,
-                            )?;
-                            Ok((activities, course))
-                        },
-                    );
-                    handles.push(handle);
+                let activities = Self::get_course_activities_with_policy(
+                    &excludes,
+                    &policy,
+                    &session,
+                    &course,
+                    set_excludes,
+                )?;
+                Ok((activities, course))
+            },
+        );
+        let mut course_activities_map = HashMap::new();
+        for result in results {
+            match result {
+                Ok((activities, course)) => {
+                    let sessions = course_sessions_map.remove(&course).unwrap_or_default();
+                    course_activities_map.insert(course, (activities, sessions));
                 }
-            }
-            for h in handles {
-                debug!("handle: {h:?} join.");
-                let activities = h.join().unwrap();
-                let activities = match activities {
-                    Ok(activities) => (
-                        activities.0,
-                        course_sessions_map
-                            .remove(&activities.1)
-                            .unwrap_or_default(),
-                    ),
-                    Err(e) => {
-                        if e.is_fatal() {
-                            return Err(e);
-                        } else {
-                            (Default::default(), Default::default())
-                        }
+                Err(e) => {
+                    if e.is_fatal() {
+                        return Err(e);
                     }
-                };
-                let mut iter = activities.0.into_iter();
-                if let Some(activity) = iter.next() {
-                    for activity in iter {
-                        valid_signs.insert(activity, activities.1.clone());
-                    }
-                    valid_signs.insert(activity, activities.1);
                 }
             }
         }
         if set_excludes {
-            table.update_excludes(&Arc::into_inner(excludes).unwrap().into_inner().unwrap());
+            table.update_excludes(&excludes.into_inner().unwrap());
         }
-        Ok(valid_signs)
+        Ok(course_activities_map)
     }
     /// 获取所有的活动。
     ///
     /// 当 `set_excludes` 为 `true` 时，该函数会获取所有活动，并根据结果改变排除列表。
     ///
-    /// 具体逻辑参见 [`CourseExcludeInfoTrait::if_should_exclude`].
+    /// 具体逻辑参见 [`CourseExcludeInfoTrait::explain_exclusion`].
     ///
     /// 反之，则会根据排除列表排除部分课程，以此提高获取速度。
     ///
@@ -208,60 +291,55 @@ impl Activity {
         let courses = Course::get_courses(sessions)?;
         Self::get_activities(table, set_excludes, courses)
     }
+    /// 获取所有课程的活动，按课程分组。
+    ///
+    /// 与 [`get_all_activities`](Self::get_all_activities) 不同，没有活动的课程也会出现在结果中，
+    /// 获取失败的课程则不会，据此可以区分“活动列表为空”与“未能获取”。
+    pub fn get_all_course_activities<'a, Sessions: Iterator<Item = &'a Session> + Clone>(
+        table: &impl CourseExcludeInfoTrait,
+        sessions: Sessions,
+        set_excludes: bool,
+    ) -> Result<HashMap<Course, Vec<Activity>>, ActivityError> {
+        let courses = Course::get_courses(sessions)?;
+        Ok(
+            Self::get_activities_by_course(table, set_excludes, courses)?
+                .into_iter()
+                .map(|(course, (activities, _))| (course, activities))
+                .collect(),
+        )
+    }
     pub fn get_list_from_course(session: &Session, c: &Course) -> Result<Vec<Self>, ActivityError> {
         let r = protocol::active_list(session, (c.get_id(), c.get_class_id()))?;
         let r: GetActivityR = r.into_json().unwrap();
-        let activities = Arc::new(Mutex::new(Vec::new()));
+        let mut activities = Vec::new();
         if let Some(data) = r.data {
-            let thread_count = 1;
-            let len = data.active_list.len();
-            let chunk_rest = len % thread_count;
-            let chunk_count = len / thread_count + if chunk_rest == 0 { 0 } else { 1 };
-            for i in 0..chunk_count {
-                let ars = &data.active_list[i * thread_count..if i != chunk_count - 1 {
-                    (i + 1) * thread_count
+            for ar in data.active_list {
+                if ar.other_id.as_ref().is_some_and(|oid| {
+                    let other_id_i64: i64 = oid.parse().unwrap();
+                    (0..=5).contains(&other_id_i64)
+                }) {
+                    let other_id = unsafe { ar.other_id.unwrap_unchecked() };
+                    let active_id = ar.id.to_string();
+                    let base_sign = RawSign {
+                        active_id,
+                        name: ar.name_one,
+                        course: c.clone(),
+                        other_id,
+                        status_code: ar.status,
+                        start_time_mills: ar.start_time_mills,
+                    };
+                    activities.push(Self::RawSign(base_sign))
                 } else {
-                    len
-                }];
-                let mut handles = Vec::new();
-                for ar in ars {
-                    let ar = ar.clone();
-                    let c = c.clone();
-                    let activities = activities.clone();
-                    let handle = std::thread::spawn(move || {
-                        if ar.other_id.as_ref().is_some_and(|oid| {
-                            let other_id_i64: i64 = oid.parse().unwrap();
-                            (0..=5).contains(&other_id_i64)
-                        }) {
-                            let other_id = unsafe { ar.other_id.unwrap_unchecked() };
-                            let active_id = ar.id.to_string();
-                            let base_sign = RawSign {
-                                active_id,
-                                name: ar.name_one,
-                                course: c.clone(),
-                                other_id,
-                                status_code: ar.status,
-                                start_time_mills: ar.start_time_mills,
-                            };
-                            activities.lock().unwrap().push(Self::RawSign(base_sign))
-                        } else {
-                            activities.lock().unwrap().push(Self::Other(OtherActivity {
-                                id: ar.id.to_string(),
-                                name: ar.name_one,
-                                course: c.clone(),
-                                status: ar.status,
-                                start_time_mills: ar.start_time_mills,
-                            }))
-                        }
-                    });
-                    handles.push(handle);
-                }
-                for h in handles {
-                    h.join().unwrap();
+                    activities.push(Self::Other(OtherActivity {
+                        id: ar.id.to_string(),
+                        name: ar.name_one,
+                        course: c.clone(),
+                        status: ar.status,
+                        start_time_mills: ar.start_time_mills,
+                    }))
                 }
             }
         }
-        let activities = Arc::into_inner(activities).unwrap().into_inner().unwrap();
         Ok(activities)
     }
 }
@@ -269,7 +347,7 @@ impl Activity {
 /// # OtherActivity
 ///
 /// 除课程签到外的其他活动，如通知、作业等。
-#[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Hash)]
+#[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Hash, Clone)]
 pub struct OtherActivity {
     pub id: String,
     pub name: String,
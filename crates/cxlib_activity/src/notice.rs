@@ -0,0 +1,302 @@
+use crate::utils::{id_to_string, parse_api_response};
+use cxlib_error::ActivityError;
+use cxlib_protocol::collect::activity as protocol;
+use cxlib_types::Course;
+use cxlib_user::Session;
+use log::{debug, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use ureq::{serde_json, Response};
+
+fn parse_response<T: DeserializeOwned>(r: Response) -> Result<T, ActivityError> {
+    parse_api_response(r).map_err(ActivityError::UnexpectedResponse)
+}
+
+/// # Notice
+///
+/// 课程通知。
+///
+/// 活动列表中的通知只有名称，归类为[其他活动](crate::OtherActivity)，
+/// 可通过 [`Notice::list`] 获取带有已读状态的通知列表，并通过 [`Notice::get_detail`] 获取正文及附件。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Notice {
+    id: String,
+    title: String,
+    course: Course,
+    sender: String,
+    send_time_mills: u64,
+    is_read: bool,
+}
+#[derive(Deserialize)]
+struct NoticeRaw {
+    id: serde_json::Value,
+    #[serde(default)]
+    title: String,
+    #[serde(default, rename = "creatorName")]
+    creator_name: String,
+    #[serde(default, rename = "sendTime")]
+    send_time: u64,
+    #[serde(default, rename = "isRead")]
+    is_read: i32,
+}
+#[derive(Deserialize)]
+struct NoticeListRaw {
+    #[serde(default)]
+    list: Vec<NoticeRaw>,
+    #[serde(default)]
+    total: usize,
+}
+/// 通知列表的一页。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoticePage {
+    pub notices: Vec<Notice>,
+    /// 通知总数。
+    pub total: usize,
+}
+/// 依次获取各页（从 `1` 开始）并拼接，参见 [`Notice::list`].
+///
+/// 某页与上一页的第一条通知相同时，认为服务端忽略了页码，停止翻页。
+fn collect_pages(
+    page_size: u32,
+    max_pages: u32,
+    mut fetch: impl FnMut(u32) -> Result<NoticePage, ActivityError>,
+) -> Result<Vec<Notice>, ActivityError> {
+    let mut notices = Vec::new();
+    let mut last_first_id = None;
+    for page in 1..=max_pages {
+        let NoticePage {
+            notices: mut page_notices,
+            total,
+        } = fetch(page)?;
+        let first_id = page_notices.first().map(|n| n.id.clone());
+        if first_id.is_some() && first_id == last_first_id {
+            warn!("通知第 {page} 页与上一页重复，停止翻页。");
+            return Ok(notices);
+        }
+        let is_last_page = page_notices.len() < page_size as usize;
+        notices.append(&mut page_notices);
+        if is_last_page || notices.len() >= total {
+            return Ok(notices);
+        }
+        last_first_id = first_id;
+    }
+    Err(ActivityError::UnexpectedResponse(format!(
+        "通知超过 {max_pages} 页仍未列完。"
+    )))
+}
+impl Notice {
+    /// 每页的通知数量。
+    pub const PAGE_SIZE: u32 = 20;
+    /// 获取所有通知时最多请求的页数。
+    pub const MAX_PAGES: u32 = 1000;
+    fn from_raw(raw: NoticeRaw, course: &Course) -> Option<Self> {
+        Some(Self {
+            id: id_to_string(raw.id)?,
+            title: raw.title,
+            course: course.clone(),
+            sender: raw.creator_name,
+            send_time_mills: raw.send_time,
+            is_read: raw.is_read != 0,
+        })
+    }
+    /// 获取课程通知的某一页，`page` 从 `1` 开始。
+    pub fn list_page(
+        session: &Session,
+        course: &Course,
+        page: u32,
+        page_size: u32,
+    ) -> Result<NoticePage, ActivityError> {
+        let r = protocol::notice_list(
+            session,
+            (course.get_id(), course.get_class_id()),
+            page,
+            page_size,
+        )?;
+        let raw: NoticeListRaw = parse_response(r)?;
+        let notices = raw
+            .list
+            .into_iter()
+            .filter_map(|raw| Self::from_raw(raw, course))
+            .collect();
+        Ok(NoticePage {
+            notices,
+            total: raw.total,
+        })
+    }
+    /// 获取课程的所有通知，按发布时间从新到旧排列。
+    pub fn list(session: &Session, course: &Course) -> Result<Vec<Self>, ActivityError> {
+        let mut notices = collect_pages(Self::PAGE_SIZE, Self::MAX_PAGES, |page| {
+            Self::list_page(session, course, page, Self::PAGE_SIZE)
+        })?;
+        debug!("课程[{}]共有 {} 条通知。", course.get_name(), notices.len());
+        notices.sort_by(|a, b| b.send_time_mills.cmp(&a.send_time_mills));
+        Ok(notices)
+    }
+    /// 获取课程的未读通知。
+    pub fn list_unread(session: &Session, course: &Course) -> Result<Vec<Self>, ActivityError> {
+        let mut notices = Self::list(session, course)?;
+        notices.retain(|n| !n.is_read);
+        Ok(notices)
+    }
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+    pub fn get_course(&self) -> &Course {
+        &self.course
+    }
+    /// 发布者名称。
+    pub fn get_sender(&self) -> &str {
+        &self.sender
+    }
+    /// 发布时间（毫秒时间戳）。
+    pub fn get_send_time_mills(&self) -> u64 {
+        self.send_time_mills
+    }
+    pub fn is_read(&self) -> bool {
+        self.is_read
+    }
+    /// 获取通知正文及附件。
+    ///
+    /// 获取详情不会将通知标记为已读，请使用 [`Notice::mark_read`].
+    pub fn get_detail(&self, session: &Session) -> Result<NoticeDetail, ActivityError> {
+        let r = protocol::notice_detail(session, &self.id)?;
+        let raw: NoticeDetailRaw = parse_response(r)?;
+        Ok(NoticeDetail {
+            notice: self.clone(),
+            content: raw.content,
+            attachments: raw.attachments,
+        })
+    }
+    /// 将通知标记为已读。
+    pub fn mark_read(&mut self, session: &Session) -> Result<(), ActivityError> {
+        let r = protocol::notice_mark_read(session, &self.id)?;
+        parse_response::<serde_json::Value>(r)?;
+        self.is_read = true;
+        Ok(())
+    }
+}
+
+/// 通知附件。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NoticeAttachment {
+    name: String,
+    #[serde(default, rename = "objectId")]
+    object_id: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    size: u64,
+}
+impl NoticeAttachment {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    /// 云盘对象 ID, 可通过 [`CloudDrive::get_metadata`](cxlib_types::CloudDrive::get_metadata) 获取下载地址。
+    pub fn get_object_id(&self) -> Option<&str> {
+        self.object_id.as_deref().filter(|o| !o.is_empty())
+    }
+    /// 下载地址，部分附件没有该值。
+    pub fn get_url(&self) -> Option<&str> {
+        self.url.as_deref().filter(|u| !u.is_empty())
+    }
+    /// 文件大小（字节），未知时为 `0`.
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+}
+
+#[derive(Deserialize)]
+struct NoticeDetailRaw {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    attachments: Vec<NoticeAttachment>,
+}
+/// 通知详情。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoticeDetail {
+    notice: Notice,
+    content: String,
+    attachments: Vec<NoticeAttachment>,
+}
+impl NoticeDetail {
+    pub fn get_notice(&self) -> &Notice {
+        &self.notice
+    }
+    /// 通知正文。
+    pub fn get_content(&self) -> &str {
+        &self.content
+    }
+    pub fn get_attachments(&self) -> &[NoticeAttachment] {
+        &self.attachments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notice::{collect_pages, Notice, NoticeListRaw, NoticePage};
+    use cxlib_types::Course;
+    use ureq::serde_json;
+
+    #[test]
+    fn test_notice_list() {
+        let course = Course::new(1, 2, "teacher", "", "高等数学");
+        let raw: NoticeListRaw = serde_json::from_str(
+            r#"{"total":3,"list":[
+                {"id":11,"title":"停课","creatorName":"teacher","sendTime":1700000000000,"isRead":0},
+                {"id":"12","title":"考试","sendTime":1700000001000,"isRead":1},
+                {"id":"","title":"无效"}
+            ]}"#,
+        )
+        .unwrap();
+        let notices = raw
+            .list
+            .into_iter()
+            .filter_map(|raw| Notice::from_raw(raw, &course))
+            .collect::<Vec<_>>();
+        assert_eq!(notices.len(), 2);
+        assert_eq!(notices[0].get_id(), "11");
+        assert!(!notices[0].is_read());
+        assert!(notices[1].is_read());
+        assert_eq!(notices[1].get_sender(), "");
+    }
+
+    #[test]
+    fn test_collect_pages() {
+        let course = Course::new(1, 2, "teacher", "", "高等数学");
+        let page = |ids: &[u32], total: usize| NoticePage {
+            notices: ids
+                .iter()
+                .map(|id| Notice {
+                    id: id.to_string(),
+                    title: String::new(),
+                    course: course.clone(),
+                    sender: String::new(),
+                    send_time_mills: 0,
+                    is_read: false,
+                })
+                .collect(),
+            total,
+        };
+        // 服务端忽略页码，总是返回第一页，且总数有误。
+        let notices = collect_pages(2, 10, |_| Ok(page(&[1, 2], 100))).unwrap();
+        assert_eq!(notices.len(), 2);
+        let notices = collect_pages(2, 10, |p| {
+            Ok(match p {
+                1 => page(&[1, 2], 3),
+                2 => page(&[3], 3),
+                _ => unreachable!(),
+            })
+        })
+        .unwrap();
+        assert_eq!(notices.len(), 3);
+        let mut next = 0;
+        assert!(collect_pages(2, 10, |_| {
+            next += 2;
+            Ok(page(&[next, next + 1], 100))
+        })
+        .is_err());
+    }
+}
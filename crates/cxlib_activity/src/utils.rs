@@ -0,0 +1,29 @@
+use serde::{de::DeserializeOwned, Deserialize};
+use ureq::{serde_json, Response};
+
+/// 通知等接口的通用响应。
+#[derive(Deserialize)]
+struct ApiR<T> {
+    #[serde(default)]
+    result: i32,
+    #[serde(default)]
+    msg: Option<String>,
+    data: Option<T>,
+}
+/// 解析形如 `{"result":1,"msg":"","data":...}` 的响应，失败时返回错误信息或响应原文。
+pub(crate) fn parse_api_response<T: DeserializeOwned>(r: Response) -> Result<T, String> {
+    let text = r.into_string().map_err(|e| e.to_string())?;
+    let r: ApiR<T> = serde_json::from_str(&text).map_err(|e| format!("{e}: {text}"))?;
+    match r.data {
+        Some(data) if r.result == 1 => Ok(data),
+        _ => Err(r.msg.unwrap_or(text)),
+    }
+}
+/// 将字符串或数字形式的 ID 转为字符串。
+pub(crate) fn id_to_string(id: serde_json::Value) -> Option<String> {
+    match id {
+        serde_json::Value::String(id) if !id.is_empty() => Some(id),
+        serde_json::Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{Activity, ActivityError, CourseExcludeInfoTrait, OtherActivity};
+use cxlib_error::MaybeFatalError;
+use cxlib_types::Course;
+use cxlib_user::Session;
+use log::{debug, warn};
+use std::{collections::HashMap, ops::ControlFlow, sync::Mutex, time::Duration};
+
+/// 活动状态码：已结束。
+pub const ACTIVITY_STATUS_ENDED: i32 = 2;
+
+/// # ActivityEvent
+///
+/// [`ActivityWatcher`] 在两次轮询之间检测到的活动变化。
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ActivityEvent {
+    /// 新出现的活动。
+    New(Activity),
+    /// 活动状态发生了变化，`old_status` 为上次记录的状态码。
+    StatusChanged { activity: Activity, old_status: i32 },
+    /// 活动已结束。
+    ///
+    /// 若活动已从课程的活动列表中消失，则无法得知其详细信息，此时为一个 [`OtherActivity`],
+    /// 其中只有活动 ID 与课程有效。
+    Ended(Activity),
+}
+impl ActivityEvent {
+    /// 事件对应的活动。
+    pub fn get_activity(&self) -> &Activity {
+        match self {
+            ActivityEvent::New(activity) => activity,
+            ActivityEvent::StatusChanged { activity, .. } => activity,
+            ActivityEvent::Ended(activity) => activity,
+        }
+    }
+}
+
+/// # ActivitySnapshotTrait
+/// 活动快照特型。按课程记录上次见到的活动及其状态，供 [`ActivityWatcher`] 计算增量。
+pub trait ActivitySnapshotTrait {
+    /// 获取某课程上次记录的活动快照，键为活动 ID, 值为状态码。
+    ///
+    /// 若该课程从未被记录过，返回 `None`.
+    fn get_snapshot(&self, course_id: i64) -> Option<HashMap<String, i32>>;
+    /// 更新某课程的活动快照。
+    /// 在默认实现中，该函数会完全删除该课程的旧数据，并更新为新数据。
+    fn update_snapshot(&self, course_id: i64, snapshot: &HashMap<String, i32>);
+}
+impl ActivitySnapshotTrait for Mutex<HashMap<i64, HashMap<String, i32>>> {
+    fn get_snapshot(&self, course_id: i64) -> Option<HashMap<String, i32>> {
+        self.lock().unwrap().get(&course_id).cloned()
+    }
+
+    fn update_snapshot(&self, course_id: i64, snapshot: &HashMap<String, i32>) {
+        self.lock().unwrap().insert(course_id, snapshot.clone());
+    }
+}
+
+/// 比较某课程的新旧快照，返回活动变化事件。
+///
+/// `old` 为 `None` 时表示该课程尚无记录，此时仅当 `emit_initial` 为 `true` 时才将所有活动视为新活动。
+///
+/// 旧快照中有、但 `activities` 中没有的活动视为已结束。
+pub fn diff_activities<I: IntoIterator<Item = Activity>>(
+    course: &Course,
+    old: Option<&HashMap<String, i32>>,
+    activities: I,
+    emit_initial: bool,
+) -> (Vec<ActivityEvent>, HashMap<String, i32>) {
+    let mut events = Vec::new();
+    let mut snapshot = HashMap::new();
+    for activity in activities {
+        let status = activity.get_status();
+        snapshot.insert(activity.get_id().to_owned(), status);
+        match old {
+            None => {
+                if emit_initial {
+                    events.push(ActivityEvent::New(activity))
+                }
+            }
+            Some(old) => match old.get(activity.get_id()) {
+                None => events.push(ActivityEvent::New(activity)),
+                Some(&old_status) if old_status != status => {
+                    if status == ACTIVITY_STATUS_ENDED {
+                        events.push(ActivityEvent::Ended(activity))
+                    } else {
+                        events.push(ActivityEvent::StatusChanged {
+                            activity,
+                            old_status,
+                        })
+                    }
+                }
+                _ => (),
+            },
+        }
+    }
+    if let Some(old) = old {
+        let mut missing = old
+            .iter()
+            .filter(|(id, _)| !snapshot.contains_key(*id))
+            .collect::<Vec<_>>();
+        missing.sort();
+        for (id, &status) in missing {
+            if status == ACTIVITY_STATUS_ENDED {
+                continue;
+            }
+            events.push(ActivityEvent::Ended(Activity::Other(OtherActivity {
+                id: id.clone(),
+                name: String::new(),
+                course: course.clone(),
+                status: ACTIVITY_STATUS_ENDED,
+                start_time_mills: 0,
+            })))
+        }
+    }
+    (events, snapshot)
+}
+
+/// # ActivityWatcher
+///
+/// 活动监视器。定时轮询所有课程的活动，并与[快照](ActivitySnapshotTrait)比较，只报告变化的部分。
+///
+/// 轮询时会遵循[排除列表](CourseExcludeInfoTrait)，被排除的课程不会被请求。
+pub struct ActivityWatcher<'t, Excludes: CourseExcludeInfoTrait, Snapshots: ActivitySnapshotTrait> {
+    excludes: &'t Excludes,
+    snapshots: &'t Snapshots,
+    interval: Duration,
+    max_backoff: Duration,
+    emit_initial: bool,
+}
+impl<'t, Excludes: CourseExcludeInfoTrait, Snapshots: ActivitySnapshotTrait>
+    ActivityWatcher<'t, Excludes, Snapshots>
+{
+    /// 默认轮询间隔为 30 秒，出错时最长退避至 10 分钟。
+    pub fn new(excludes: &'t Excludes, snapshots: &'t Snapshots) -> Self {
+        Self {
+            excludes,
+            snapshots,
+            interval: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(600),
+            emit_initial: false,
+        }
+    }
+    /// 设置轮询间隔。
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    /// 设置出错时退避的最长间隔。每次出错后间隔翻倍，直至该值。
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+    /// 是否在首次见到某课程时将其所有活动作为 [`ActivityEvent::New`] 报告。默认不报告。
+    pub fn with_emit_initial(mut self, emit_initial: bool) -> Self {
+        self.emit_initial = emit_initial;
+        self
+    }
+    pub fn get_interval(&self) -> Duration {
+        self.interval
+    }
+    /// 轮询一次，返回自上次轮询以来的活动变化，并更新快照。
+    pub fn poll<'a, Sessions: Iterator<Item = &'a Session> + Clone>(
+        &self,
+        sessions: Sessions,
+    ) -> Result<Vec<ActivityEvent>, ActivityError> {
+        let course_activities_map =
+            Activity::get_all_course_activities(self.excludes, sessions, false)?;
+        let events = self.update(course_activities_map);
+        debug!("本次轮询共检测到 {} 个活动变化。", events.len());
+        Ok(events)
+    }
+    /// 以各课程新获取的活动更新快照，返回活动变化。
+    ///
+    /// 没有活动的课程也应包含在内，否则其中消失的活动不会被报告为已结束。
+    fn update(&self, course_activities_map: HashMap<Course, Vec<Activity>>) -> Vec<ActivityEvent> {
+        let mut events = Vec::new();
+        for (course, activities) in course_activities_map {
+            let course_id = course.get_id();
+            let old = self.snapshots.get_snapshot(course_id);
+            let (mut course_events, snapshot) =
+                diff_activities(&course, old.as_ref(), activities, self.emit_initial);
+            self.snapshots.update_snapshot(course_id, &snapshot);
+            events.append(&mut course_events);
+        }
+        events
+    }
+    /// 持续轮询，每次轮询后将变化交给 `on_events` 处理，直至其返回 [`ControlFlow::Break`].
+    ///
+    /// 非致命错误会被记录并以指数退避的方式重试；致命错误则直接返回。
+    pub fn watch<'a, Sessions, OnEvents>(
+        &self,
+        sessions: Sessions,
+        mut on_events: OnEvents,
+    ) -> Result<(), ActivityError>
+    where
+        Sessions: Iterator<Item = &'a Session> + Clone,
+        OnEvents: FnMut(Vec<ActivityEvent>) -> ControlFlow<()>,
+    {
+        let mut delay = self.interval;
+        loop {
+            match self.poll(sessions.clone()) {
+                Ok(events) => {
+                    delay = self.interval;
+                    if on_events(events).is_break() {
+                        return Ok(());
+                    }
+                }
+                Err(e) => {
+                    if e.is_fatal() {
+                        return Err(e);
+                    }
+                    delay = (delay * 2).min(self.max_backoff);
+                    warn!(
+                        "轮询活动失败，将在 {} 秒后重试。错误信息：{e}.",
+                        delay.as_secs()
+                    );
+                }
+            }
+            std::thread::sleep(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{diff_activities, Activity, ActivityEvent, ActivityWatcher, OtherActivity};
+    use cxlib_types::Course;
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Mutex,
+    };
+
+    fn course() -> Course {
+        Course::new(1, 2, "teacher", "", "course")
+    }
+
+    fn activity(id: &str, status: i32) -> Activity {
+        Activity::Other(OtherActivity {
+            id: id.to_owned(),
+            name: "作业".to_owned(),
+            course: course(),
+            status,
+            start_time_mills: 0,
+        })
+    }
+
+    fn removed(id: &str) -> Activity {
+        Activity::Other(OtherActivity {
+            id: id.to_owned(),
+            name: String::new(),
+            course: course(),
+            status: 2,
+            start_time_mills: 0,
+        })
+    }
+
+    #[test]
+    fn test_diff_activities() {
+        let course = course();
+        let (events, snapshot) = diff_activities(&course, None, [activity("1", 1)], false);
+        assert!(events.is_empty());
+        assert_eq!(snapshot, HashMap::from([("1".to_owned(), 1)]));
+        let (events, _) = diff_activities(&course, None, [activity("1", 1)], true);
+        assert_eq!(events, vec![ActivityEvent::New(activity("1", 1))]);
+        let (events, snapshot) = diff_activities(
+            &course,
+            Some(&snapshot),
+            [activity("1", 2), activity("2", 1)],
+            false,
+        );
+        assert_eq!(
+            events,
+            vec![
+                ActivityEvent::Ended(activity("1", 2)),
+                ActivityEvent::New(activity("2", 1))
+            ]
+        );
+        // 已结束的活动消失时不再重复报告。
+        let (events, _) = diff_activities(&course, Some(&snapshot), [activity("2", 3)], false);
+        assert_eq!(
+            events,
+            vec![ActivityEvent::StatusChanged {
+                activity: activity("2", 3),
+                old_status: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_missing_activities() {
+        let course = course();
+        let old = HashMap::from([
+            ("1".to_owned(), 1),
+            ("2".to_owned(), 1),
+            ("3".to_owned(), 2),
+        ]);
+        let (events, snapshot) = diff_activities(&course, Some(&old), [activity("2", 1)], false);
+        assert_eq!(events, vec![ActivityEvent::Ended(removed("1"))]);
+        assert_eq!(snapshot, HashMap::from([("2".to_owned(), 1)]));
+    }
+
+    #[test]
+    fn test_update_empty_course() {
+        let excludes = Mutex::new(HashSet::new());
+        let snapshots = Mutex::new(HashMap::new());
+        let watcher = ActivityWatcher::new(&excludes, &snapshots);
+        let events = watcher.update(HashMap::from([(course(), vec![activity("1", 1)])]));
+        assert!(events.is_empty());
+        // 课程的活动列表变为空时，也应更新快照并报告结束。
+        let events = watcher.update(HashMap::from([(course(), vec![])]));
+        assert_eq!(events, vec![ActivityEvent::Ended(removed("1"))]);
+        assert_eq!(
+            snapshots.lock().unwrap().get(&course().get_id()),
+            Some(&HashMap::new())
+        );
+        assert!(watcher
+            .update(HashMap::from([(course(), vec![])]))
+            .is_empty());
+    }
+}
@@ -19,15 +19,20 @@ impl LocationSign {
             .as_ref()
             .map(|l| l.to_shifted_location())
     }
+    /// 教师设置的签到位置及范围。
+    pub fn get_preset_range(&self) -> Option<&LocationWithRange> {
+        self.preset_location.as_ref()
+    }
 }
 impl SignTrait for LocationSign {
     type PreSignData = ();
     type Data = Location;
 
     fn sign_url(&self, session: &Session, _: &(), data: &Location) -> PPTSignHelper {
+        let (lat, lon) = (data.get_lat().to_string(), data.get_lon().to_string());
         protocol::location_sign_url(
             (session.get_uid(), session.get_fid(), session.get_stu_name()),
-            (data.get_addr(), data.get_lat(), data.get_lon()),
+            (data.get_addr(), &lat, &lon),
             self.raw_sign.active_id.as_str(),
             self.preset_location.is_some(),
         )
@@ -28,13 +28,22 @@ impl SignTrait for QrCodeSign {
     type Data = Option<Location>;
 
     fn sign_url(&self, session: &Session, enc: &str, location: &Option<Location>) -> PPTSignHelper {
+        // 没有海拔时以 0 代替。
+        let fields = location.as_ref().map(|l| {
+            (
+                l.get_addr(),
+                l.get_lat().to_string(),
+                l.get_lon().to_string(),
+                l.get_alt().unwrap_or_default().to_string(),
+            )
+        });
         protocol::qrcode_sign_url(
             (session.get_uid(), session.get_fid(), session.get_stu_name()),
             enc,
             self.as_inner().active_id.as_str(),
-            location
+            fields
                 .as_ref()
-                .map(|l| (l.get_addr(), l.get_lat(), l.get_lon(), l.get_alt())),
+                .map(|(addr, lat, lon, alt)| (*addr, lat.as_str(), lon.as_str(), alt.as_str())),
         )
     }
 
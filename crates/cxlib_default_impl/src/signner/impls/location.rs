@@ -36,7 +36,15 @@ impl<T: LocationInfoGetterTrait> SignnerTrait<LocationSign> for DefaultLocationS
         #[allow(clippy::mutable_key_type)]
         let mut map = HashMap::new();
         for session in sessions {
-            let r = Self::sign_single(sign, session, &locations)?;
+            let (r, index) = crate::signner::impls::utils::sign_single_retry_with_index(
+                sign,
+                session,
+                (&(), &locations),
+            )?;
+            if let Some(location) = index.and_then(|i| locations.get(i)) {
+                self.location_info_getter
+                    .on_location_success(sign, location);
+            }
             map.insert(session, r);
         }
         Ok(map)
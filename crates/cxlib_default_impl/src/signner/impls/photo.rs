@@ -1,16 +1,21 @@
 use crate::sign::PhotoSign;
+use cxlib_imageproc::PhotoPreprocessor;
 use cxlib_sign::{SignError, SignResult, SignTrait, SignnerTrait};
-use cxlib_types::Photo;
+use cxlib_types::{Photo, PhotoCacheTrait};
 use cxlib_user::Session;
 use log::warn;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::io::Cursor;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
 
-pub struct DefaultPhotoSignner {
+pub struct DefaultPhotoSignner<'c> {
     path: Option<PathBuf>,
+    preprocessor: Option<PhotoPreprocessor>,
+    cache: Option<&'c dyn PhotoCacheTrait>,
 }
 
-impl DefaultPhotoSignner {
+impl<'c> DefaultPhotoSignner<'c> {
     pub fn new(path: &Option<PathBuf>) -> Self {
         let path = path.as_ref().and_then(|pic| {
             std::fs::metadata(pic).ok().and_then(|metadata| {
@@ -21,10 +26,51 @@ impl DefaultPhotoSignner {
                 }
             })
         });
-        Self { path }
+        Self {
+            path,
+            preprocessor: None,
+            cache: None,
+        }
+    }
+    /// 设置上传照片前的预处理，参见 [`PhotoPreprocessor`]. 默认为 `None`, 即原样上传。
+    ///
+    /// 传入 [`PhotoPreprocessor::new`] 可去除 EXIF 等元数据，缩放及压缩需另行设置。
+    ///
+    /// 仅对通过路径指定的照片生效。
+    pub fn with_preprocessor(mut self, preprocessor: Option<PhotoPreprocessor>) -> Self {
+        self.preprocessor = preprocessor;
+        self
+    }
+    /// 设置照片缓存，参见 [`PhotoCacheTrait`]. 设置后，已上传过的照片不会重复上传。
+    pub fn with_cache(mut self, cache: Option<&'c dyn PhotoCacheTrait>) -> Self {
+        self.cache = cache;
+        self
+    }
+    fn upload_photo(&self, session: &Session, pic: &Path) -> Result<Photo, SignError> {
+        let file_name = pic
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or("photo.jpg");
+        let (data, file_name) = match &self.preprocessor {
+            Some(preprocessor) => {
+                let data = preprocessor
+                    .process_file(pic)
+                    .map_err(std::io::Error::other)?;
+                let file_name = PhotoPreprocessor::processed_file_name(file_name, &data);
+                (data, file_name)
+            }
+            None => (std::fs::read(pic)?, file_name.to_owned()),
+        };
+        let photo = match self.cache {
+            Some(cache) => Photo::upload_cached(session, &data, &file_name, cache)?,
+            None => Photo::upload_with_progress(session, Cursor::new(&data), &file_name, |_| {
+                ControlFlow::Continue(())
+            })?,
+        };
+        Ok(photo)
     }
 }
-impl SignnerTrait<PhotoSign> for DefaultPhotoSignner {
+impl SignnerTrait<PhotoSign> for DefaultPhotoSignner<'_> {
     type ExtData<'e> = &'e Photo;
 
     fn sign<'a, Sessions: Iterator<Item = &'a Session> + Clone>(
@@ -35,18 +81,34 @@ impl SignnerTrait<PhotoSign> for DefaultPhotoSignner {
         let mut pic_map = HashMap::new();
         #[allow(clippy::mutable_key_type)]
         let mut session_to_index = HashMap::new();
+        let mut fail_msg = format!("拍照签到[{}]没有获取到有效的照片！", sign.as_inner().name);
         if let Some(pic) = self.path.as_ref() {
-            for session in sessions.clone() {
-                let photo = Photo::get_from_file(session, pic);
-                pic_map.insert(0, photo);
-                for session in sessions.clone() {
-                    session_to_index.insert(session, 0);
+            // 所有用户共用同一张照片，故只需上传一次。
+            if let Some(session) = sessions.clone().next() {
+                match self.upload_photo(session, pic) {
+                    Ok(photo) => {
+                        pic_map.insert(0, photo);
+                    }
+                    Err(e) => {
+                        warn!("拍照签到时上传照片 `{}` 失败：{e}.", pic.display());
+                        fail_msg = format!("拍照签到[{}]上传照片失败：{e}.", sign.as_inner().name);
+                    }
                 }
             }
+            for session in sessions.clone() {
+                session_to_index.insert(session, 0);
+            }
         } else {
             let mut index = 0;
             for session in sessions.clone() {
-                let photo = Photo::default(session);
+                let photo = match self.cache {
+                    Some(cache) => Photo::default_cached(session, cache),
+                    None => Photo::default(session),
+                }
+                .unwrap_or_else(|e| {
+                    warn!("用户[{}]在网盘中查找照片失败：{e}.", session.get_stu_name());
+                    None
+                });
                 session_to_index.insert(session, index);
                 if let Some(photo) = photo {
                     pic_map.insert(index, photo);
@@ -70,7 +132,7 @@ impl SignnerTrait<PhotoSign> for DefaultPhotoSignner {
                 map.insert(
                     session,
                     SignResult::Fail {
-                        msg: format!("拍照签到[{}]没有获取到有效的照片！", sign.as_inner().name),
+                        msg: fail_msg.clone(),
                     },
                 );
             }
@@ -34,16 +34,29 @@ pub(crate) fn sign_single_retry<
 >(
     sign: &Sign,
     session: &Session,
-    (pre_sign_data, locations): (&<Sign as SignTrait>::PreSignData, InputDataIter),
+    data: (&<Sign as SignTrait>::PreSignData, InputDataIter),
 ) -> Result<SignResult, SignError> {
+    sign_single_retry_with_index(sign, session, data).map(|(r, _)| r)
+}
+/// 同 [`sign_single_retry`], 并返回签到成功时所用数据的序号。预签到即成功时序号为 `None`.
+pub(crate) fn sign_single_retry_with_index<
+    Sign: SignTrait + SignRetry<InputData, Data>,
+    InputData,
+    Data: Borrow<<Sign as SignTrait>::Data>,
+    InputDataIter: IntoIterator<Item = InputData>,
+>(
+    sign: &Sign,
+    session: &Session,
+    (pre_sign_data, locations): (&<Sign as SignTrait>::PreSignData, InputDataIter),
+) -> Result<(SignResult, Option<usize>), SignError> {
     let r = sign.pre_sign(session, pre_sign_data)?;
     match r {
-        PreSignResult::Susses => Ok(SignResult::Susses),
+        PreSignResult::Susses => Ok((SignResult::Susses, None)),
         PreSignResult::Data {
             ref url,
             data: ref pre_sign_result_data,
         } => {
-            for location in locations {
+            for (index, location) in locations.into_iter().enumerate() {
                 match sign.sign(
                     session,
                     url,
@@ -51,20 +64,23 @@ pub(crate) fn sign_single_retry<
                     pre_sign_data,
                     Sign::data_helper(location).borrow(),
                 )? {
-                    r @ SignResult::Susses => return Ok(r),
+                    r @ SignResult::Susses => return Ok((r, Some(index))),
                     SignResult::Fail { msg } => {
                         if Sign::guess_if_retry(msg.as_str()) {
                             continue;
                         } else {
-                            return Ok(SignResult::Fail { msg });
+                            return Ok((SignResult::Fail { msg }, None));
                         }
                     }
                 }
             }
             warn!("BUG: 请保留现场联系开发者处理。");
-            Ok(SignResult::Fail {
-                msg: "所有位置均不可用。".to_string(),
-            })
+            Ok((
+                SignResult::Fail {
+                    msg: "所有位置均不可用。".to_string(),
+                },
+                None,
+            ))
         }
     }
 }
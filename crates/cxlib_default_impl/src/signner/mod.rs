@@ -2,22 +2,37 @@ mod impls;
 pub mod utils;
 
 use crate::sign::LocationSign;
-use crate::store::{DataBase, LocationTable};
+use crate::store::{DataBase, LocationLibraryTrait};
 use cxlib_sign::SignTrait;
 use cxlib_types::Location;
 pub use impls::*;
+use log::warn;
 
 pub trait LocationInfoGetterTrait {
-    fn get_location_by_location_str(&self, location_str: &str) -> Option<Location>;
+    /// 由别名或位置 ID 获取位置，别名对应多个位置时优先选择属于该签到课程的位置。
+    fn get_location_by_location_str(
+        &self,
+        sign: &LocationSign,
+        location_str: &str,
+    ) -> Option<Location>;
     fn get_fallback_location(&self, sign: &LocationSign) -> Option<Location>;
+    /// 使用 `location` 签到成功后调用，默认不做任何事。
+    fn on_location_success(&self, sign: &LocationSign, location: &Location) {
+        let _ = sign;
+        let _ = location;
+    }
     fn get_locations(&self, sign: &LocationSign, location_str: &Option<String>) -> Vec<Location> {
         let mut locations = Vec::new();
         // 该位置保证能够签到成功。
         let l2 = sign.get_preset_location();
         if let Some(location_str) = location_str {
             let location_str = location_str.trim();
-            let l1 = location_str.parse::<Location>().ok();
+            let l1 = location_str
+                .parse::<Location>()
+                .ok()
+                .or_else(|| self.get_location_by_location_str(sign, location_str));
             if let Some(l1) = l1 {
+                warn_if_out_of_range(sign, &l1);
                 locations.push(l1);
             } else if let Some(mut l2) = l2 {
                 l2.set_addr(location_str);
@@ -30,42 +45,118 @@ pub trait LocationInfoGetterTrait {
         }
         let l3 = self.get_fallback_location(sign);
         if let Some(l3) = l3 {
+            warn_if_out_of_range(sign, &l3);
             locations.push(l3);
         }
         locations
     }
 }
+/// 位置不在教师设置的签到范围内时给出警告，不影响签到。
+fn warn_if_out_of_range(sign: &LocationSign, location: &Location) {
+    if let Some(range) = sign.get_preset_range() {
+        if !range.contains(location) {
+            warn!(
+                "位置 `{location}` 距签到地点 `{}` 约 {:.0} 米，超出了 {} 米的签到范围。",
+                range.get_addr(),
+                range.distance_to(location),
+                range.get_range()
+            );
+        }
+    }
+}
 
-pub struct DefaultLocationInfoGetter<'a>(&'a DataBase);
-impl<'a> DefaultLocationInfoGetter<'a> {
-    pub fn new(db: &'a DataBase) -> Self {
+/// 从存储中获取位置，存储可以是任意实现了 [`LocationLibraryTrait`] 的后端。
+///
+/// 后备位置为该课程最近一次签到成功时使用的位置，签到成功后会记录所使用的位置。
+pub struct DefaultLocationInfoGetter<'a, S = DataBase>(&'a S);
+impl<'a, S> DefaultLocationInfoGetter<'a, S> {
+    pub fn new(db: &'a S) -> Self {
         Self(db)
     }
 }
-impl<'a> From<&'a DataBase> for DefaultLocationInfoGetter<'a> {
-    fn from(db: &'a DataBase) -> Self {
+impl<'a, S> From<&'a S> for DefaultLocationInfoGetter<'a, S> {
+    fn from(db: &'a S) -> Self {
         Self::new(db)
     }
 }
 
-impl LocationInfoGetterTrait for DefaultLocationInfoGetter<'_> {
-    fn get_location_by_location_str(&self, trimmed_location_str: &str) -> Option<Location> {
-        LocationTable::get_location_by_alias(self.0, trimmed_location_str).or_else(|| {
-            trimmed_location_str
-                .parse()
-                .map(|location_id| {
-                    if LocationTable::has_location(self.0, location_id) {
-                        let (_, location) = LocationTable::get_location(self.0, location_id);
-                        Some(location)
-                    } else {
-                        None
-                    }
-                })
-                .ok()
-                .flatten()
-        })
+impl<S: LocationLibraryTrait> LocationInfoGetterTrait for DefaultLocationInfoGetter<'_, S> {
+    fn get_location_by_location_str(
+        &self,
+        sign: &LocationSign,
+        trimmed_location_str: &str,
+    ) -> Option<Location> {
+        self.0
+            .resolve_alias(trimmed_location_str, Some(sign.as_inner().course.get_id()))
+            .or_else(|| trimmed_location_str.parse().ok())
+            .and_then(|location_id| self.0.get_location(location_id))
+            .map(|(_, location)| location)
     }
     fn get_fallback_location(&self, sign: &LocationSign) -> Option<Location> {
-        LocationTable::get_location_list_by_course(self.0, sign.as_inner().course.get_id()).pop()
+        self.0
+            .get_fallback_location_id(sign.as_inner().course.get_id())
+            .and_then(|location_id| self.0.get_location(location_id))
+            .map(|(_, location)| location)
+    }
+    fn on_location_success(&self, sign: &LocationSign, location: &Location) {
+        if let Some(location_id) = self
+            .0
+            .get_locations_by_course(sign.as_inner().course.get_id())
+            .into_iter()
+            .filter(|(_, l)| l == location)
+            .map(|(location_id, _)| location_id)
+            .min()
+        {
+            self.0.record_usage_now(location_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sign::LocationSign;
+    use crate::signner::{DefaultLocationInfoGetter, LocationInfoGetterTrait};
+    use crate::store::{AliasStoreTrait, LocationStoreTrait, MemoryStore};
+    use cxlib_activity::RawSign;
+    use cxlib_types::{Course, Location};
+
+    fn location_sign(course_id: i64) -> LocationSign {
+        LocationSign {
+            raw_sign: RawSign {
+                start_time_mills: 0,
+                active_id: String::new(),
+                name: String::new(),
+                course: Course::new(course_id, 0, "", "", ""),
+                other_id: String::new(),
+                status_code: 0,
+            },
+            preset_location: None,
+        }
+    }
+
+    #[test]
+    fn test_alias_resolved_by_course() {
+        let store = MemoryStore::new();
+        let a = Location::parse("教室A,116.0,40.0,50").unwrap();
+        let b = Location::parse("教室B,117.0,41.0,50").unwrap();
+        store.add_alias("教室", store.insert_location(1, &a).unwrap());
+        store.add_alias("教室", store.insert_location(2, &b).unwrap());
+        let getter = DefaultLocationInfoGetter::new(&store);
+        assert_eq!(
+            getter.get_location_by_location_str(&location_sign(1), "教室"),
+            Some(a.clone())
+        );
+        assert_eq!(
+            getter.get_location_by_location_str(&location_sign(2), "教室"),
+            Some(b.clone())
+        );
+        // 未给出坐标时按别名取位置，而非直接使用后备位置。
+        let location_str = Some("教室".to_owned());
+        assert_eq!(
+            getter
+                .get_locations(&location_sign(2), &location_str)
+                .first(),
+            Some(&b)
+        );
     }
 }
@@ -0,0 +1,273 @@
+use crate::store::{
+    AccountData, AccountStoreTrait, AliasStoreTrait, DataFormat, KVStoreTrait,
+    LocationLibraryTrait, LocationStoreTrait, LocationUsage, MemoryStore, StoreData,
+};
+use cxlib_activity::{CourseExcludeInfoTrait, ExclusionPolicy};
+use cxlib_error::StoreError;
+use cxlib_types::Location;
+use log::warn;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+/// # FileStore
+///
+/// 以单个 TOML 或 JSON 文件保存所有表的存储，数据格式见 [`StoreData`].
+///
+/// 数据在打开时全部读入内存，每次修改后写回文件。
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    format: DataFormat,
+    inner: MemoryStore,
+}
+impl FileStore {
+    /// 打开文件，扩展名为 `.json` 时使用 JSON, 否则使用 TOML. 文件不存在时视为空存储。
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let format = match DataFormat::from_path(path.as_ref()) {
+            Some(DataFormat::Json) => DataFormat::Json,
+            _ => DataFormat::Toml,
+        };
+        Self::open_with_format(format, path)
+    }
+    /// 以指定格式打开文件，不支持 [`DataFormat::Csv`].
+    pub fn open_with_format(
+        format: DataFormat,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, StoreError> {
+        let path = path.as_ref().to_path_buf();
+        let data = if path.exists() {
+            format.parse(&std::fs::read_to_string(&path)?)?
+        } else {
+            StoreData::default()
+        };
+        Ok(Self {
+            path,
+            format,
+            inner: MemoryStore::from_data(data),
+        })
+    }
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+    pub fn get_format(&self) -> DataFormat {
+        self.format
+    }
+    /// 将数据写回文件。
+    ///
+    /// 先写入同目录下的临时文件再重命名，写入中途出错时不会损坏原文件。
+    pub fn save(&self) -> Result<(), StoreError> {
+        let content = self.format.to_string(&self.inner.get_data())?;
+        let file_name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let temp = self.path.with_file_name(format!(".{file_name}.tmp"));
+        let result =
+            std::fs::write(&temp, content).and_then(|_| std::fs::rename(&temp, &self.path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        Ok(result?)
+    }
+    fn save_or_warn(&self) {
+        if let Err(e) = self.save() {
+            warn!("文件 `{}` 保存失败：{e}.", self.path.display());
+        }
+    }
+}
+impl AccountStoreTrait for FileStore {
+    fn has_account(&self, uid: &str) -> bool {
+        self.inner.has_account(uid)
+    }
+
+    fn get_account(&self, uid: &str) -> Option<AccountData> {
+        self.inner.get_account(uid)
+    }
+
+    fn get_accounts(&self) -> HashSet<AccountData> {
+        self.inner.get_accounts()
+    }
+
+    fn add_account(&self, account: &AccountData) {
+        self.inner.add_account(account);
+        self.save_or_warn();
+    }
+
+    fn delete_account(&self, uid: &str) {
+        self.inner.delete_account(uid);
+        self.save_or_warn();
+    }
+}
+impl AliasStoreTrait for FileStore {
+    fn get_location_ids(&self, alias: &str) -> Vec<i64> {
+        self.inner.get_location_ids(alias)
+    }
+
+    fn get_aliases(&self, location_id: i64) -> Vec<String> {
+        self.inner.get_aliases(location_id)
+    }
+
+    fn get_all_aliases(&self) -> Vec<(String, i64)> {
+        self.inner.get_all_aliases()
+    }
+
+    fn add_alias(&self, alias: &str, location_id: i64) {
+        self.inner.add_alias(alias, location_id);
+        self.save_or_warn();
+    }
+
+    fn remove_alias(&self, alias: &str, location_id: i64) {
+        self.inner.remove_alias(alias, location_id);
+        self.save_or_warn();
+    }
+
+    fn delete_alias(&self, alias: &str) {
+        self.inner.delete_alias(alias);
+        self.save_or_warn();
+    }
+}
+impl LocationStoreTrait for FileStore {
+    fn get_location(&self, location_id: i64) -> Option<(i64, Location)> {
+        self.inner.get_location(location_id)
+    }
+
+    fn get_locations(&self) -> HashMap<i64, (i64, Location)> {
+        self.inner.get_locations()
+    }
+
+    fn insert_location(&self, course_id: i64, location: &Location) -> Result<i64, StoreError> {
+        let lid = self.inner.insert_location(course_id, location)?;
+        self.save()?;
+        Ok(lid)
+    }
+
+    fn delete_location(&self, location_id: i64) {
+        self.inner.delete_location(location_id);
+        self.save_or_warn();
+    }
+}
+impl LocationLibraryTrait for FileStore {
+    fn get_tags(&self, location_id: i64) -> BTreeSet<String> {
+        self.inner.get_tags(location_id)
+    }
+
+    fn add_tag(&self, location_id: i64, tag: &str) {
+        self.inner.add_tag(location_id, tag);
+        self.save_or_warn();
+    }
+
+    fn remove_tag(&self, location_id: i64, tag: &str) {
+        self.inner.remove_tag(location_id, tag);
+        self.save_or_warn();
+    }
+
+    fn get_locations_by_tag(&self, tag: &str) -> BTreeSet<i64> {
+        self.inner.get_locations_by_tag(tag)
+    }
+
+    fn get_usage(&self, location_id: i64) -> Option<LocationUsage> {
+        self.inner.get_usage(location_id)
+    }
+
+    fn record_usage(&self, location_id: i64, time_mills: u64) {
+        self.inner.record_usage(location_id, time_mills);
+        self.save_or_warn();
+    }
+}
+impl KVStoreTrait for FileStore {
+    fn get_value(&self, key: &str) -> Option<String> {
+        self.inner.get_value(key)
+    }
+
+    fn set_value(&self, key: &str, value: &str) {
+        self.inner.set_value(key, value);
+        self.save_or_warn();
+    }
+
+    fn remove_value(&self, key: &str) {
+        self.inner.remove_value(key);
+        self.save_or_warn();
+    }
+
+    fn get_values(&self) -> HashMap<String, String> {
+        self.inner.get_values()
+    }
+}
+impl CourseExcludeInfoTrait for FileStore {
+    fn get_exclusion_policy(&self) -> ExclusionPolicy {
+        self.inner.get_exclusion_policy()
+    }
+
+    fn is_excluded(&self, id: i64) -> bool {
+        self.inner.is_excluded(id)
+    }
+
+    fn get_excludes(&self) -> HashSet<i64> {
+        self.inner.get_excludes()
+    }
+
+    fn exclude(&self, id: i64) {
+        self.inner.exclude(id);
+        self.save_or_warn();
+    }
+
+    fn cancel_exclude(&self, id: i64) {
+        self.inner.cancel_exclude(id);
+        self.save_or_warn();
+    }
+
+    fn update_excludes<'a, I: IntoIterator<Item = &'a i64>>(&self, excludes: I) {
+        self.inner.update_excludes(excludes);
+        self.save_or_warn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{
+        AliasStoreTrait, ExcludeTable, FileStore, KVStoreTrait, LocationLibraryTrait,
+        LocationStoreTrait,
+    };
+    use cxlib_activity::CourseExcludeInfoTrait;
+    use cxlib_types::Location;
+
+    #[test]
+    fn test_file_store_round_trip() {
+        for ext in ["toml", "json"] {
+            let path = std::env::temp_dir().join(format!(
+                "cxlib_file_store_test_{}.{ext}",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            {
+                let store = FileStore::open(&path).unwrap();
+                let lid = store
+                    .insert_location(1, &Location::get_none_location())
+                    .unwrap();
+                store.add_alias("教室", lid);
+                store.add_tag(lid, "室内");
+                store.record_usage(lid, 100);
+                store.set_value("k", "v");
+                store.exclude(42);
+                store.set_value(ExcludeTable::PINNED_KEY, "7,8");
+            }
+            let temp = path.with_file_name(format!(
+                ".{}.tmp",
+                path.file_name().unwrap().to_string_lossy()
+            ));
+            assert!(!temp.exists());
+            let store = FileStore::open(&path).unwrap();
+            assert_eq!(store.get_location_id("教室"), Some(0));
+            assert!(store.has_location(0));
+            assert!(store.get_tags(0).contains("室内"));
+            assert_eq!(store.get_usage(0).map(|u| u.last_used_mills), Some(100));
+            assert_eq!(store.get_value("k").as_deref(), Some("v"));
+            assert!(store.is_excluded(42));
+            assert!(store.get_exclusion_policy().pinned.contains(&7));
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+}
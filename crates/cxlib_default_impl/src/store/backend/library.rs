@@ -0,0 +1,179 @@
+use crate::store::{AliasStoreTrait, LocationStoreTrait};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, collections::BTreeSet, time::SystemTime};
+
+/// 位置的使用记录，只统计签到成功的使用。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LocationUsage {
+    /// 成功使用的次数。
+    pub count: u64,
+    /// 最近一次成功使用的时间（毫秒时间戳）。
+    pub last_used_mills: u64,
+}
+impl LocationUsage {
+    /// 在此基础上记录一次使用。
+    pub fn record(self, time_mills: u64) -> Self {
+        Self {
+            count: self.count + 1,
+            last_used_mills: self.last_used_mills.max(time_mills),
+        }
+    }
+}
+
+/// # LocationLibraryTrait
+///
+/// 位置库特型，在位置及别名之上提供标签、使用记录、别名搜索，以及按课程和使用记录选择位置。
+pub trait LocationLibraryTrait: LocationStoreTrait + AliasStoreTrait {
+    /// 某位置的所有标签。
+    fn get_tags(&self, location_id: i64) -> BTreeSet<String>;
+    fn add_tag(&self, location_id: i64, tag: &str);
+    fn remove_tag(&self, location_id: i64, tag: &str);
+    /// 带有某标签的所有位置 ID.
+    fn get_locations_by_tag(&self, tag: &str) -> BTreeSet<i64>;
+    /// 位置的使用记录，从未成功使用过时为 `None`.
+    fn get_usage(&self, location_id: i64) -> Option<LocationUsage>;
+    /// 记录一次成功的使用。
+    fn record_usage(&self, location_id: i64, time_mills: u64);
+    /// 以当前时间记录一次成功的使用。
+    fn record_usage_now(&self, location_id: i64) {
+        self.record_usage(location_id, now_mills())
+    }
+    /// 模糊搜索别名，按匹配程度排序，返回别名及其对应的位置 ID.
+    ///
+    /// 匹配规则见 [`fuzzy_match_score`].
+    fn search_aliases(&self, query: &str) -> Vec<(String, i64)> {
+        let mut matched = self
+            .get_all_aliases()
+            .into_iter()
+            .filter_map(|(alias, lid)| {
+                fuzzy_match_score(query, &alias).map(|score| (score, alias, lid))
+            })
+            .collect::<Vec<_>>();
+        matched.sort();
+        matched
+            .into_iter()
+            .map(|(_, alias, lid)| (alias, lid))
+            .collect()
+    }
+    /// 选择别名对应的位置。
+    ///
+    /// 依次优先选择：属于 `course_id` 课程的位置、最近成功使用过的位置、ID 最小的位置。
+    fn resolve_alias(&self, alias: &str, course_id: Option<i64>) -> Option<i64> {
+        self.get_location_ids(alias).into_iter().max_by_key(|lid| {
+            let in_course = course_id.is_some_and(|course_id| {
+                self.get_location(*lid)
+                    .is_some_and(|(course, _)| course == course_id)
+            });
+            let last_used = self.get_usage(*lid).map(|u| u.last_used_mills);
+            (in_course, last_used, Reverse(*lid))
+        })
+    }
+    /// 某课程的后备位置：最近一次成功使用的位置。均未使用过时为最后添加（ID 最大）的位置。
+    fn get_fallback_location_id(&self, course_id: i64) -> Option<i64> {
+        self.get_locations_by_course(course_id)
+            .into_keys()
+            .max_by_key(|lid| (self.get_usage(*lid).map(|u| u.last_used_mills), *lid))
+    }
+}
+
+/// 模糊匹配，不区分大小写。不匹配时返回 `None`, 否则分数越小越匹配：
+///
+/// - `0`: 完全相同；
+/// - `1`: 以查询内容开头；
+/// - `2`: 包含查询内容；
+/// - `3` 及以上：按顺序包含查询内容的每个字符，分数随中间间隔的字符数增加。
+///
+/// 查询内容为空时匹配所有别名。
+pub fn fuzzy_match_score(query: &str, candidate: &str) -> Option<u32> {
+    let query = query.trim().to_lowercase();
+    let candidate = candidate.trim().to_lowercase();
+    if query.is_empty() || query == candidate {
+        return Some(0);
+    }
+    if candidate.starts_with(&query) {
+        return Some(1);
+    }
+    if candidate.contains(&query) {
+        return Some(2);
+    }
+    let mut query_chars = query.chars().peekable();
+    let mut gaps = 0;
+    let mut started = false;
+    for c in candidate.chars() {
+        match query_chars.peek() {
+            Some(q) if *q == c => {
+                query_chars.next();
+                started = true;
+            }
+            Some(_) if started => gaps += 1,
+            Some(_) => (),
+            None => break,
+        }
+    }
+    if query_chars.peek().is_none() {
+        Some(3 + gaps)
+    } else {
+        None
+    }
+}
+fn now_mills() -> u64 {
+    SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{
+        fuzzy_match_score, AliasStoreTrait, LocationLibraryTrait, LocationStoreTrait, MemoryStore,
+    };
+    use cxlib_types::Location;
+
+    #[test]
+    fn test_fuzzy_match_score() {
+        assert_eq!(fuzzy_match_score("Lab", "lab"), Some(0));
+        assert_eq!(fuzzy_match_score("教学", "教学楼"), Some(1));
+        assert_eq!(fuzzy_match_score("学楼", "教学楼"), Some(2));
+        assert_eq!(fuzzy_match_score("一学", "第一教学楼"), Some(4));
+        assert_eq!(fuzzy_match_score("一楼", "第一教学楼"), Some(5));
+        assert_eq!(fuzzy_match_score("二楼", "第一教学楼"), None);
+    }
+
+    #[test]
+    fn test_location_library() {
+        let store = MemoryStore::new();
+        let none = Location::get_none_location();
+        let a = store.insert_location(1, &none).unwrap();
+        let b = store.insert_location(2, &none).unwrap();
+        let c = store.insert_location(2, &none).unwrap();
+        store.add_alias("教室", a);
+        store.add_alias("教室", b);
+        store.add_alias("操场", c);
+        // 后备位置：均未使用时为最后添加的位置，否则为最近成功使用的位置。
+        assert_eq!(store.get_fallback_location_id(2), Some(c));
+        assert_eq!(store.get_location_ids("教室"), vec![a, b]);
+        assert_eq!(store.resolve_alias("教室", Some(2)), Some(b));
+        assert_eq!(store.resolve_alias("教室", None), Some(a));
+        store.record_usage(b, 100);
+        assert_eq!(store.resolve_alias("教室", None), Some(b));
+        assert_eq!(
+            store.search_aliases("教"),
+            vec![("教室".to_owned(), a), ("教室".to_owned(), b)]
+        );
+
+        store.record_usage(b, 200);
+        assert_eq!(store.get_fallback_location_id(2), Some(b));
+        assert_eq!(store.get_usage(b).map(|u| u.count), Some(2));
+
+        store.add_tag(a, "室内");
+        store.add_tag(b, "室内");
+        assert_eq!(store.get_locations_by_tag("室内").len(), 2);
+        store.remove_alias("教室", a);
+        assert_eq!(store.get_location_ids("教室"), vec![b]);
+        store.delete_location(b);
+        assert!(!store.has_alias("教室"));
+        assert_eq!(store.get_locations_by_tag("室内").len(), 1);
+        assert_eq!(store.get_usage(b), None);
+    }
+}
@@ -0,0 +1,360 @@
+use crate::store::{
+    AccountData, AccountStoreTrait, AliasStoreTrait, ExcludeTable, KVStoreTrait,
+    LocationLibraryTrait, LocationStoreTrait, LocationUsage,
+};
+use cxlib_activity::{CourseExcludeInfoTrait, ExclusionPolicy};
+use cxlib_error::StoreError;
+use cxlib_types::Location;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    convert::Infallible,
+    sync::{Mutex, MutexGuard},
+};
+
+/// 位置表中的一行。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocationRecord {
+    pub lid: i64,
+    pub course: i64,
+    pub location: Location,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<LocationUsage>,
+}
+/// 所有表的数据，[`MemoryStore`] 与 [`FileStore`](crate::store::FileStore) 均以此格式保存。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreData {
+    #[serde(default)]
+    pub accounts: Vec<AccountData>,
+    #[serde(default)]
+    pub locations: Vec<LocationRecord>,
+    /// alias, location_ids
+    #[serde(default, deserialize_with = "deserialize_aliases")]
+    pub aliases: BTreeMap<String, BTreeSet<i64>>,
+    #[serde(default)]
+    pub excludes: BTreeSet<i64>,
+    #[serde(default)]
+    pub kv: BTreeMap<String, String>,
+}
+/// 兼容旧格式中每个别名只对应一个位置 ID 的情况。
+fn deserialize_aliases<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, BTreeSet<i64>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum AliasTargets {
+        One(i64),
+        Many(BTreeSet<i64>),
+    }
+    Ok(BTreeMap::<String, AliasTargets>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(alias, targets)| match targets {
+            AliasTargets::One(lid) => (alias, BTreeSet::from([lid])),
+            AliasTargets::Many(lids) => (alias, lids),
+        })
+        .collect())
+}
+
+/// # MemoryStore
+///
+/// 内存存储，数据不会被持久化，适用于测试。
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    data: Mutex<StoreData>,
+}
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn from_data(data: StoreData) -> Self {
+        Self {
+            data: Mutex::new(data),
+        }
+    }
+    /// 获取所有数据的副本。
+    pub fn get_data(&self) -> StoreData {
+        self.lock().clone()
+    }
+    fn lock(&self) -> MutexGuard<'_, StoreData> {
+        self.data.lock().unwrap()
+    }
+}
+impl AccountStoreTrait for MemoryStore {
+    fn has_account(&self, uid: &str) -> bool {
+        self.lock().accounts.iter().any(|a| a.uid() == uid)
+    }
+
+    fn get_account(&self, uid: &str) -> Option<AccountData> {
+        self.lock()
+            .accounts
+            .iter()
+            .find(|a| a.uid() == uid)
+            .cloned()
+    }
+
+    fn get_accounts(&self) -> HashSet<AccountData> {
+        self.lock().accounts.iter().cloned().collect()
+    }
+
+    fn add_account(&self, account: &AccountData) {
+        let mut data = self.lock();
+        match data.accounts.iter_mut().find(|a| a.uid() == account.uid()) {
+            Some(a) => *a = account.clone(),
+            None => data.accounts.push(account.clone()),
+        }
+    }
+
+    fn delete_account(&self, uid: &str) {
+        self.lock().accounts.retain(|a| a.uid() != uid);
+    }
+}
+impl AliasStoreTrait for MemoryStore {
+    fn get_location_ids(&self, alias: &str) -> Vec<i64> {
+        self.lock()
+            .aliases
+            .get(alias)
+            .map(|lids| lids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn get_aliases(&self, location_id: i64) -> Vec<String> {
+        self.lock()
+            .aliases
+            .iter()
+            .filter(|(_, lids)| lids.contains(&location_id))
+            .map(|(alias, _)| alias.clone())
+            .collect()
+    }
+
+    fn get_all_aliases(&self) -> Vec<(String, i64)> {
+        self.lock()
+            .aliases
+            .iter()
+            .flat_map(|(alias, lids)| lids.iter().map(move |lid| (alias.clone(), *lid)))
+            .collect()
+    }
+
+    fn add_alias(&self, alias: &str, location_id: i64) {
+        self.lock()
+            .aliases
+            .entry(alias.to_owned())
+            .or_default()
+            .insert(location_id);
+    }
+
+    fn remove_alias(&self, alias: &str, location_id: i64) {
+        let mut data = self.lock();
+        if let Some(lids) = data.aliases.get_mut(alias) {
+            lids.remove(&location_id);
+            if lids.is_empty() {
+                data.aliases.remove(alias);
+            }
+        }
+    }
+
+    fn delete_alias(&self, alias: &str) {
+        self.lock().aliases.remove(alias);
+    }
+}
+impl LocationStoreTrait for MemoryStore {
+    fn get_location(&self, location_id: i64) -> Option<(i64, Location)> {
+        self.lock()
+            .locations
+            .iter()
+            .find(|r| r.lid == location_id)
+            .map(|r| (r.course, r.location.clone()))
+    }
+
+    fn get_locations(&self) -> HashMap<i64, (i64, Location)> {
+        self.lock()
+            .locations
+            .iter()
+            .map(|r| (r.lid, (r.course, r.location.clone())))
+            .collect()
+    }
+
+    /// 与 [`DataBase`](crate::store::DataBase) 相同，分配最小的未被使用的非负 ID.
+    fn insert_location(&self, course_id: i64, location: &Location) -> Result<i64, StoreError> {
+        let mut data = self.lock();
+        let used = data.locations.iter().map(|r| r.lid).collect::<HashSet<_>>();
+        let lid = (0..).find(|lid| !used.contains(lid)).unwrap_or_default();
+        data.locations.push(LocationRecord {
+            lid,
+            course: course_id,
+            location: location.clone(),
+            tags: BTreeSet::new(),
+            usage: None,
+        });
+        Ok(lid)
+    }
+
+    fn delete_location(&self, location_id: i64) {
+        let mut data = self.lock();
+        data.locations.retain(|r| r.lid != location_id);
+        data.aliases.retain(|_, lids| {
+            lids.remove(&location_id);
+            !lids.is_empty()
+        });
+    }
+}
+impl LocationLibraryTrait for MemoryStore {
+    fn get_tags(&self, location_id: i64) -> BTreeSet<String> {
+        self.lock()
+            .locations
+            .iter()
+            .find(|r| r.lid == location_id)
+            .map(|r| r.tags.clone())
+            .unwrap_or_default()
+    }
+
+    fn add_tag(&self, location_id: i64, tag: &str) {
+        if let Some(r) = self
+            .lock()
+            .locations
+            .iter_mut()
+            .find(|r| r.lid == location_id)
+        {
+            r.tags.insert(tag.to_owned());
+        }
+    }
+
+    fn remove_tag(&self, location_id: i64, tag: &str) {
+        if let Some(r) = self
+            .lock()
+            .locations
+            .iter_mut()
+            .find(|r| r.lid == location_id)
+        {
+            r.tags.remove(tag);
+        }
+    }
+
+    fn get_locations_by_tag(&self, tag: &str) -> BTreeSet<i64> {
+        self.lock()
+            .locations
+            .iter()
+            .filter(|r| r.tags.contains(tag))
+            .map(|r| r.lid)
+            .collect()
+    }
+
+    fn get_usage(&self, location_id: i64) -> Option<LocationUsage> {
+        self.lock()
+            .locations
+            .iter()
+            .find(|r| r.lid == location_id)
+            .and_then(|r| r.usage)
+    }
+
+    fn record_usage(&self, location_id: i64, time_mills: u64) {
+        if let Some(r) = self
+            .lock()
+            .locations
+            .iter_mut()
+            .find(|r| r.lid == location_id)
+        {
+            r.usage = Some(r.usage.unwrap_or_default().record(time_mills));
+        }
+    }
+}
+impl KVStoreTrait for MemoryStore {
+    fn get_value(&self, key: &str) -> Option<String> {
+        self.lock().kv.get(key).cloned()
+    }
+
+    fn set_value(&self, key: &str, value: &str) {
+        self.lock().kv.insert(key.to_owned(), value.to_owned());
+    }
+
+    fn remove_value(&self, key: &str) {
+        self.lock().kv.remove(key);
+    }
+
+    fn get_values(&self) -> HashMap<String, String> {
+        self.lock().kv.clone().into_iter().collect()
+    }
+}
+impl CourseExcludeInfoTrait for MemoryStore {
+    /// 与 [`DataBase`](crate::store::DataBase) 相同，排除策略保存在键值对中，参见 [`ExcludeTable`].
+    fn get_exclusion_policy(&self) -> ExclusionPolicy {
+        ExcludeTable::read_exclusion_policy(|key| Ok::<_, Infallible>(self.get_value(key)))
+            .unwrap_or_else(|e| match e {})
+    }
+
+    fn is_excluded(&self, id: i64) -> bool {
+        self.lock().excludes.contains(&id)
+    }
+
+    fn get_excludes(&self) -> HashSet<i64> {
+        self.lock().excludes.iter().copied().collect()
+    }
+
+    fn exclude(&self, id: i64) {
+        self.lock().excludes.insert(id);
+    }
+
+    fn cancel_exclude(&self, id: i64) {
+        self.lock().excludes.remove(&id);
+    }
+
+    fn update_excludes<'a, I: IntoIterator<Item = &'a i64>>(&self, excludes: I) {
+        let mut data = self.lock();
+        data.excludes.clear();
+        data.excludes.extend(excludes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{
+        AccountData, AccountStoreTrait, AliasStoreTrait, KVStoreTrait, LocationStoreTrait,
+        MemoryStore, StoreData,
+    };
+    use cxlib_types::Location;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_old_aliases_format() {
+        let data: StoreData = ureq::serde_json::from_str(r#"{"aliases":{"教室":1}}"#).unwrap();
+        assert_eq!(data.aliases["教室"], BTreeSet::from([1]));
+    }
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryStore::new();
+        let account = AccountData::new(
+            "1".to_owned(),
+            "uname".to_owned(),
+            "enc".to_owned(),
+            String::new(),
+        );
+        store.add_account(&account);
+        store.add_account(&account);
+        assert_eq!(store.get_accounts().len(), 1);
+        let l0 = store
+            .insert_location(1, &Location::get_none_location())
+            .unwrap();
+        let l1 = store
+            .insert_location(2, &Location::get_none_location())
+            .unwrap();
+        assert_eq!((l0, l1), (0, 1));
+        store.add_alias("教室", l1);
+        assert_eq!(store.get_location_id("教室"), Some(1));
+        store.add_alias("教室", l0);
+        assert_eq!(store.get_location_ids("教室"), vec![0, 1]);
+        assert_eq!(store.get_locations_by_course(2).len(), 1);
+        store.delete_location(l0);
+        assert_eq!(
+            store
+                .insert_location(3, &Location::get_none_location())
+                .unwrap(),
+            0
+        );
+        store.delete_location(l1);
+        assert!(!store.has_alias("教室"));
+        store.set_value("k", "v");
+        assert_eq!(store.get_value("k").as_deref(), Some("v"));
+    }
+}
@@ -0,0 +1,91 @@
+//! 与存储后端无关的表接口。
+//!
+//! 各表的操作以特型的形式定义，目前有以下实现：
+//!
+//! - [`DataBase`](crate::store::DataBase): SQLite 数据库，即默认实现；
+//! - [`MemoryStore`]: 内存存储，不会持久化，适用于测试；
+//! - [`FileStore`]: TOML 或 JSON 文件，便于手动编辑。
+//!
+//! 排除列表使用 [`CourseExcludeInfoTrait`](cxlib_activity::CourseExcludeInfoTrait),
+//! 位置库使用 [`LocationLibraryTrait`], 以上后端均已实现。
+mod file;
+mod library;
+mod memory;
+
+pub use file::*;
+pub use library::*;
+pub use memory::*;
+
+use crate::store::AccountData;
+use cxlib_error::StoreError;
+use cxlib_types::Location;
+use std::collections::{HashMap, HashSet};
+
+/// # AccountStoreTrait
+/// 账号表特型。
+pub trait AccountStoreTrait {
+    fn has_account(&self, uid: &str) -> bool;
+    fn get_account(&self, uid: &str) -> Option<AccountData>;
+    fn get_accounts(&self) -> HashSet<AccountData>;
+    /// 添加账号，已有同一 uid 的账号时覆盖。
+    fn add_account(&self, account: &AccountData);
+    fn delete_account(&self, uid: &str);
+}
+/// # AliasStoreTrait
+/// 位置别名表特型。
+///
+/// 别名与位置为多对多关系：同一别名可以对应多个位置（如不同课程中的同名教室），一个位置也可以有多个别名。
+/// 按课程选择别名对应的位置请使用 [`LocationLibraryTrait::resolve_alias`].
+pub trait AliasStoreTrait {
+    fn has_alias(&self, alias: &str) -> bool {
+        !self.get_location_ids(alias).is_empty()
+    }
+    /// 别名对应的所有位置 ID, 按升序排列。
+    fn get_location_ids(&self, alias: &str) -> Vec<i64>;
+    /// 别名对应的位置 ID, 有多个时返回最小者。
+    fn get_location_id(&self, alias: &str) -> Option<i64> {
+        self.get_location_ids(alias).into_iter().min()
+    }
+    /// 某位置的所有别名。
+    fn get_aliases(&self, location_id: i64) -> Vec<String>;
+    /// 所有别名及其对应的位置 ID.
+    fn get_all_aliases(&self) -> Vec<(String, i64)>;
+    /// 为位置添加别名，别名对应的其他位置不受影响。
+    fn add_alias(&self, alias: &str, location_id: i64);
+    /// 仅删除别名与某位置的对应关系。
+    fn remove_alias(&self, alias: &str, location_id: i64);
+    /// 删除别名及其与所有位置的对应关系。
+    fn delete_alias(&self, alias: &str);
+}
+/// # LocationStoreTrait
+/// 位置表特型。位置以位置 ID 标识，并属于某课程。
+pub trait LocationStoreTrait {
+    fn has_location(&self, location_id: i64) -> bool {
+        self.get_location(location_id).is_some()
+    }
+    /// 课程号及位置。
+    fn get_location(&self, location_id: i64) -> Option<(i64, Location)>;
+    /// location_id, (course_id, location)
+    fn get_locations(&self) -> HashMap<i64, (i64, Location)>;
+    /// 某课程的所有位置，location_id, location
+    fn get_locations_by_course(&self, course_id: i64) -> HashMap<i64, Location> {
+        self.get_locations()
+            .into_iter()
+            .filter(|(_, (c, _))| *c == course_id)
+            .map(|(id, (_, l))| (id, l))
+            .collect()
+    }
+    /// 添加位置，返回新分配的位置 ID.
+    fn insert_location(&self, course_id: i64, location: &Location) -> Result<i64, StoreError>;
+    /// 删除位置及其所有别名、标签及使用记录。
+    fn delete_location(&self, location_id: i64);
+}
+/// # KVStoreTrait
+/// 键值配置表特型。
+pub trait KVStoreTrait {
+    fn get_value(&self, key: &str) -> Option<String>;
+    /// 设置值，已有该键时覆盖。
+    fn set_value(&self, key: &str, value: &str);
+    fn remove_value(&self, key: &str);
+    fn get_values(&self) -> HashMap<String, String>;
+}
@@ -1,54 +1,92 @@
+mod backend;
+mod pool;
 mod table;
 
+pub use backend::*;
 pub use cxlib_error::StoreError;
+pub use pool::*;
 pub use table::*;
 
-use cxlib_activity::CourseExcludeInfoTrait;
-use cxlib_store::{Dir, StorageTableCommandTrait, StorageTrait};
-use log::info;
-use sqlite::Connection;
-use std::{collections::HashSet, fs::File, ops::Deref};
+use cxlib_activity::{ActivitySnapshotTrait, CourseExcludeInfoTrait, ExclusionPolicy};
+use cxlib_error::{log_default, BundleError, ConfigError, CxlibResultUtils};
+use cxlib_store::{
+    BundleManifest, Config, ConfigLayerTrait, Dir, EnvConfigLayer, FileConfigLayer,
+    StorageTableCommandTrait, StorageTrait,
+};
+use cxlib_types::{Course, CourseCacheTrait, Location, PhotoCacheTrait};
+use log::{info, warn};
+use sqlite::{Bindable, Connection, OpenFlags, State, Statement};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeSet, HashMap, HashSet},
+    io::Write,
+    ops::{Deref, DerefMut},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub trait DataBaseTableTrait: StorageTableCommandTrait<DataBase> {
     const TABLE_ARGS: &'static str;
     const TABLE_NAME: &'static str;
-    fn init(db: &DataBase) {
-        if !Self::is_existed(db) {
+    fn init(db: &DataBase) -> Result<(), StoreError> {
+        if Self::is_existed(db)? {
+            Self::migrate(db)?;
+        } else {
             db.execute(format!(
                 "CREATE TABLE {} ({});",
                 Self::TABLE_NAME,
                 Self::TABLE_ARGS
-            ))
-            .unwrap();
+            ))?;
         }
+        Ok(())
     }
-    fn is_existed(db: &DataBase) -> bool {
-        let mut query = db
-            .prepare(format!(
-                "SELECT count(*) FROM sqlite_master WHERE type='table' AND name='{}';",
-                Self::TABLE_NAME
-            ))
-            .unwrap();
-        query.next().unwrap();
-        query.read::<i64, _>(0).unwrap() == 1
-    }
-    fn delete(db: &DataBase) {
-        let mut query = db
-            .prepare(format!("DELETE FROM {};", Self::TABLE_NAME))
-            .unwrap();
-        query.next().unwrap();
+    /// 数据表已存在时由 [`init`](DataBaseTableTrait::init) 调用，用于将旧版本的表结构升级为 [`TABLE_ARGS`](DataBaseTableTrait::TABLE_ARGS).
+    ///
+    /// 默认不做任何事。
+    fn migrate(db: &DataBase) -> Result<(), StoreError> {
+        let _ = db;
+        Ok(())
+    }
+    fn is_existed(db: &DataBase) -> Result<bool, StoreError> {
+        Ok(db.query_count(
+            "SELECT count(*) FROM sqlite_master WHERE type='table' AND name=?;",
+            (1, Self::TABLE_NAME),
+        )? == 1)
+    }
+    fn delete(db: &DataBase) -> Result<(), StoreError> {
+        db.execute(format!("DELETE FROM {};", Self::TABLE_NAME))?;
         info!("已删除数据表 {}。", Self::TABLE_NAME);
+        Ok(())
     }
+    /// 以行格式导入，默认不支持。
     fn import(db: &DataBase, content: &str) {
-        <Self as StorageTableCommandTrait<DataBase>>::import(db, content);
+        let _ = db;
+        let _ = content;
+        warn!("数据表 {} 不支持导入。", Self::TABLE_NAME);
     }
+    /// 以行格式导出，默认不支持。
     fn export(db: &DataBase) -> String {
-        <Self as StorageTableCommandTrait<DataBase>>::export(db)
+        let _ = db;
+        warn!("数据表 {} 不支持导出。", Self::TABLE_NAME);
+        String::new()
     }
 }
+/// # DataBase
+///
+/// SQLite 数据库连接。
+///
+/// 以 WAL 模式打开，并设置了忙等待超时，因此多个进程（如守护进程与命令行）可以同时访问同一数据库文件。
+///
+/// 单个连接不能在多个线程间共享，多线程时请使用 [`DataBasePool`], 为每个线程取得各自的连接。
 pub struct DataBase {
+    // 缓存的语句引用了连接，须在连接之前释放，故声明在前。
+    statements: RefCell<HashMap<String, Statement<'static>>>,
+    transaction_depth: Cell<usize>,
     connection: Connection,
 }
+// 连接以 `SQLITE_OPEN_FULLMUTEX` 打开，且未设置忙等待回调，可以在线程间转移。
+// 缓存的语句只属于该连接，随连接一同转移。
+unsafe impl Send for DataBase {}
 impl StorageTrait for DataBase {}
 impl Deref for DataBase {
     type Target = Connection;
@@ -57,19 +95,204 @@ impl Deref for DataBase {
         &self.connection
     }
 }
+/// 缓存的预编译语句，释放时重置并放回缓存。
+///
+/// 参见 [`DataBase::prepare_cached`].
+pub struct CachedStatement<'a> {
+    db: &'a DataBase,
+    sql: String,
+    statement: Option<Statement<'a>>,
+}
+impl<'a> Deref for CachedStatement<'a> {
+    type Target = Statement<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        self.statement.as_ref().unwrap()
+    }
+}
+impl DerefMut for CachedStatement<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.statement.as_mut().unwrap()
+    }
+}
+impl Drop for CachedStatement<'_> {
+    fn drop(&mut self) {
+        if let Some(mut statement) = self.statement.take() {
+            if statement.reset().is_ok() {
+                // SAFETY: 语句只会放回其所属连接的缓存中，且缓存先于连接释放。
+                let statement =
+                    unsafe { std::mem::transmute::<Statement<'_>, Statement<'static>>(statement) };
+                self.db
+                    .statements
+                    .borrow_mut()
+                    .entry(std::mem::take(&mut self.sql))
+                    .or_insert(statement);
+            }
+        }
+    }
+}
 // self
 impl DataBase {
+    /// 忙等待超时，单位为毫秒。
+    pub const BUSY_TIMEOUT_MS: usize = 5000;
+    fn from_connection(mut connection: Connection) -> Result<Self, StoreError> {
+        connection.set_busy_timeout(Self::BUSY_TIMEOUT_MS)?;
+        connection.execute("PRAGMA journal_mode=WAL;PRAGMA synchronous=NORMAL;")?;
+        Ok(Self {
+            statements: RefCell::new(HashMap::new()),
+            transaction_depth: Cell::new(0),
+            connection,
+        })
+    }
+    /// 所有连接均以 `SQLITE_OPEN_FULLMUTEX` 打开，参见 [`DataBase`] 的 [`Send`] 实现。
+    fn open_flags() -> OpenFlags {
+        OpenFlags::new()
+            .with_create()
+            .with_read_write()
+            .with_full_mutex()
+    }
+    /// 打开指定路径的数据库，文件不存在时将被创建。
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_with_flags(path, Self::open_flags())?)
+    }
+    /// 打开当前配置目录中的数据库，出错时 panic. 请优先使用 [`DataBase::try_new`].
     pub fn new() -> Self {
-        let db_dir = Dir::get_database_dir();
-        if db_dir.metadata().is_err() {
-            File::create(db_dir.clone()).unwrap();
-        }
-        let connection = Connection::open(db_dir.to_str().unwrap()).unwrap();
-        Self { connection }
+        Self::try_new().log_unwrap()
     }
-    pub fn add_table<T: DataBaseTableTrait>(&self) {
+    /// 打开当前配置目录中的数据库。
+    pub fn try_new() -> Result<Self, StoreError> {
+        Self::open(&Dir::get_database_dir())
+    }
+    /// 打开内存数据库，数据不会被持久化。
+    pub fn in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_with_flags(":memory:", Self::open_flags())?)
+    }
+    pub fn add_table<T: DataBaseTableTrait>(&self) -> Result<(), StoreError> {
         <T as DataBaseTableTrait>::init(self)
     }
+    /// 获取预编译语句，相同的 SQL 语句只编译一次。
+    pub fn prepare_cached(&self, sql: &str) -> Result<CachedStatement<'_>, StoreError> {
+        let cached = self.statements.borrow_mut().remove(sql);
+        let statement = match cached {
+            Some(statement) => statement,
+            None => self.connection.prepare(sql)?,
+        };
+        Ok(CachedStatement {
+            db: self,
+            sql: sql.to_owned(),
+            statement: Some(statement),
+        })
+    }
+    /// 执行带参数的语句，不读取结果。
+    pub fn execute_with<B: Bindable>(&self, sql: &str, bindings: B) -> Result<(), StoreError> {
+        let mut query = self.prepare_cached(sql)?;
+        query.bind(bindings)?;
+        while query.next()? != State::Done {}
+        Ok(())
+    }
+    /// 执行形如 `SELECT count(*) ...` 的查询。
+    pub fn query_count<B: Bindable>(&self, sql: &str, bindings: B) -> Result<i64, StoreError> {
+        let mut query = self.prepare_cached(sql)?;
+        query.bind(bindings)?;
+        query.next()?;
+        Ok(query.read::<i64, _>(0)?)
+    }
+    /// 在事务中执行 `f`, 返回 `Ok` 时提交，返回 `Err` 时回滚。
+    ///
+    /// 可以嵌套调用，内层事务以保存点实现，回滚时只撤销内层的修改。
+    /// 最外层事务以 `BEGIN IMMEDIATE` 开始，以免与其他连接的写入冲突时无法提交。
+    pub fn transaction<T, F: FnOnce(&DataBase) -> Result<T, StoreError>>(
+        &self,
+        f: F,
+    ) -> Result<T, StoreError> {
+        let depth = self.transaction_depth.get();
+        let (begin, commit, rollback) = if depth == 0 {
+            (
+                "BEGIN IMMEDIATE;".to_owned(),
+                "COMMIT;".to_owned(),
+                "ROLLBACK;".to_owned(),
+            )
+        } else {
+            (
+                format!("SAVEPOINT sp{depth};"),
+                format!("RELEASE sp{depth};"),
+                format!("ROLLBACK TO sp{depth};RELEASE sp{depth};"),
+            )
+        };
+        self.execute(begin)?;
+        self.transaction_depth.set(depth + 1);
+        let result = f(self);
+        self.transaction_depth.set(depth);
+        let result = result.and_then(|r| {
+            self.execute(&commit)?;
+            Ok(r)
+        });
+        if result.is_err() {
+            if let Err(e) = self.execute(rollback) {
+                warn!("事务回滚失败：{e}.");
+            }
+        }
+        result
+    }
+    /// 是否处于事务中。
+    pub fn in_transaction(&self) -> bool {
+        self.transaction_depth.get() > 0
+    }
+}
+/// 是否为违反约束（如唯一性约束）导致的错误。`add_xxx_or` 系列函数仅在此时调用 `or`.
+pub(crate) fn is_constraint_error(e: &StoreError) -> bool {
+    const SQLITE_CONSTRAINT: isize = 19;
+    matches!(e, StoreError::SqliteError(e) if e.code == Some(SQLITE_CONSTRAINT))
+}
+/// 备份，参见 [`Dir::export_bundle`].
+impl DataBase {
+    /// 数据库的一致快照，包含仍在 WAL 中尚未写回的修改。不能在事务中调用。
+    pub fn snapshot(&self) -> Result<Vec<u8>, StoreError> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let path =
+            std::env::temp_dir().join(format!("cxlib_snapshot_{}_{nanos}.db", std::process::id()));
+        let result = self
+            .execute_with("VACUUM INTO ?;", (1, path.to_string_lossy().as_ref()))
+            .and_then(|_| Ok(std::fs::read(&path)?));
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+    /// 备份 `dir` 配置目录，其中的数据库以该连接的[快照](DataBase::snapshot)代替，因此无需关闭数据库。
+    pub fn export_bundle(
+        &self,
+        dir: &Dir,
+        writer: impl Write,
+    ) -> Result<BundleManifest, BundleError> {
+        dir.export_bundle_with_database(writer, self.snapshot()?)
+    }
+}
+/// 配置，参见 [`Config`].
+impl DataBase {
+    /// 按默认值 < 配置文件 < 数据库 < 环境变量的顺序加载配置，并设为全局配置。
+    pub fn load_config(&self) -> Config {
+        let config = Config::load(&[&FileConfigLayer::load_default(), self, &EnvConfigLayer]);
+        Config::set(config.clone());
+        config
+    }
+    /// 校验并将配置项写入数据库。需重新[加载](DataBase::load_config)后生效。
+    pub fn set_config_value(&self, key: &str, value: &str) -> Result<(), ConfigError> {
+        Config::validate(key, value)?;
+        self.add_table::<KVConfigTable>()?;
+        KVConfigTable::insert_or(self, key, value.trim(), KVConfigTable::update)?;
+        Ok(())
+    }
+}
+impl ConfigLayerTrait for DataBase {
+    fn get_config_value(&self, key: &str) -> Option<String> {
+        match KVConfigTable::is_existed(self) {
+            Ok(true) => KVConfigTable::get_by_key(self, key).unwrap_or_log_default(),
+            Ok(false) => None,
+            Err(e) => log_default(e),
+        }
+    }
 }
 impl Default for DataBase {
     fn default() -> Self {
@@ -77,23 +300,305 @@ impl Default for DataBase {
     }
 }
 impl CourseExcludeInfoTrait for DataBase {
+    fn get_exclusion_policy(&self) -> ExclusionPolicy {
+        ExcludeTable::get_exclusion_policy(self).unwrap_or_log_default()
+    }
+
     fn is_excluded(&self, id: i64) -> bool {
-        ExcludeTable::has_exclude(self, id)
+        ExcludeTable::has_exclude(self, id).unwrap_or_log_default()
     }
 
     fn get_excludes(&self) -> HashSet<i64> {
-        ExcludeTable::get_excludes(self)
+        ExcludeTable::get_excludes(self).unwrap_or_log_default()
     }
 
     fn exclude(&self, id: i64) {
-        ExcludeTable::add_exclude(self, id)
+        ExcludeTable::add_exclude(self, id).unwrap_or_log_default()
     }
 
     fn cancel_exclude(&self, id: i64) {
-        ExcludeTable::delete_exclude(self, id)
+        ExcludeTable::delete_exclude(self, id).unwrap_or_log_default()
     }
 
     fn update_excludes<'a, I: IntoIterator<Item = &'a i64>>(&self, excludes: I) {
-        ExcludeTable::update_excludes(self, excludes)
+        ExcludeTable::update_excludes(self, excludes).unwrap_or_log_default()
+    }
+}
+impl ActivitySnapshotTrait for DataBase {
+    fn get_snapshot(&self, course_id: i64) -> Option<HashMap<String, i32>> {
+        match self
+            .add_table::<ActivitySnapshotTable>()
+            .and_then(|_| ActivitySnapshotTable::has_course(self, course_id))
+        {
+            Ok(true) => ActivitySnapshotTable::get_snapshot(self, course_id)
+                .map(Some)
+                .unwrap_or_else(log_default),
+            Ok(false) => None,
+            Err(e) => log_default(e),
+        }
+    }
+
+    fn update_snapshot(&self, course_id: i64, snapshot: &HashMap<String, i32>) {
+        self.add_table::<ActivitySnapshotTable>()
+            .and_then(|_| ActivitySnapshotTable::update_snapshot(self, course_id, snapshot))
+            .unwrap_or_log_default()
+    }
+}
+impl PhotoCacheTrait for DataBase {
+    fn get_cached_photo(&self, uid: &str, hash: &str) -> Option<(String, u64)> {
+        self.add_table::<PhotoTable>()
+            .and_then(|_| PhotoTable::get_photo(self, uid, hash))
+            .unwrap_or_log_default()
+    }
+
+    fn cache_photo(&self, uid: &str, hash: &str, object_id: &str, time_mills: u64) {
+        self.add_table::<PhotoTable>()
+            .and_then(|_| {
+                PhotoTable::add_photo_or(
+                    self,
+                    uid,
+                    hash,
+                    object_id,
+                    time_mills,
+                    PhotoTable::update_photo,
+                )
+            })
+            .unwrap_or_log_default()
+    }
+
+    fn remove_cached_photo(&self, uid: &str, hash: &str) {
+        self.add_table::<PhotoTable>()
+            .and_then(|_| PhotoTable::delete_photo(self, uid, hash))
+            .unwrap_or_log_default()
+    }
+}
+impl CourseCacheTrait for DataBase {
+    fn get_cached_courses(&self, uid: &str) -> Option<(Vec<Course>, u64)> {
+        self.add_table::<CourseTable>()
+            .and_then(|_| CourseTable::get_courses(self, uid))
+            .unwrap_or_log_default()
+    }
+
+    fn cache_courses(&self, uid: &str, courses: &[Course], time_mills: u64) {
+        self.add_table::<CourseTable>()
+            .and_then(|_| CourseTable::update_courses(self, uid, courses, time_mills))
+            .unwrap_or_log_default()
+    }
+
+    fn remove_cached_courses(&self, uid: &str) {
+        self.add_table::<CourseTable>()
+            .and_then(|_| CourseTable::delete_user(self, uid))
+            .unwrap_or_log_default()
+    }
+}
+impl AccountStoreTrait for DataBase {
+    fn has_account(&self, uid: &str) -> bool {
+        AccountTable::has_account(self, uid).unwrap_or_log_default()
+    }
+
+    fn get_account(&self, uid: &str) -> Option<AccountData> {
+        AccountTable::get_account(self, uid).unwrap_or_log_default()
+    }
+
+    fn get_accounts(&self) -> HashSet<AccountData> {
+        AccountTable::get_accounts(self).unwrap_or_log_default()
+    }
+
+    fn add_account(&self, account: &AccountData) {
+        AccountTable::add_account_or(self, account, AccountTable::update_account)
+            .unwrap_or_log_default()
+    }
+
+    fn delete_account(&self, uid: &str) {
+        AccountTable::delete_account(self, uid).unwrap_or_log_default()
+    }
+}
+impl AliasStoreTrait for DataBase {
+    fn has_alias(&self, alias: &str) -> bool {
+        AliasTable::has_alias(self, alias).unwrap_or_log_default()
+    }
+
+    fn get_location_ids(&self, alias: &str) -> Vec<i64> {
+        AliasTable::get_location_ids(self, alias).unwrap_or_log_default()
+    }
+
+    fn get_location_id(&self, alias: &str) -> Option<i64> {
+        AliasTable::get_location_id(self, alias).unwrap_or_log_default()
+    }
+
+    fn get_aliases(&self, location_id: i64) -> Vec<String> {
+        AliasTable::get_aliases(self, location_id).unwrap_or_log_default()
+    }
+
+    fn get_all_aliases(&self) -> Vec<(String, i64)> {
+        AliasTable::get_all_aliases(self)
+            .unwrap_or_log_default()
+            .into_iter()
+            .map(|AliasData { name, lid }| (name, lid))
+            .collect()
+    }
+
+    fn add_alias(&self, alias: &str, location_id: i64) {
+        AliasTable::add_alias_or(self, alias, location_id, |_, _, _| Ok(())).unwrap_or_log_default()
+    }
+
+    fn remove_alias(&self, alias: &str, location_id: i64) {
+        AliasTable::remove_alias(self, alias, location_id).unwrap_or_log_default()
+    }
+
+    fn delete_alias(&self, alias: &str) {
+        AliasTable::delete_alias(self, alias).unwrap_or_log_default()
+    }
+}
+impl LocationStoreTrait for DataBase {
+    fn has_location(&self, location_id: i64) -> bool {
+        LocationTable::has_location(self, location_id).unwrap_or_log_default()
+    }
+
+    fn get_location(&self, location_id: i64) -> Option<(i64, Location)> {
+        LocationTable::get_location(self, location_id).unwrap_or_log_default()
+    }
+
+    fn get_locations(&self) -> HashMap<i64, (i64, Location)> {
+        LocationTable::get_locations(self).unwrap_or_log_default()
+    }
+
+    fn get_locations_by_course(&self, course_id: i64) -> HashMap<i64, Location> {
+        LocationTable::get_location_map_by_course(self, course_id).unwrap_or_log_default()
+    }
+
+    fn insert_location(&self, course_id: i64, location: &Location) -> Result<i64, StoreError> {
+        LocationTable::insert_location(self, course_id, location)
+    }
+
+    fn delete_location(&self, location_id: i64) {
+        LocationTable::delete_location(self, location_id).unwrap_or_log_default()
+    }
+}
+/// 标签表和使用记录表在首次使用时创建。
+impl LocationLibraryTrait for DataBase {
+    fn get_tags(&self, location_id: i64) -> BTreeSet<String> {
+        self.add_table::<LocationTagTable>()
+            .and_then(|_| LocationTagTable::get_tags(self, location_id))
+            .unwrap_or_log_default()
+    }
+
+    fn add_tag(&self, location_id: i64, tag: &str) {
+        self.add_table::<LocationTagTable>()
+            .and_then(|_| LocationTagTable::add_tag(self, location_id, tag))
+            .unwrap_or_log_default()
+    }
+
+    fn remove_tag(&self, location_id: i64, tag: &str) {
+        self.add_table::<LocationTagTable>()
+            .and_then(|_| LocationTagTable::remove_tag(self, location_id, tag))
+            .unwrap_or_log_default()
+    }
+
+    fn get_locations_by_tag(&self, tag: &str) -> BTreeSet<i64> {
+        self.add_table::<LocationTagTable>()
+            .and_then(|_| LocationTagTable::get_locations_by_tag(self, tag))
+            .unwrap_or_log_default()
+    }
+
+    fn get_usage(&self, location_id: i64) -> Option<LocationUsage> {
+        self.add_table::<LocationUsageTable>()
+            .and_then(|_| LocationUsageTable::get_usage(self, location_id))
+            .unwrap_or_log_default()
+    }
+
+    fn record_usage(&self, location_id: i64, time_mills: u64) {
+        self.add_table::<LocationUsageTable>()
+            .and_then(|_| LocationUsageTable::record_usage(self, location_id, time_mills))
+            .unwrap_or_log_default()
+    }
+}
+impl KVStoreTrait for DataBase {
+    fn get_value(&self, key: &str) -> Option<String> {
+        KVConfigTable::get_by_key(self, key).unwrap_or_log_default()
+    }
+
+    fn set_value(&self, key: &str, value: &str) {
+        KVConfigTable::insert_or(self, key, value, KVConfigTable::update).unwrap_or_log_default()
+    }
+
+    fn remove_value(&self, key: &str) {
+        KVConfigTable::remove(self, key).unwrap_or_log_default()
+    }
+
+    fn get_values(&self) -> HashMap<String, String> {
+        KVConfigTable::get_as_map(self).unwrap_or_log_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{DataBase, KVConfigTable};
+    use cxlib_activity::ActivitySnapshotTrait;
+    use cxlib_store::Dir;
+    use cxlib_types::{Course, CourseCacheTrait};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_snapshot_table_created_on_first_use() {
+        let db = DataBase::in_memory().unwrap();
+        assert_eq!(db.get_snapshot(1), None);
+        db.update_snapshot(1, &HashMap::from([("a".to_owned(), 1)]));
+        assert_eq!(
+            db.get_snapshot(1),
+            Some(HashMap::from([("a".to_owned(), 1)]))
+        );
+        // 空快照与未记录的课程不同。
+        db.update_snapshot(1, &HashMap::new());
+        assert_eq!(db.get_snapshot(1), Some(HashMap::new()));
+        assert_eq!(db.get_snapshot(2), None);
+    }
+
+    #[test]
+    fn test_course_table_created_on_first_use() {
+        let db = DataBase::in_memory().unwrap();
+        assert_eq!(db.get_cached_courses("uid"), None);
+        let courses = vec![Course::new(1, 2, "教师", "", "课程")];
+        db.cache_courses("uid", &courses, 100);
+        assert_eq!(db.get_cached_courses("uid"), Some((courses, 100)));
+        db.remove_cached_courses("uid");
+        assert_eq!(db.get_cached_courses("uid"), None);
+    }
+
+    #[test]
+    fn test_export_bundle_without_closing() {
+        let root =
+            std::env::temp_dir().join(format!("cxlib_db_bundle_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let (src, dst) = (Dir::new(&root.join("src")), Dir::new(&root.join("dst")));
+        std::fs::create_dir_all(src.get_base_path()).unwrap();
+        std::fs::create_dir_all(dst.get_base_path()).unwrap();
+        let db = DataBase::open(src.get_database_path()).unwrap();
+        db.add_table::<KVConfigTable>().unwrap();
+        KVConfigTable::insert_or(&db, "k", "v", KVConfigTable::update).unwrap();
+        // 连接未关闭，修改仍在 WAL 中。
+        assert!(src.export_bundle(&mut Vec::new()).is_err());
+        let mut bundle = Vec::new();
+        db.export_bundle(&src, &mut bundle).unwrap();
+
+        {
+            let old = DataBase::open(dst.get_database_path()).unwrap();
+            old.add_table::<KVConfigTable>().unwrap();
+            KVConfigTable::insert_or(&old, "old", "v", KVConfigTable::update).unwrap();
+        }
+        let wal = dst.get_base_path().join("cx.db-wal");
+        std::fs::write(&wal, b"stale").unwrap();
+        dst.restore_bundle(bundle.as_slice(), true).unwrap();
+        assert!(!wal.exists());
+        let restored = DataBase::open(dst.get_database_path()).unwrap();
+        assert_eq!(
+            KVConfigTable::get_by_key(&restored, "k")
+                .unwrap()
+                .as_deref(),
+            Some("v")
+        );
+        assert!(!KVConfigTable::has_key(&restored, "old").unwrap());
+        drop((db, restored));
+        std::fs::remove_dir_all(&root).unwrap();
     }
 }
@@ -0,0 +1,147 @@
+use crate::store::{DataBase, StoreError};
+use cxlib_store::Dir;
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{Condvar, Mutex},
+};
+
+struct PoolState {
+    idle: Vec<DataBase>,
+    /// 已打开的连接数，包括正在使用的连接。
+    opened: usize,
+}
+/// # DataBasePool
+///
+/// 数据库连接池，可在多个线程间共享。
+///
+/// 连接按需打开，数量不超过 `max_size`; 连接均已被占用时，[`DataBasePool::get`] 将等待其他线程归还连接。
+pub struct DataBasePool {
+    path: PathBuf,
+    max_size: usize,
+    state: Mutex<PoolState>,
+    available: Condvar,
+}
+impl DataBasePool {
+    /// 默认的最大连接数。
+    pub const DEFAULT_MAX_SIZE: usize = 4;
+    pub fn new(path: &Path, max_size: usize) -> Self {
+        Self {
+            path: path.to_owned(),
+            max_size: max_size.max(1),
+            state: Mutex::new(PoolState {
+                idle: Vec::new(),
+                opened: 0,
+            }),
+            available: Condvar::new(),
+        }
+    }
+    /// 当前配置目录中的数据库。
+    pub fn for_current_dir() -> Self {
+        Self::new(&Dir::get_database_dir(), Self::DEFAULT_MAX_SIZE)
+    }
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+    pub fn get_max_size(&self) -> usize {
+        self.max_size
+    }
+    /// 取得一个连接，释放时归还。
+    pub fn get(&self) -> Result<PooledDataBase<'_>, StoreError> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(db) = state.idle.pop() {
+                return Ok(PooledDataBase {
+                    pool: self,
+                    db: Some(db),
+                });
+            }
+            if state.opened < self.max_size {
+                state.opened += 1;
+                break;
+            }
+            state = self.available.wait(state).unwrap();
+        }
+        drop(state);
+        match DataBase::open(&self.path) {
+            Ok(db) => Ok(PooledDataBase {
+                pool: self,
+                db: Some(db),
+            }),
+            Err(e) => {
+                self.discard();
+                Err(e)
+            }
+        }
+    }
+    fn discard(&self) {
+        self.state.lock().unwrap().opened -= 1;
+        self.available.notify_one();
+    }
+}
+/// 从 [`DataBasePool`] 中取得的连接。
+pub struct PooledDataBase<'a> {
+    pool: &'a DataBasePool,
+    db: Option<DataBase>,
+}
+impl Deref for PooledDataBase<'_> {
+    type Target = DataBase;
+
+    fn deref(&self) -> &Self::Target {
+        self.db.as_ref().unwrap()
+    }
+}
+impl Drop for PooledDataBase<'_> {
+    fn drop(&mut self) {
+        if let Some(db) = self.db.take() {
+            // 事务中途发生恐慌时连接状态未知，不再复用。
+            if db.in_transaction() || std::thread::panicking() {
+                drop(db);
+                self.pool.discard();
+            } else {
+                self.pool.state.lock().unwrap().idle.push(db);
+                self.pool.available.notify_one();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{DataBasePool, KVConfigTable, StoreError};
+
+    #[test]
+    fn test_pool_concurrent_write() {
+        let dir = std::env::temp_dir().join(format!("cxlib_pool_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let pool = DataBasePool::new(&dir.join("cx.db"), 2);
+        pool.get().unwrap().add_table::<KVConfigTable>().unwrap();
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let pool = &pool;
+                s.spawn(move || {
+                    for i in 0..20 {
+                        let db = pool.get().unwrap();
+                        db.transaction(|db| {
+                            let key = format!("{t}-{i}");
+                            KVConfigTable::insert_or(db, &key, "v", KVConfigTable::update)
+                        })
+                        .unwrap();
+                    }
+                });
+            }
+        });
+        let db = pool.get().unwrap();
+        assert_eq!(KVConfigTable::get_as_map(&db).unwrap().len(), 80);
+        let r = db.transaction(|db| {
+            KVConfigTable::insert_or(db, "rollback", "v", KVConfigTable::update)?;
+            Err::<(), _>(StoreError::Conflict("test".to_owned()))
+        });
+        assert!(r.is_err());
+        assert!(!KVConfigTable::has_key(&db, "rollback").unwrap());
+        drop(db);
+        drop(pool);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,8 +1,11 @@
-use crate::store::{DataBase, DataBaseTableTrait};
-use cxlib_error::StoreError;
+use crate::store::{is_constraint_error, DataBase, DataBaseTableTrait, TableRecordTrait};
+use cxlib_error::{CxlibResultUtils, StoreError};
 use cxlib_store::{Dir, StorageTableCommandTrait};
-use cxlib_user::{DefaultLoginSolver, LoginError, LoginSolverTrait, LoginSolverWrapper, Session};
+use cxlib_user::{
+    DefaultLoginSolver, LoginError, LoginSolverTrait, LoginSolverWrapper, LoginSolvers, Session,
+};
 use log::{info, warn};
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
     fmt::Display,
@@ -10,7 +13,7 @@ use std::{
 };
 
 pub struct AccountTable;
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct AccountData {
     uid: String,
     uname: String,
@@ -51,210 +54,365 @@ impl From<(String, String, String, String)> for AccountData {
 }
 impl Display for AccountData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{},{},{}", self.uname, self.enc_pwd, self.login_type)
+        write!(
+            f,
+            "{},{},{},{}",
+            self.uname, self.enc_pwd, self.login_type, self.uid
+        )
     }
 }
 impl FromStr for AccountData {
     type Err = StoreError;
 
+    /// 格式见 [`AccountImportEntry`]. 含有 uid 时仅解析，不登录。
+    ///
+    /// 兼容旧版本导出的 `uname,enc_pwd[,login_type]` 格式：缺少 uid 时会登录以获取 uid 并保存 Cookies.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s
-            .split(',')
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<_>>();
-        if s.len() < 2 {
+        let entry = s.parse::<AccountImportEntry>()?;
+        match entry.to_account_data() {
+            Some(account) => Ok(account),
+            None => entry.login().map(|(account, _)| account),
+        }
+    }
+}
+
+/// # AccountImportEntry
+///
+/// 导入文件中的一行账号信息，格式为 `uname,enc_pwd[,login_type[,uid]]`.
+///
+/// 解析不会进行网络请求。缺少 uid 的账号须通过 [`AccountImportEntry::login`] 登录后才能得到完整的 [`AccountData`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AccountImportEntry {
+    uname: String,
+    enc_pwd: String,
+    login_type: String,
+    uid: Option<String>,
+}
+impl FromStr for AccountImportEntry {
+    type Err = StoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.split(',').map(|s| s.trim()).collect::<Vec<_>>();
+        if s.len() < 2 || s[0].is_empty() || s[1].is_empty() {
             Err(StoreError::ParseError(
-                "登录所需信息解析出错！格式为 `uname,enc_pwd[, login_typ]`.".to_string(),
+                "登录所需信息解析出错！格式为 `uname,enc_pwd[,login_type[,uid]]`.".to_string(),
             ))?
-        } else {
-            let uname = s[0].to_string();
-            let enc_pwd = s[1].to_string();
-            let login_type = if s.len() == 3 {
-                s[2].to_string()
-            } else {
-                String::new()
-            };
-            let (agent, cookies) =
-                Session::relogin_raw(&uname, &enc_pwd, &LoginSolverWrapper::new(&login_type))?;
-            Session::store_cookies(&agent, cookies.get_uid())?;
-            Ok(Self {
-                uid: cookies.get_uid().to_owned(),
-                uname,
-                enc_pwd,
-                login_type,
-            })
+        }
+        let login_type = match s.get(2) {
+            Some(login_type) if !login_type.is_empty() => login_type.to_string(),
+            _ => DefaultLoginSolver.login_type().to_owned(),
+        };
+        Ok(Self {
+            uname: s[0].to_string(),
+            enc_pwd: s[1].to_string(),
+            login_type,
+            uid: s
+                .get(3)
+                .filter(|uid| !uid.is_empty())
+                .map(|uid| uid.to_string()),
+        })
+    }
+}
+impl AccountImportEntry {
+    pub fn uname(&self) -> &str {
+        &self.uname
+    }
+    pub fn login_type(&self) -> &str {
+        &self.login_type
+    }
+    pub fn uid(&self) -> Option<&str> {
+        self.uid.as_deref()
+    }
+    /// 检查数据是否有效：uid 须为数字，登录方式须已[注册](cxlib_user::LoginSolvers::register)。
+    pub fn validate(&self) -> Result<(), StoreError> {
+        if let Some(uid) = &self.uid {
+            if !uid.chars().all(|c| c.is_ascii_digit()) {
+                Err(StoreError::ParseError(format!("uid `{uid}` 不是数字。")))?
+            }
+        }
+        if !LoginSolvers::is_registered(&self.login_type) {
+            Err(StoreError::ParseError(format!(
+                "不支持的登录方式：`{}`.",
+                self.login_type
+            )))?
+        }
+        Ok(())
+    }
+    /// 含有 uid 时可直接转换为 [`AccountData`].
+    pub fn to_account_data(&self) -> Option<AccountData> {
+        self.uid.as_ref().map(|uid| {
+            AccountData::new(
+                uid.clone(),
+                self.uname.clone(),
+                self.enc_pwd.clone(),
+                self.login_type.clone(),
+            )
+        })
+    }
+    /// 登录并保存 Cookies, 返回以登录结果中的 uid 构造的 [`AccountData`].
+    pub fn login(&self) -> Result<(AccountData, Session), StoreError> {
+        let session = Session::relogin(
+            &self.uname,
+            &self.enc_pwd,
+            &LoginSolverWrapper::new(&self.login_type),
+        )?;
+        let account = AccountData::new(
+            session.get_uid().to_owned(),
+            self.uname.clone(),
+            self.enc_pwd.clone(),
+            self.login_type.clone(),
+        );
+        Ok((account, session))
+    }
+}
+
+/// 导入时每一行的结果。
+#[derive(Debug)]
+pub enum AccountImportStatus {
+    /// 已导入，值为 uid.
+    Imported(String),
+    /// 数据有效但缺少 uid, 等待登录。
+    PendingLogin(AccountImportEntry),
+    /// 解析或校验失败。
+    Invalid(StoreError),
+    /// 登录失败。
+    LoginFailed(AccountImportEntry, StoreError),
+}
+/// # AccountImportReport
+///
+/// 账号导入报告，记录每一非空行的结果，行号从 `1` 开始。
+#[derive(Debug, Default)]
+pub struct AccountImportReport {
+    lines: Vec<(usize, AccountImportStatus)>,
+}
+impl AccountImportReport {
+    pub fn get_lines(&self) -> &[(usize, AccountImportStatus)] {
+        &self.lines
+    }
+    pub fn imported_count(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|(_, s)| matches!(s, AccountImportStatus::Imported(_)))
+            .count()
+    }
+    pub fn has_pending(&self) -> bool {
+        self.lines
+            .iter()
+            .any(|(_, s)| matches!(s, AccountImportStatus::PendingLogin(_)))
+    }
+    /// 以日志的形式输出每一行的结果。
+    pub fn log(&self) {
+        for (line, status) in &self.lines {
+            match status {
+                AccountImportStatus::Imported(uid) => {
+                    info!("第 {line} 行：账号 [{uid}] 导入成功！")
+                }
+                AccountImportStatus::PendingLogin(e) => {
+                    info!("第 {line} 行：账号 [{}] 缺少 uid, 需要登录。", e.uname())
+                }
+                AccountImportStatus::Invalid(e) => {
+                    warn!("第 {line} 行：数据无效，该行将被跳过！错误信息：{e}.")
+                }
+                AccountImportStatus::LoginFailed(a, e) => {
+                    warn!(
+                        "第 {line} 行：账号 [{}] 登录失败！错误信息：{e}.",
+                        a.uname()
+                    )
+                }
+            }
         }
     }
 }
+
 impl AccountTable {
     pub fn get_sessions_by_uid_list_str(
         db: &DataBase,
         uid_list_str: &str,
-    ) -> HashMap<String, Session> {
+    ) -> Result<HashMap<String, Session>, StoreError> {
         let str_list = uid_list_str
             .split(',')
             .map(|a| a.trim())
             .collect::<Vec<&str>>();
         let mut s = HashMap::new();
         for uid in str_list {
-            if let Some(session) = Self::get_session(db, uid) {
+            if let Some(session) = Self::get_session(db, uid)? {
                 s.insert(uid.to_string(), session);
             }
         }
-        s
+        Ok(s)
     }
-    pub fn get_session(db: &DataBase, uid: &str) -> Option<Session> {
-        if Self::has_account(db, uid) {
-            let account = Self::get_account(db, uid)?;
-            Session::load_cookies(uid, account.uname()).ok()
+    pub fn get_session(db: &DataBase, uid: &str) -> Result<Option<Session>, StoreError> {
+        if let Some(account) = Self::get_account(db, uid)? {
+            Ok(Session::load_cookies(uid, account.uname()).ok())
         } else {
             warn!("没有该账号：[`{uid}`]，请检查输入或登录。");
-            None
+            Ok(None)
         }
     }
-    pub fn get_sessions(db: &DataBase) -> HashMap<String, Session> {
-        let accounts = Self::get_accounts(db).into_iter().collect::<Vec<_>>();
+    pub fn get_sessions(db: &DataBase) -> Result<HashMap<String, Session>, StoreError> {
+        let accounts = Self::get_accounts(db)?;
         let mut s = HashMap::new();
         for account in accounts {
-            if Self::has_account(db, &account.uid) {
-                if let Ok(session) = Session::load_cookies_or_relogin(
-                    account.uname(),
-                    account.uid(),
-                    account.enc_pwd(),
-                    &LoginSolverWrapper::new(account.login_type()),
-                ) {
-                    s.insert(account.uid.clone(), session);
-                } else {
-                    warn!("账号加载失败：[`{}`]，跳过。", account.uname);
-                }
+            if let Ok(session) = Session::load_cookies_or_relogin(
+                account.uname(),
+                account.uid(),
+                account.enc_pwd(),
+                &LoginSolverWrapper::new(account.login_type()),
+            ) {
+                s.insert(account.uid.clone(), session);
             } else {
-                warn!(
-                    "没有该账号：[`{}`]，跳过。请检查输入或登录。",
-                    account.uname
-                );
+                warn!("账号加载失败：[`{}`]，跳过。", account.uname);
             }
         }
-        s
+        Ok(s)
     }
-    pub fn has_account(db: &DataBase, uid: &str) -> bool {
-        let mut query = db
-            .prepare(format!(
-                "SELECT count(*) FROM {} WHERE uid=?;",
-                Self::TABLE_NAME
-            ))
-            .unwrap();
-        query.bind((1, uid)).unwrap();
-        query.next().unwrap();
-        query.read::<i64, _>(0).unwrap() > 0
+    pub fn has_account(db: &DataBase, uid: &str) -> Result<bool, StoreError> {
+        Ok(db.query_count(
+            &format!("SELECT count(*) FROM {} WHERE uid=?;", Self::TABLE_NAME),
+            (1, uid),
+        )? > 0)
     }
 
-    pub fn delete_account(db: &DataBase, uid: &str) {
-        if Self::has_account(db, uid) {
-            let mut query = db
-                .prepare(format!("DELETE FROM {} WHERE uid=?;", Self::TABLE_NAME))
-                .unwrap();
-            query.bind((1, uid)).unwrap();
-            query.next().unwrap();
+    /// 删除账号及其 Cookies.
+    pub fn delete_account(db: &DataBase, uid: &str) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("DELETE FROM {} WHERE uid=?;", Self::TABLE_NAME),
+            (1, uid),
+        )?;
+        match std::fs::remove_file(Dir::get_json_file_path(uid)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e)?,
+            _ => Ok(()),
         }
-        std::fs::remove_file(Dir::get_json_file_path(uid)).unwrap();
     }
 
-    pub fn add_account_or<O: Fn(&DataBase, &AccountData)>(
+    pub fn add_account_or<O: Fn(&DataBase, &AccountData) -> Result<(), StoreError>>(
         db: &DataBase,
         account: &AccountData,
         or: O,
-    ) {
-        let mut query = db
-            .prepare(format!(
+    ) -> Result<(), StoreError> {
+        match db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
                 "INSERT INTO {}(uid,uname,enc_pwd,login_type) values(:uid,:uname,:enc_pwd,:login_type);",
                 Self::TABLE_NAME
-            ))
-            .unwrap();
-        query
-            .bind::<&[(_, sqlite::Value)]>(
-                &[
-                    (":uid", account.uid().into()),
-                    (":uname", account.uname().into()),
-                    (":enc_pwd", account.enc_pwd().into()),
-                    (":login_type", account.login_type().into()),
-                ][..],
-            )
-            .unwrap();
-        match query.next() {
-            Ok(_) => (),
-            Err(_) => or(db, account),
-        };
+            ),
+            &[
+                (":uid", account.uid().into()),
+                (":uname", account.uname().into()),
+                (":enc_pwd", account.enc_pwd().into()),
+                (":login_type", account.login_type().into()),
+            ][..],
+        ) {
+            Err(e) if is_constraint_error(&e) => or(db, account),
+            r => r,
+        }
     }
 
-    pub fn update_account(db: &DataBase, account: &AccountData) {
-        let mut query = db
-            .prepare(format!(
+    pub fn update_account(db: &DataBase, account: &AccountData) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
                 "UPDATE {} SET uname=:uname,enc_pwd=:enc_pwd,login_type=:login_type WHERE uid=:uid;",
                 Self::TABLE_NAME
-            ))
-            .unwrap();
-        query
-            .bind::<&[(_, sqlite::Value)]>(
-                &[
-                    (":uid", account.uid().into()),
-                    (":uname", account.uname().into()),
-                    (":enc_pwd", account.enc_pwd().into()),
-                    (":login_type", account.login_type().into()),
-                ][..],
-            )
-            .unwrap();
-        query.next().unwrap();
+            ),
+            &[
+                (":uid", account.uid().into()),
+                (":uname", account.uname().into()),
+                (":enc_pwd", account.enc_pwd().into()),
+                (":login_type", account.login_type().into()),
+            ][..],
+        )
     }
 
-    pub fn get_accounts(db: &DataBase) -> HashSet<AccountData> {
-        let mut query = db
-            .prepare(format!("SELECT * FROM {};", Self::TABLE_NAME))
-            .unwrap();
+    fn read_account(row: &sqlite::Row) -> Result<AccountData, StoreError> {
+        let uid: &str = row.try_read("uid")?;
+        let uname: &str = row.try_read("uname")?;
+        let enc_pwd: &str = row.try_read("enc_pwd")?;
+        let login_type: &str = row.try_read("login_type")?;
+        Ok(AccountData::new(
+            uid.into(),
+            uname.into(),
+            enc_pwd.into(),
+            login_type.into(),
+        ))
+    }
+
+    pub fn get_accounts(db: &DataBase) -> Result<HashSet<AccountData>, StoreError> {
+        let mut query = db.prepare_cached(&format!("SELECT * FROM {};", Self::TABLE_NAME))?;
         let mut accounts = HashSet::new();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let uid: &str = row.read("uid");
-                let uname: &str = row.read("uname");
-                let enc_pwd: &str = row.read("enc_pwd");
-                let login_type: &str = row.read("login_type");
-                accounts.insert(AccountData::new(
-                    uid.into(),
-                    uname.into(),
-                    enc_pwd.into(),
-                    login_type.into(),
-                ));
-            } else {
-                warn!("账号解析行出错：{c:?}.");
-            }
+        for row in query.iter() {
+            accounts.insert(Self::read_account(&row?)?);
         }
         if accounts.is_empty() {
             warn!("没有登录的账号，请登录。");
         }
-        accounts
-    }
-    pub fn get_account(db: &DataBase, uid: &str) -> Option<AccountData> {
-        let mut query = db
-            .prepare(format!("SELECT * FROM {} WHERE uid=?;", Self::TABLE_NAME))
-            .unwrap();
-        query.bind((1, uid)).unwrap();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let uid: &str = row.read("uid");
-                let uname: &str = row.read("uname");
-                let enc_pwd: &str = row.read("enc_pwd");
-                let login_type: &str = row.read("login_type");
-                return Some(AccountData::new(
-                    uid.into(),
-                    uname.into(),
-                    enc_pwd.into(),
-                    login_type.into(),
-                ));
-            } else {
-                warn!("账号解析行出错：{c:?}.");
+        Ok(accounts)
+    }
+    pub fn get_account(db: &DataBase, uid: &str) -> Result<Option<AccountData>, StoreError> {
+        let mut query =
+            db.prepare_cached(&format!("SELECT * FROM {} WHERE uid=?;", Self::TABLE_NAME))?;
+        query.bind((1, uid))?;
+        if let Some(row) = query.iter().next() {
+            return Ok(Some(Self::read_account(&row?)?));
+        }
+        Ok(None)
+    }
+    /// 解析并校验导入内容，不进行网络请求。返回非空行的行号及结果。
+    pub fn parse_import(content: &str) -> Vec<(usize, Result<AccountImportEntry, StoreError>)> {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let entry = line
+                    .parse::<AccountImportEntry>()
+                    .and_then(|e| e.validate().map(|_| e));
+                (i + 1, entry)
+            })
+            .collect()
+    }
+    /// 导入账号。含有 uid 的账号直接写入，不进行网络请求；缺少 uid 的账号在报告中标记为等待登录，
+    /// 可通过 [`AccountTable::login_pending`] 稍后登录。
+    ///
+    /// 所有账号在同一事务中写入，数据库出错时不会导入任何账号。
+    pub fn import_accounts(
+        db: &DataBase,
+        content: &str,
+    ) -> Result<AccountImportReport, StoreError> {
+        let entries = Self::parse_import(content);
+        db.transaction(|db| {
+            let mut lines = Vec::with_capacity(entries.len());
+            for (line, entry) in entries {
+                let status = match entry {
+                    Ok(entry) => match entry.to_account_data() {
+                        Some(account) => {
+                            Self::add_account_or(db, &account, Self::update_account)?;
+                            AccountImportStatus::Imported(account.uid)
+                        }
+                        None => AccountImportStatus::PendingLogin(entry),
+                    },
+                    Err(e) => AccountImportStatus::Invalid(e),
+                };
+                lines.push((line, status));
+            }
+            Ok(AccountImportReport { lines })
+        })
+    }
+    /// 登录报告中所有等待登录的账号，并更新报告。
+    pub fn login_pending(
+        db: &DataBase,
+        report: &mut AccountImportReport,
+    ) -> Result<(), StoreError> {
+        for (_, status) in report.lines.iter_mut() {
+            if let AccountImportStatus::PendingLogin(entry) = status {
+                *status = match entry.login() {
+                    Ok((account, _)) => {
+                        Self::add_account_or(db, &account, Self::update_account)?;
+                        AccountImportStatus::Imported(account.uid)
+                    }
+                    Err(e) => AccountImportStatus::LoginFailed(entry.clone(), e),
+                };
             }
         }
-        None
+        Ok(())
     }
     /// 用于第一次登录。
     pub fn login(
@@ -262,7 +420,7 @@ impl AccountTable {
         uname: String,
         pwd: Option<String>,
         login_type: String,
-    ) -> Result<Session, LoginError> {
+    ) -> Result<Session, StoreError> {
         let pwd = pwd.ok_or(LoginError::BadPassword("没有密码。".to_owned()))?;
         let solver = LoginSolverWrapper::new(&login_type);
         let enc_pwd = solver.pwd_enc(pwd)?;
@@ -271,7 +429,7 @@ impl AccountTable {
             db,
             &AccountData::new(session.get_uid().to_owned(), uname, enc_pwd, login_type),
             AccountTable::update_account,
-        );
+        )?;
         Ok(session)
     }
     pub fn relogin(db: &DataBase, uid: String) -> Result<Session, StoreError> {
@@ -280,28 +438,27 @@ impl AccountTable {
             uname,
             enc_pwd,
             login_type,
-        }) = AccountTable::get_account(db, &uid)
+        }) = AccountTable::get_account(db, &uid)?
         {
             let session =
                 Session::relogin(&uname, &enc_pwd, &LoginSolverWrapper::new(&login_type))?;
             Session::store_cookies(&session, &uid)?;
             Ok(session)
         } else {
-            warn!("数据库中没有该用户！可能是实现错误。");
-            panic!()
+            Err(StoreError::NotFound(format!("没有该账号：[`{uid}`]")))
         }
     }
 }
 
 impl StorageTableCommandTrait<DataBase> for AccountTable {
     fn init(storage: &DataBase) {
-        <Self as DataBaseTableTrait>::init(storage);
+        <Self as DataBaseTableTrait>::init(storage).unwrap_or_log_default();
     }
     fn uninit(storage: &DataBase) -> bool {
-        !Self::is_existed(storage)
+        !Self::is_existed(storage).unwrap_or_log_default()
     }
     fn clear(storage: &DataBase) {
-        Self::delete(storage);
+        Self::delete(storage).unwrap_or_log_default();
     }
     fn import(storage: &DataBase, content: &str) {
         <Self as DataBaseTableTrait>::import(storage, content);
@@ -316,24 +473,101 @@ impl DataBaseTableTrait for AccountTable {
     const TABLE_NAME: &'static str = "account";
 
     fn import(db: &DataBase, data: &str) {
-        db.add_table::<Self>();
-        let data = crate::utils::parse::<_, AccountData>(data);
-        for account in data {
-            match Session::relogin(account.uname(), account.enc_pwd(), &DefaultLoginSolver) {
-                Ok(session) => {
-                    info!(
-                        "账号 [{}]（用户名：{}）导入成功！",
-                        account.uname(),
-                        session.get_stu_name()
-                    );
-                    Self::add_account_or(db, &account, AccountTable::update_account);
-                }
-                Err(e) => warn!("账号 [{}] 导入失败！错误信息：{e}.", account.uname(),),
+        let r = db.add_table::<Self>().and_then(|_| {
+            let mut report = Self::import_accounts(db, data)?;
+            if report.has_pending() {
+                Self::login_pending(db, &mut report)?;
             }
+            Ok(report)
+        });
+        match r {
+            Ok(report) => report.log(),
+            Err(e) => warn!("账号导入失败：{e}."),
         }
     }
 
     fn export(db: &DataBase) -> String {
-        crate::utils::to_string(Self::get_accounts(db).iter())
+        crate::utils::to_string(Self::get_accounts(db).unwrap_or_log_default().iter())
+    }
+}
+impl TableRecordTrait for AccountTable {
+    type Record = AccountData;
+
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError> {
+        Ok(Self::get_accounts(db)?.into_iter().collect())
+    }
+
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError> {
+        Self::has_account(db, record.uid())
+    }
+
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError> {
+        Self::add_account_or(db, record, Self::update_account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{
+        AccountData, AccountImportEntry, AccountImportStatus, AccountTable, DataBase, StoreError,
+    };
+
+    #[test]
+    fn test_parse_import() {
+        let content =
+            "uname,enc,default,123\n\nuname2,enc2\nbad\nuname3,enc3,unknown\nuname4,enc4,,abc\n";
+        let lines = AccountTable::parse_import(content);
+        assert_eq!(
+            lines.iter().map(|(l, _)| *l).collect::<Vec<_>>(),
+            vec![1, 3, 4, 5, 6]
+        );
+        let first = lines[0].1.as_ref().unwrap();
+        assert_eq!(first.uid(), Some("123"));
+        let second = lines[1].1.as_ref().unwrap();
+        assert_eq!(second.uid(), None);
+        assert_eq!(second.login_type(), "default");
+        assert!(lines[2..].iter().all(|(_, r)| r.is_err()));
+    }
+    #[test]
+    fn test_account_data_round_trip() {
+        let account = AccountData::new(
+            "123".to_owned(),
+            "uname".to_owned(),
+            "enc".to_owned(),
+            "default".to_owned(),
+        );
+        assert_eq!(account.to_string().parse::<AccountData>().unwrap(), account);
+        assert!("uname,enc"
+            .parse::<AccountImportEntry>()
+            .unwrap()
+            .to_account_data()
+            .is_none());
+    }
+    #[test]
+    fn test_parse_legacy_line() {
+        // 旧版本导出的格式为 `uname,enc_pwd,login_type`, 登录方式可能为空。
+        for line in ["uname,enc,default", "uname,enc,"] {
+            let entry = line.parse::<AccountImportEntry>().unwrap();
+            assert_eq!(entry.uname(), "uname");
+            assert_eq!(entry.login_type(), "default");
+            assert_eq!(entry.uid(), None);
+            entry.validate().unwrap();
+        }
+        let db = DataBase::in_memory().unwrap();
+        db.add_table::<AccountTable>().unwrap();
+        let report = AccountTable::import_accounts(&db, "uname,enc,default\n").unwrap();
+        assert!(matches!(
+            report.get_lines(),
+            [(1, AccountImportStatus::PendingLogin(_))]
+        ));
+    }
+    #[test]
+    fn test_relogin_missing_account() {
+        let db = DataBase::in_memory().unwrap();
+        db.add_table::<AccountTable>().unwrap();
+        assert!(matches!(
+            AccountTable::relogin(&db, "uid".to_owned()),
+            Err(StoreError::NotFound(_))
+        ));
     }
 }
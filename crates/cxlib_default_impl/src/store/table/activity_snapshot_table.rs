@@ -0,0 +1,209 @@
+use crate::store::{
+    DataBase, DataBaseTableTrait, DataFormat, MergeStrategy, StoreError, TableRecordTrait,
+};
+use cxlib_error::CxlibResultUtils;
+use cxlib_store::StorageTableCommandTrait;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 活动快照表，记录每门课程上次轮询时见到的活动及其状态。
+///
+/// 参见 [`ActivitySnapshotTrait`](cxlib_activity::ActivitySnapshotTrait).
+///
+/// 活动列表为空的课程以一行活动 ID 为空的记录标记，以便与从未记录过的课程区分。
+pub struct ActivitySnapshotTable;
+/// 活动快照表中的一行。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ActivitySnapshotData {
+    pub course: i64,
+    pub active_id: String,
+    pub status: i32,
+}
+
+impl ActivitySnapshotTable {
+    /// 空快照标记行的活动 ID.
+    pub const EMPTY_MARKER: &'static str = "";
+    pub fn has_course(db: &DataBase, course_id: i64) -> Result<bool, StoreError> {
+        Ok(db.query_count(
+            &format!(
+                "SELECT count(*) FROM {} WHERE courseid=?;",
+                Self::TABLE_NAME
+            ),
+            (1, course_id),
+        )? > 0)
+    }
+
+    /// active_id, status
+    pub fn get_snapshot(db: &DataBase, course_id: i64) -> Result<HashMap<String, i32>, StoreError> {
+        let mut query = db.prepare_cached(&format!(
+            "SELECT * FROM {} WHERE courseid=?;",
+            Self::TABLE_NAME
+        ))?;
+        query.bind((1, course_id))?;
+        let mut snapshot = HashMap::new();
+        for row in query.iter() {
+            let row = row?;
+            let active_id: &str = row.try_read("activeid")?;
+            if active_id == Self::EMPTY_MARKER {
+                continue;
+            }
+            let status: i64 = row.try_read("status")?;
+            snapshot.insert(active_id.to_owned(), status as i32);
+        }
+        Ok(snapshot)
+    }
+
+    pub fn has_activity(
+        db: &DataBase,
+        course_id: i64,
+        active_id: &str,
+    ) -> Result<bool, StoreError> {
+        Ok(db.query_count::<&[(_, sqlite::Value)]>(
+            &format!(
+                "SELECT count(*) FROM {} WHERE courseid=:courseid AND activeid=:activeid;",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":courseid", course_id.into()),
+                (":activeid", active_id.into()),
+            ][..],
+        )? > 0)
+    }
+
+    /// 所有课程的快照。
+    pub fn get_all(db: &DataBase) -> Result<Vec<ActivitySnapshotData>, StoreError> {
+        let mut query = db.prepare_cached(&format!("SELECT * FROM {};", Self::TABLE_NAME))?;
+        let mut data = Vec::new();
+        for row in query.iter() {
+            let row = row?;
+            let course = row.try_read("courseid")?;
+            let active_id: &str = row.try_read("activeid")?;
+            let status: i64 = row.try_read("status")?;
+            data.push(ActivitySnapshotData {
+                course,
+                active_id: active_id.to_owned(),
+                status: status as i32,
+            });
+        }
+        Ok(data)
+    }
+
+    pub fn delete_course(db: &DataBase, course_id: i64) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("DELETE FROM {} WHERE courseid=?;", Self::TABLE_NAME),
+            (1, course_id),
+        )
+    }
+
+    pub fn add_activity(
+        db: &DataBase,
+        course_id: i64,
+        active_id: &str,
+        status: i32,
+    ) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "INSERT OR IGNORE INTO {}(courseid,activeid,status) values(:courseid,:activeid,:status);",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":courseid", course_id.into()),
+                (":activeid", active_id.into()),
+                (":status", (status as i64).into()),
+            ][..],
+        )
+    }
+
+    pub fn update_activity(
+        db: &DataBase,
+        course_id: i64,
+        active_id: &str,
+        status: i32,
+    ) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "UPDATE {} SET status=:status WHERE courseid=:courseid AND activeid=:activeid;",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":courseid", course_id.into()),
+                (":activeid", active_id.into()),
+                (":status", (status as i64).into()),
+            ][..],
+        )
+    }
+
+    /// 以新的快照替换某课程的快照，在同一事务中完成。
+    pub fn update_snapshot(
+        db: &DataBase,
+        course_id: i64,
+        snapshot: &HashMap<String, i32>,
+    ) -> Result<(), StoreError> {
+        db.transaction(|db| {
+            Self::delete_course(db, course_id)?;
+            if snapshot.is_empty() {
+                return Self::add_activity(db, course_id, Self::EMPTY_MARKER, 0);
+            }
+            for (active_id, status) in snapshot {
+                Self::add_activity(db, course_id, active_id, *status)?;
+            }
+            Ok(())
+        })
+    }
+}
+impl StorageTableCommandTrait<DataBase> for ActivitySnapshotTable {
+    fn init(storage: &DataBase) {
+        <Self as DataBaseTableTrait>::init(storage).unwrap_or_log_default();
+    }
+    fn uninit(storage: &DataBase) -> bool {
+        !Self::is_existed(storage).unwrap_or_log_default()
+    }
+    fn clear(storage: &DataBase) {
+        Self::delete(storage).unwrap_or_log_default();
+    }
+    fn import(storage: &DataBase, content: &str) {
+        <Self as DataBaseTableTrait>::import(storage, content);
+    }
+    fn export(storage: &DataBase) -> String {
+        <Self as DataBaseTableTrait>::export(storage)
+    }
+}
+impl DataBaseTableTrait for ActivitySnapshotTable {
+    const TABLE_ARGS: &'static str =
+        "courseid INTEGER NOT NULL,activeid CHAR (50) NOT NULL,status INTEGER NOT NULL,UNIQUE(courseid,activeid)";
+    const TABLE_NAME: &'static str = "activity_snapshot";
+
+    fn import(db: &DataBase, data: &str) {
+        match Self::import_as(db, data, DataFormat::Csv, MergeStrategy::Overwrite) {
+            Ok(summary) => info!("活动快照导入完成：{summary:?}."),
+            Err(e) => warn!("活动快照导入失败：{e}."),
+        }
+    }
+
+    fn export(db: &DataBase) -> String {
+        Self::export_as(db, DataFormat::Csv).unwrap_or_else(|e| {
+            warn!("活动快照导出失败：{e}.");
+            String::new()
+        })
+    }
+}
+impl TableRecordTrait for ActivitySnapshotTable {
+    type Record = ActivitySnapshotData;
+
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError> {
+        Self::get_all(db)
+    }
+
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError> {
+        Self::has_activity(db, record.course, &record.active_id)
+    }
+
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError> {
+        if Self::has_record(db, record)? {
+            Self::update_activity(db, record.course, &record.active_id, record.status)
+        } else {
+            Self::add_activity(db, record.course, &record.active_id, record.status)
+        }
+    }
+}
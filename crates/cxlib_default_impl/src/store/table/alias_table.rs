@@ -1,111 +1,143 @@
-use crate::store::{DataBase, DataBaseTableTrait};
+use crate::store::{
+    is_constraint_error, DataBase, DataBaseTableTrait, DataFormat, MergeStrategy, StoreError,
+    TableRecordTrait,
+};
+use cxlib_error::CxlibResultUtils;
 use cxlib_store::StorageTableCommandTrait;
-use log::warn;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
 
 pub struct AliasTable;
+/// 别名表中的一行。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct AliasData {
+    pub name: String,
+    pub lid: i64,
+}
 
 impl AliasTable {
-    pub fn has_alias(db: &DataBase, alias: &str) -> bool {
-        let mut query = db
-            .prepare(format!(
-                "SELECT count(*) FROM {} WHERE name=?;",
+    pub fn has_alias(db: &DataBase, alias: &str) -> Result<bool, StoreError> {
+        Ok(db.query_count(
+            &format!("SELECT count(*) FROM {} WHERE name=?;", Self::TABLE_NAME),
+            (1, alias),
+        )? > 0)
+    }
+    /// 别名是否对应该位置。
+    pub fn has_pair(db: &DataBase, alias: &str, location_id: i64) -> Result<bool, StoreError> {
+        Ok(db.query_count::<&[(_, sqlite::Value)]>(
+            &format!(
+                "SELECT count(*) FROM {} WHERE name=:name AND lid=:lid;",
                 Self::TABLE_NAME
-            ))
-            .unwrap();
-        query.bind((1, alias)).unwrap();
-        query.next().unwrap();
-        query.read::<i64, _>(0).unwrap() > 0
+            ),
+            &[(":name", alias.into()), (":lid", location_id.into())][..],
+        )? > 0)
     }
 
-    pub fn delete_alias(db: &DataBase, alias: &str) {
-        let mut query = db
-            .prepare(format!("DELETE FROM {} WHERE name=?;", Self::TABLE_NAME))
-            .unwrap();
-        query.bind((1, alias)).unwrap();
-        query.next().unwrap();
+    pub fn delete_alias(db: &DataBase, alias: &str) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("DELETE FROM {} WHERE name=?;", Self::TABLE_NAME),
+            (1, alias),
+        )
     }
-
-    pub fn add_alias_or<O: Fn(&DataBase, &str, i64)>(
+    /// 仅删除别名与该位置的对应关系。
+    pub fn remove_alias(db: &DataBase, alias: &str, location_id: i64) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "DELETE FROM {} WHERE name=:name AND lid=:lid;",
+                Self::TABLE_NAME
+            ),
+            &[(":name", alias.into()), (":lid", location_id.into())][..],
+        )
+    }
+    /// 删除该位置的所有别名。
+    pub fn delete_aliases_of(db: &DataBase, location_id: i64) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("DELETE FROM {} WHERE lid=?;", Self::TABLE_NAME),
+            (1, location_id),
+        )
+    }
+    /// 为位置添加别名，别名已对应该位置时调用 `or`.
+    pub fn add_alias_or<O: Fn(&DataBase, &str, i64) -> Result<(), StoreError>>(
         db: &DataBase,
         alias: &str,
         location_id: i64,
         or: O,
-    ) {
-        let mut query = db
-            .prepare(format!(
+    ) -> Result<(), StoreError> {
+        match db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
                 "INSERT INTO {}(name,lid) values(:name,:lid);",
                 Self::TABLE_NAME
-            ))
-            .unwrap();
-        query
-            .bind::<&[(_, sqlite::Value)]>(
-                &[(":name", alias.into()), (":lid", location_id.into())][..],
-            )
-            .unwrap();
-        match query.next() {
-            Ok(_) => (),
-            Err(_) => or(db, alias, location_id),
-        };
+            ),
+            &[(":name", alias.into()), (":lid", location_id.into())][..],
+        ) {
+            Err(e) if is_constraint_error(&e) => or(db, alias, location_id),
+            r => r,
+        }
     }
-    pub fn update_alias(db: &DataBase, alias: &str, location_id: i64) {
-        let mut query = db
-            .prepare(format!(
-                "UPDATE {} SET name=:name,lid=:lid WHERE name=:name;",
-                Self::TABLE_NAME
-            ))
-            .unwrap();
-        query
-            .bind::<&[(_, sqlite::Value)]>(
-                &[(":name", alias.into()), (":lid", location_id.into())][..],
-            )
-            .unwrap();
-        query.next().unwrap();
+    /// 使别名仅对应该位置。
+    pub fn update_alias(db: &DataBase, alias: &str, location_id: i64) -> Result<(), StoreError> {
+        db.transaction(|db| {
+            Self::delete_alias(db, alias)?;
+            Self::add_alias_or(db, alias, location_id, |_, _, _| Ok(()))
+        })
     }
-    pub fn get_aliases(db: &DataBase, location_id: i64) -> Vec<String> {
-        let mut query = db
-            .prepare(format!("SELECT * FROM {} WHERE lid=?;", Self::TABLE_NAME))
-            .unwrap();
-        query.bind((1, location_id)).unwrap();
+    /// 所有别名。
+    pub fn get_all_aliases(db: &DataBase) -> Result<Vec<AliasData>, StoreError> {
+        let mut query = db.prepare_cached(&format!(
+            "SELECT * FROM {} ORDER BY name,lid;",
+            Self::TABLE_NAME
+        ))?;
         let mut aliases = Vec::new();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let name: &str = row.read("name");
-                aliases.push(name.to_owned());
-            } else {
-                warn!("位置解析行出错：{c:?}.");
-            }
+        for row in query.iter() {
+            let row = row?;
+            let name: &str = row.try_read("name")?;
+            let lid = row.try_read("lid")?;
+            aliases.push(AliasData {
+                name: name.to_owned(),
+                lid,
+            });
         }
-        aliases
+        Ok(aliases)
     }
-
-    pub fn get_location_id(db: &DataBase, alias: &str) -> Option<i64> {
-        if Self::has_alias(db, alias) {
-            let mut query = db
-                .prepare(format!("SELECT * FROM {} WHERE name=?;", Self::TABLE_NAME))
-                .unwrap();
-            query.bind((1, alias)).unwrap();
-            let c: Vec<sqlite::Row> = query
-                .iter()
-                .filter_map(|e| if let Ok(e) = e { Some(e) } else { None })
-                .collect();
-            let row = &c[0];
-            let location_id: i64 = row.read("lid");
-            Some(location_id)
-        } else {
-            None
+    pub fn get_aliases(db: &DataBase, location_id: i64) -> Result<Vec<String>, StoreError> {
+        let mut query =
+            db.prepare_cached(&format!("SELECT * FROM {} WHERE lid=?;", Self::TABLE_NAME))?;
+        query.bind((1, location_id))?;
+        let mut aliases = Vec::new();
+        for row in query.iter() {
+            let name: &str = row?.try_read("name")?;
+            aliases.push(name.to_owned());
         }
+        Ok(aliases)
+    }
+    /// 别名对应的所有位置 ID, 按升序排列。
+    pub fn get_location_ids(db: &DataBase, alias: &str) -> Result<Vec<i64>, StoreError> {
+        let mut query = db.prepare_cached(&format!(
+            "SELECT * FROM {} WHERE name=? ORDER BY lid;",
+            Self::TABLE_NAME
+        ))?;
+        query.bind((1, alias))?;
+        let mut location_ids = Vec::new();
+        for row in query.iter() {
+            location_ids.push(row?.try_read("lid")?);
+        }
+        Ok(location_ids)
+    }
+    /// 别名对应的位置 ID, 有多个时返回最小者。
+    pub fn get_location_id(db: &DataBase, alias: &str) -> Result<Option<i64>, StoreError> {
+        Ok(Self::get_location_ids(db, alias)?.into_iter().next())
     }
 }
 
 impl StorageTableCommandTrait<DataBase> for AliasTable {
     fn init(storage: &DataBase) {
-        <Self as DataBaseTableTrait>::init(storage);
+        <Self as DataBaseTableTrait>::init(storage).unwrap_or_log_default();
     }
     fn uninit(storage: &DataBase) -> bool {
-        !Self::is_existed(storage)
+        !Self::is_existed(storage).unwrap_or_log_default()
     }
     fn clear(storage: &DataBase) {
-        Self::delete(storage);
+        Self::delete(storage).unwrap_or_log_default();
     }
     fn import(storage: &DataBase, content: &str) {
         <Self as DataBaseTableTrait>::import(storage, content);
@@ -116,6 +148,85 @@ impl StorageTableCommandTrait<DataBase> for AliasTable {
 }
 
 impl DataBaseTableTrait for AliasTable {
-    const TABLE_ARGS: &'static str = "name CHAR (50) UNIQUE NOT NULL,lid INTEGER NOT NULL";
+    const TABLE_ARGS: &'static str =
+        "name CHAR (50) NOT NULL,lid INTEGER NOT NULL,UNIQUE(name,lid)";
     const TABLE_NAME: &'static str = "alias";
+
+    /// 旧版本中别名唯一，只能对应一个位置。升级时保留原有的对应关系。
+    fn migrate(db: &DataBase) -> Result<(), StoreError> {
+        let sql = {
+            let mut query =
+                db.prepare_cached("SELECT sql FROM sqlite_master WHERE type='table' AND name=?;")?;
+            query.bind((1, Self::TABLE_NAME))?;
+            match query.iter().next() {
+                Some(row) => row?.try_read::<&str, _>("sql")?.to_owned(),
+                None => return Ok(()),
+            }
+        };
+        if sql.contains("UNIQUE(name,lid)") {
+            return Ok(());
+        }
+        info!("正在升级数据表 {}。", Self::TABLE_NAME);
+        db.transaction(|db| {
+            db.execute(format!(
+                "ALTER TABLE {0} RENAME TO {0}_old;CREATE TABLE {0} ({1});INSERT INTO {0}(name,lid) SELECT name,lid FROM {0}_old;DROP TABLE {0}_old;",
+                Self::TABLE_NAME,
+                Self::TABLE_ARGS
+            ))?;
+            Ok(())
+        })
+    }
+
+    fn import(db: &DataBase, data: &str) {
+        match Self::import_as(db, data, DataFormat::Csv, MergeStrategy::Overwrite) {
+            Ok(summary) => info!("别名导入完成：{summary:?}."),
+            Err(e) => warn!("别名导入失败：{e}."),
+        }
+    }
+
+    fn export(db: &DataBase) -> String {
+        Self::export_as(db, DataFormat::Csv).unwrap_or_else(|e| {
+            warn!("别名导出失败：{e}.");
+            String::new()
+        })
+    }
+}
+impl TableRecordTrait for AliasTable {
+    type Record = AliasData;
+
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError> {
+        Self::get_all_aliases(db)
+    }
+
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError> {
+        Self::has_pair(db, &record.name, record.lid)
+    }
+
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError> {
+        Self::add_alias_or(db, &record.name, record.lid, |_, _, _| Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{AliasTable, DataBase, DataBaseTableTrait};
+
+    #[test]
+    fn test_migrate_unique_alias() {
+        let db = DataBase::in_memory().unwrap();
+        db.execute("CREATE TABLE alias (name CHAR (50) UNIQUE NOT NULL,lid INTEGER NOT NULL);INSERT INTO alias(name,lid) values('教室',1);")
+            .unwrap();
+        db.add_table::<AliasTable>().unwrap();
+        assert_eq!(AliasTable::get_location_ids(&db, "教室").unwrap(), vec![1]);
+        AliasTable::add_alias_or(&db, "教室", 0, |_, _, _| Ok(())).unwrap();
+        assert_eq!(
+            AliasTable::get_location_ids(&db, "教室").unwrap(),
+            vec![0, 1]
+        );
+        AliasTable::update_alias(&db, "教室", 2).unwrap();
+        assert_eq!(AliasTable::get_location_ids(&db, "教室").unwrap(), vec![2]);
+        // 已是新的表结构时不再升级。
+        <AliasTable as DataBaseTableTrait>::migrate(&db).unwrap();
+        assert_eq!(AliasTable::get_all_aliases(&db).unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,217 @@
+use crate::store::{DataBase, DataBaseTableTrait, StoreError, TableRecordTrait};
+use cxlib_error::CxlibResultUtils;
+use cxlib_store::StorageTableCommandTrait;
+use cxlib_types::Course;
+use serde::{Deserialize, Serialize};
+
+/// 课程缓存表，按用户 uid 缓存课程列表。
+///
+/// 参见 [`CourseCacheTrait`](cxlib_types::CourseCacheTrait).
+pub struct CourseTable;
+/// 课程缓存表中的一行。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CourseData {
+    pub uid: String,
+    pub id: i64,
+    pub class_id: i64,
+    pub teacher: String,
+    pub image_url: String,
+    pub name: String,
+    pub term: Option<String>,
+    pub archived: bool,
+    pub time: u64,
+}
+impl CourseData {
+    pub fn to_course(&self) -> Course {
+        Course::new(
+            self.id,
+            self.class_id,
+            &self.teacher,
+            &self.image_url,
+            &self.name,
+        )
+        .with_term(self.term.clone())
+        .with_archived(self.archived)
+    }
+}
+
+impl CourseTable {
+    /// 课程列表及缓存时间。同一用户的课程缓存时间相同，没有缓存时返回 `None`.
+    pub fn get_courses(db: &DataBase, uid: &str) -> Result<Option<(Vec<Course>, u64)>, StoreError> {
+        let mut query =
+            db.prepare_cached(&format!("SELECT * FROM {} WHERE uid=?;", Self::TABLE_NAME))?;
+        query.bind((1, uid))?;
+        let mut courses = Vec::new();
+        let mut time = None;
+        for row in query.iter() {
+            let row = row?;
+            let id: i64 = row.try_read("id")?;
+            let class_id: i64 = row.try_read("classid")?;
+            let teacher: &str = row.try_read("teacher")?;
+            let image_url: &str = row.try_read("imageurl")?;
+            let name: &str = row.try_read("name")?;
+            let term: Option<&str> = row.try_read("term")?;
+            let archived: i64 = row.try_read("archived")?;
+            let row_time: i64 = row.try_read("time")?;
+            courses.push(
+                Course::new(id, class_id, teacher, image_url, name)
+                    .with_term(term.map(|t| t.to_owned()))
+                    .with_archived(archived != 0),
+            );
+            time = Some(time.map_or(row_time, |t: i64| t.min(row_time)));
+        }
+        Ok(time.map(|time| (courses, time as u64)))
+    }
+
+    /// 所有用户的缓存。
+    pub fn get_all(db: &DataBase) -> Result<Vec<CourseData>, StoreError> {
+        let mut query = db.prepare_cached(&format!("SELECT * FROM {};", Self::TABLE_NAME))?;
+        let mut data = Vec::new();
+        for row in query.iter() {
+            let row = row?;
+            let uid: &str = row.try_read("uid")?;
+            let teacher: &str = row.try_read("teacher")?;
+            let image_url: &str = row.try_read("imageurl")?;
+            let name: &str = row.try_read("name")?;
+            let term: Option<&str> = row.try_read("term")?;
+            let archived: i64 = row.try_read("archived")?;
+            let time: i64 = row.try_read("time")?;
+            data.push(CourseData {
+                uid: uid.to_owned(),
+                id: row.try_read("id")?,
+                class_id: row.try_read("classid")?,
+                teacher: teacher.to_owned(),
+                image_url: image_url.to_owned(),
+                name: name.to_owned(),
+                term: term.map(|t| t.to_owned()),
+                archived: archived != 0,
+                time: time as u64,
+            });
+        }
+        Ok(data)
+    }
+
+    pub fn has_course(
+        db: &DataBase,
+        uid: &str,
+        id: i64,
+        class_id: i64,
+    ) -> Result<bool, StoreError> {
+        Ok(db.query_count::<&[(_, sqlite::Value)]>(
+            &format!(
+                "SELECT count(*) FROM {} WHERE uid=:uid AND id=:id AND classid=:classid;",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":uid", uid.into()),
+                (":id", id.into()),
+                (":classid", class_id.into()),
+            ][..],
+        )? > 0)
+    }
+
+    pub fn add_course(
+        db: &DataBase,
+        uid: &str,
+        course: &Course,
+        time: u64,
+    ) -> Result<(), StoreError> {
+        let term = match course.get_term() {
+            Some(term) => term.into(),
+            None => sqlite::Value::Null,
+        };
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "INSERT OR IGNORE INTO {}(uid,id,classid,teacher,imageurl,name,term,archived,time) \
+                 values(:uid,:id,:classid,:teacher,:imageurl,:name,:term,:archived,:time);",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":uid", uid.into()),
+                (":id", course.get_id().into()),
+                (":classid", course.get_class_id().into()),
+                (":teacher", course.get_teacher().into()),
+                (":imageurl", course.get_image_url().into()),
+                (":name", course.get_name().into()),
+                (":term", term),
+                (":archived", (course.is_archived() as i64).into()),
+                (":time", (time as i64).into()),
+            ][..],
+        )
+    }
+
+    pub fn delete_course(
+        db: &DataBase,
+        uid: &str,
+        id: i64,
+        class_id: i64,
+    ) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "DELETE FROM {} WHERE uid=:uid AND id=:id AND classid=:classid;",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":uid", uid.into()),
+                (":id", id.into()),
+                (":classid", class_id.into()),
+            ][..],
+        )
+    }
+
+    /// 删除某用户的所有缓存。
+    pub fn delete_user(db: &DataBase, uid: &str) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("DELETE FROM {} WHERE uid=?;", Self::TABLE_NAME),
+            (1, uid),
+        )
+    }
+
+    /// 以新的课程列表替换某用户的缓存，在同一事务中完成。
+    pub fn update_courses(
+        db: &DataBase,
+        uid: &str,
+        courses: &[Course],
+        time: u64,
+    ) -> Result<(), StoreError> {
+        db.transaction(|db| {
+            Self::delete_user(db, uid)?;
+            for course in courses {
+                Self::add_course(db, uid, course, time)?;
+            }
+            Ok(())
+        })
+    }
+}
+impl StorageTableCommandTrait<DataBase> for CourseTable {
+    fn init(storage: &DataBase) {
+        <Self as DataBaseTableTrait>::init(storage).unwrap_or_log_default();
+    }
+    fn uninit(storage: &DataBase) -> bool {
+        !Self::is_existed(storage).unwrap_or_log_default()
+    }
+    fn clear(storage: &DataBase) {
+        Self::delete(storage).unwrap_or_log_default();
+    }
+}
+impl DataBaseTableTrait for CourseTable {
+    const TABLE_ARGS: &'static str =
+        "uid CHAR (50) NOT NULL,id INTEGER NOT NULL,classid INTEGER NOT NULL,teacher TEXT NOT NULL,imageurl TEXT NOT NULL,name TEXT NOT NULL,term TEXT,archived INTEGER NOT NULL,time INTEGER NOT NULL,UNIQUE(uid,id,classid)";
+    const TABLE_NAME: &'static str = "course";
+}
+impl TableRecordTrait for CourseTable {
+    type Record = CourseData;
+
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError> {
+        Self::get_all(db)
+    }
+
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError> {
+        Self::has_course(db, &record.uid, record.id, record.class_id)
+    }
+
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError> {
+        Self::delete_course(db, &record.uid, record.id, record.class_id)?;
+        Self::add_course(db, &record.uid, &record.to_course(), record.time)
+    }
+}
@@ -1,75 +1,211 @@
-use crate::store::{DataBase, DataBaseTableTrait};
-use cxlib_store::StorageTableCommandTrait;
+use crate::store::{DataBase, DataBaseTableTrait, KVConfigTable, StoreError, TableRecordTrait};
+use cxlib_activity::ExclusionPolicy;
+use cxlib_error::CxlibResultUtils;
+use cxlib_store::{Config, StorageTableCommandTrait};
 use log::warn;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
 pub struct ExcludeTable;
+/// 排除表中的一行。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ExcludeData {
+    pub id: i64,
+}
 
 impl ExcludeTable {
-    pub fn has_exclude(db: &DataBase, id: i64) -> bool {
-        let mut query = db
-            .prepare(format!(
-                "SELECT count(*) FROM {} WHERE id=?;",
-                Self::TABLE_NAME
-            ))
-            .unwrap();
-        query.bind((1, id)).unwrap();
-        query.next().unwrap();
-        query.read::<i64, _>(0).unwrap() > 0
+    pub fn has_exclude(db: &DataBase, id: i64) -> Result<bool, StoreError> {
+        Ok(db.query_count(
+            &format!("SELECT count(*) FROM {} WHERE id=?;", Self::TABLE_NAME),
+            (1, id),
+        )? > 0)
     }
 
-    pub fn get_excludes(db: &DataBase) -> HashSet<i64> {
-        let mut query = db
-            .prepare(format!("SELECT * FROM {};", Self::TABLE_NAME))
-            .unwrap();
+    pub fn get_excludes(db: &DataBase) -> Result<HashSet<i64>, StoreError> {
+        let mut query = db.prepare_cached(&format!("SELECT * FROM {};", Self::TABLE_NAME))?;
         let mut excludes = HashSet::new();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let id = row.read("id");
-                excludes.insert(id);
-            } else {
-                warn!("账号解析行出错：{c:?}.");
-            }
+        for row in query.iter() {
+            excludes.insert(row?.try_read("id")?);
         }
-        excludes
+        Ok(excludes)
     }
 
-    pub fn add_exclude(db: &DataBase, id: i64) {
-        let mut query = db
-            .prepare(format!("INSERT INTO {}(id) values(:id);", Self::TABLE_NAME))
-            .unwrap();
-        query
-            .bind::<&[(_, sqlite::Value)]>(&[(":id", id.into())][..])
-            .unwrap();
-        let _ = query.next();
+    pub fn add_exclude(db: &DataBase, id: i64) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("INSERT OR IGNORE INTO {}(id) values(?);", Self::TABLE_NAME),
+            (1, id),
+        )
     }
 
-    pub fn delete_exclude(db: &DataBase, id: i64) {
-        if Self::has_exclude(db, id) {
-            let mut query = db
-                .prepare(format!("DELETE FROM {} WHERE id=?;", Self::TABLE_NAME))
-                .unwrap();
-            query.bind((1, id)).unwrap();
-            query.next().unwrap();
-        }
+    pub fn delete_exclude(db: &DataBase, id: i64) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("DELETE FROM {} WHERE id=?;", Self::TABLE_NAME),
+            (1, id),
+        )
     }
 
-    pub fn update_excludes<'a, I: IntoIterator<Item = &'a i64>>(db: &DataBase, excludes: I) {
-        Self::delete(db);
-        for exclude in excludes {
-            Self::add_exclude(db, *exclude);
+    /// 以新的列表替换排除列表，在同一事务中完成。
+    pub fn update_excludes<'a, I: IntoIterator<Item = &'a i64>>(
+        db: &DataBase,
+        excludes: I,
+    ) -> Result<(), StoreError> {
+        db.transaction(|db| {
+            Self::delete(db)?;
+            for exclude in excludes {
+                Self::add_exclude(db, *exclude)?;
+            }
+            Ok(())
+        })
+    }
+    /// 学期开始时间，毫秒时间戳。
+    pub const TERM_START_KEY: &'static str = "exclude_term_start";
+    /// 课程名排除模式，以 `/` 分隔。
+    pub const NAME_PATTERNS_KEY: &'static str = "exclude_name_patterns";
+    /// 固定的课程 ID, 以 `,` 分隔。
+    pub const PINNED_KEY: &'static str = "exclude_pinned";
+    /// 手动排除的课程 ID, 以 `,` 分隔。
+    pub const MANUAL_KEY: &'static str = "exclude_manual";
+    fn parse_id_set(value: &str) -> HashSet<i64> {
+        value
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| {
+                s.parse()
+                    .map_err(|e| warn!("课程号 `{s}` 解析失败，已跳过。错误信息：{e}."))
+                    .ok()
+            })
+            .collect()
+    }
+    fn id_set_to_string(ids: &HashSet<i64>) -> String {
+        let mut ids = ids.iter().collect::<Vec<_>>();
+        ids.sort();
+        ids.iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+    /// 从键值对中读取排除策略，`get` 按键获取值。
+    ///
+    /// 与存储后端无关，[`MemoryStore`](crate::store::MemoryStore) 等后端也以同样的键保存排除策略。
+    pub fn read_exclusion_policy<E>(
+        get: impl Fn(&str) -> Result<Option<String>, E>,
+    ) -> Result<ExclusionPolicy, E> {
+        let mut policy = ExclusionPolicy::from_config(&Config::get());
+        if let Some(term_start) = get(Self::TERM_START_KEY)? {
+            policy.term_start_mills = term_start
+                .trim()
+                .parse()
+                .map_err(|e| warn!("学期开始时间 `{term_start}` 解析失败，已忽略。错误信息：{e}."))
+                .ok();
+        }
+        if let Some(patterns) = get(Self::NAME_PATTERNS_KEY)? {
+            policy.name_patterns = patterns
+                .split('/')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
+        if let Some(pinned) = get(Self::PINNED_KEY)? {
+            policy.pinned = Self::parse_id_set(&pinned);
         }
+        if let Some(manual) = get(Self::MANUAL_KEY)? {
+            policy.manual = Self::parse_id_set(&manual);
+        }
+        Ok(policy)
+    }
+    /// 获取排除策略，排除策略保存在 [`KVConfigTable`] 中。
+    ///
+    /// 最长不活跃天数属于配置项，始终取自 [`Config`], 不保存在数据库中。
+    pub fn get_exclusion_policy(db: &DataBase) -> Result<ExclusionPolicy, StoreError> {
+        if !KVConfigTable::is_existed(db)? {
+            return Ok(ExclusionPolicy::from_config(&Config::get()));
+        }
+        Self::read_exclusion_policy(|key| KVConfigTable::get_by_key(db, key))
+    }
+    /// 保存排除策略。其中的 `max_inactive_days` 不会被保存，请通过配置修改。
+    pub fn set_exclusion_policy(db: &DataBase, policy: &ExclusionPolicy) -> Result<(), StoreError> {
+        db.add_table::<KVConfigTable>()?;
+        db.transaction(|db| {
+            match policy.term_start_mills {
+                Some(term_start) => KVConfigTable::insert_or(
+                    db,
+                    Self::TERM_START_KEY,
+                    &term_start.to_string(),
+                    KVConfigTable::update,
+                )?,
+                None => KVConfigTable::remove(db, Self::TERM_START_KEY)?,
+            }
+            KVConfigTable::insert_or(
+                db,
+                Self::NAME_PATTERNS_KEY,
+                &policy.name_patterns.join("/"),
+                KVConfigTable::update,
+            )?;
+            KVConfigTable::insert_or(
+                db,
+                Self::PINNED_KEY,
+                &Self::id_set_to_string(&policy.pinned),
+                KVConfigTable::update,
+            )?;
+            KVConfigTable::insert_or(
+                db,
+                Self::MANUAL_KEY,
+                &Self::id_set_to_string(&policy.manual),
+                KVConfigTable::update,
+            )
+        })
+    }
+    /// 固定某课程，使其永不被排除。
+    pub fn pin_course(db: &DataBase, id: i64) -> Result<(), StoreError> {
+        db.transaction(|db| {
+            let mut policy = Self::get_exclusion_policy(db)?;
+            policy.manual.remove(&id);
+            policy.pinned.insert(id);
+            Self::set_exclusion_policy(db, &policy)?;
+            Self::delete_exclude(db, id)
+        })
+    }
+    /// 取消对某课程的固定。
+    pub fn unpin_course(db: &DataBase, id: i64) -> Result<(), StoreError> {
+        db.transaction(|db| {
+            let mut policy = Self::get_exclusion_policy(db)?;
+            if policy.pinned.remove(&id) {
+                Self::set_exclusion_policy(db, &policy)?;
+            }
+            Ok(())
+        })
+    }
+    /// 手动排除某课程。与 [`add_exclude`](Self::add_exclude) 不同，手动排除不会在更新排除列表时被撤销。
+    pub fn exclude_manually(db: &DataBase, id: i64) -> Result<(), StoreError> {
+        db.transaction(|db| {
+            let mut policy = Self::get_exclusion_policy(db)?;
+            policy.pinned.remove(&id);
+            policy.manual.insert(id);
+            Self::set_exclusion_policy(db, &policy)?;
+            Self::add_exclude(db, id)
+        })
+    }
+    /// 取消对某课程的手动排除。
+    pub fn cancel_manual_exclude(db: &DataBase, id: i64) -> Result<(), StoreError> {
+        db.transaction(|db| {
+            let mut policy = Self::get_exclusion_policy(db)?;
+            if policy.manual.remove(&id) {
+                Self::set_exclusion_policy(db, &policy)?;
+            }
+            Ok(())
+        })
     }
 }
 impl StorageTableCommandTrait<DataBase> for ExcludeTable {
     fn init(storage: &DataBase) {
-        <Self as DataBaseTableTrait>::init(storage);
+        <Self as DataBaseTableTrait>::init(storage).unwrap_or_log_default();
     }
     fn uninit(storage: &DataBase) -> bool {
-        !Self::is_existed(storage)
+        !Self::is_existed(storage).unwrap_or_log_default()
     }
     fn clear(storage: &DataBase) {
-        Self::delete(storage);
+        Self::delete(storage).unwrap_or_log_default();
     }
     fn import(storage: &DataBase, content: &str) {
         <Self as DataBaseTableTrait>::import(storage, content);
@@ -85,11 +221,50 @@ impl DataBaseTableTrait for ExcludeTable {
     fn import(db: &DataBase, data: &str) {
         let data = crate::utils::parse(data);
         for id in data {
-            Self::add_exclude(db, id)
+            Self::add_exclude(db, id).unwrap_or_log_default()
         }
     }
 
     fn export(db: &DataBase) -> String {
-        crate::utils::to_string(Self::get_excludes(db).into_iter())
+        crate::utils::to_string(Self::get_excludes(db).unwrap_or_log_default().into_iter())
+    }
+}
+impl TableRecordTrait for ExcludeTable {
+    type Record = ExcludeData;
+
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError> {
+        Ok(Self::get_excludes(db)?
+            .into_iter()
+            .map(|id| ExcludeData { id })
+            .collect())
+    }
+
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError> {
+        Self::has_exclude(db, record.id)
+    }
+
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError> {
+        Self::add_exclude(db, record.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{DataBase, ExcludeTable, KVConfigTable};
+    use cxlib_store::Config;
+
+    #[test]
+    fn test_exclusion_policy_keeps_config_days() {
+        let db = DataBase::in_memory().unwrap();
+        let mut policy = ExcludeTable::get_exclusion_policy(&db).unwrap();
+        policy.max_inactive_days = Some(1);
+        policy.pinned.insert(42);
+        ExcludeTable::set_exclusion_policy(&db, &policy).unwrap();
+        // 旧版本写入的键也不应覆盖配置。
+        KVConfigTable::insert_or(&db, Config::MAX_INACTIVE_DAYS, "1", KVConfigTable::update)
+            .unwrap();
+        let policy = ExcludeTable::get_exclusion_policy(&db).unwrap();
+        assert_eq!(policy.max_inactive_days, Config::get().max_inactive_days);
+        assert!(policy.pinned.contains(&42));
     }
 }
@@ -1,12 +1,15 @@
-use crate::store::{DataBase, DataBaseTableTrait,StoreError };
+use crate::store::{
+    is_constraint_error, DataBase, DataBaseTableTrait, StoreError, TableRecordTrait,
+};
+use cxlib_error::CxlibResultUtils;
 use cxlib_store::StorageTableCommandTrait;
-use log::warn;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::str::FromStr;
 
 pub struct KVConfigTable;
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct KVPair {
     pub key: String,
     pub value: String,
@@ -18,173 +21,129 @@ impl From<(String, String)> for KVPair {
 }
 impl Display for KVPair {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{},{}", self.key, self.value)
+        write!(f, "{} = {}", self.key, self.value)
     }
 }
 impl FromStr for KVPair {
     type Err = StoreError;
 
+    /// 以第一个 `=` 分隔键和值，值可以为空或包含 `=`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s
-            .split('=')
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<_>>();
-        if s.len() < 2 {
-            Err(StoreError::ParseError(
+        match s.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok(Self {
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            }),
+            _ => Err(StoreError::ParseError(
                 "键值表解析出错！格式为 `key = value`.".to_string(),
-            ))?
-        } else {
-            let key = s[0].to_string();
-            let value = s[1].to_string();
-            Ok(Self { key, value })
+            ))?,
         }
     }
 }
 impl KVConfigTable {
-    pub fn keys(db: &DataBase) -> HashSet<String> {
-        let mut query = db
-            .prepare(format!("SELECT * FROM {};", Self::TABLE_NAME))
-            .unwrap();
-        let mut s = HashSet::new();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let key: &str = row.read("key");
-                s.insert(key.into());
-            } else {
-                warn!("kv_config 解析行出错：{c:?}.");
-            }
-        }
-        s
-    }
-    pub fn values(db: &DataBase) -> Vec<String> {
-        let mut query = db
-            .prepare(format!("SELECT * FROM {};", Self::TABLE_NAME))
-            .unwrap();
-        let mut s = Vec::new();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let value: &str = row.read("value");
-                s.push(value.into());
-            } else {
-                warn!("kv_config 解析行出错：{c:?}.");
-            }
-        }
-        s
+    pub fn keys(db: &DataBase) -> Result<HashSet<String>, StoreError> {
+        Ok(Self::get_as_map(db)?.into_keys().collect())
+    }
+    pub fn values(db: &DataBase) -> Result<Vec<String>, StoreError> {
+        Ok(Self::get_as_map(db)?.into_values().collect())
     }
-    pub fn get_as_map_by_keys_str(db: &DataBase, keys: &str) -> HashMap<String, String> {
+    pub fn get_as_map_by_keys_str(
+        db: &DataBase,
+        keys: &str,
+    ) -> Result<HashMap<String, String>, StoreError> {
         let str_list = keys.split(',').map(|a| a.trim()).collect::<Vec<&str>>();
         let mut s = HashMap::new();
         for key in str_list {
-            if let Some(session) = Self::get_by_key(db, key) {
-                s.insert(key.to_string(), session);
+            if let Some(value) = Self::get_by_key(db, key)? {
+                s.insert(key.to_string(), value);
             }
         }
-        s
-    }
-    pub fn get_by_key(db: &DataBase, key: &str) -> Option<String> {
-        let mut query = db
-            .prepare(format!("SELECT * FROM {} WHERE key=?;", Self::TABLE_NAME))
-            .unwrap();
-        query.bind((1, key)).unwrap();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let value: &str = row.read("value");
-                return Some(value.into());
-            } else {
-                warn!("kv_config 解析行出错：{c:?}.");
-            }
+        Ok(s)
+    }
+    pub fn get_by_key(db: &DataBase, key: &str) -> Result<Option<String>, StoreError> {
+        let mut query =
+            db.prepare_cached(&format!("SELECT * FROM {} WHERE key=?;", Self::TABLE_NAME))?;
+        query.bind((1, key))?;
+        if let Some(row) = query.iter().next() {
+            let value: &str = row?.try_read("value")?;
+            return Ok(Some(value.into()));
         }
-        None
+        Ok(None)
     }
-    pub fn get_as_map(db: &DataBase) -> HashMap<String, String> {
-        let mut query = db
-            .prepare(format!("SELECT * FROM {};", Self::TABLE_NAME))
-            .unwrap();
+    pub fn get_as_map(db: &DataBase) -> Result<HashMap<String, String>, StoreError> {
+        let mut query = db.prepare_cached(&format!("SELECT * FROM {};", Self::TABLE_NAME))?;
         let mut s = HashMap::new();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let key: &str = row.read("key");
-                let value: &str = row.read("value");
-                s.insert(key.into(), value.into());
-            } else {
-                warn!("kv_config 解析行出错：{c:?}.");
-            }
+        for row in query.iter() {
+            let row = row?;
+            let key: &str = row.try_read("key")?;
+            let value: &str = row.try_read("value")?;
+            s.insert(key.into(), value.into());
         }
-        s
+        Ok(s)
     }
-    pub fn has_key(db: &DataBase, key: &str) -> bool {
-        let mut query = db
-            .prepare(format!(
-                "SELECT count(*) FROM {} WHERE key=?;",
-                Self::TABLE_NAME
-            ))
-            .unwrap();
-        query.bind((1, key)).unwrap();
-        query.next().unwrap();
-        query.read::<i64, _>(0).unwrap() > 0
+    pub fn has_key(db: &DataBase, key: &str) -> Result<bool, StoreError> {
+        Ok(db.query_count(
+            &format!("SELECT count(*) FROM {} WHERE key=?;", Self::TABLE_NAME),
+            (1, key),
+        )? > 0)
     }
 
-    pub fn remove(db: &DataBase, key: &str) {
-        if Self::has_key(db, key) {
-            let mut query = db
-                .prepare(format!("DELETE FROM {} WHERE key=?;", Self::TABLE_NAME))
-                .unwrap();
-            query.bind((1, key)).unwrap();
-            query.next().unwrap();
-        }
+    pub fn remove(db: &DataBase, key: &str) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("DELETE FROM {} WHERE key=?;", Self::TABLE_NAME),
+            (1, key),
+        )
     }
 
-    pub fn insert_or<O: Fn(&DataBase, &str, &str)>(db: &DataBase, key: &str, value: &str, or: O) {
-        let mut query = db
-            .prepare(format!(
+    pub fn insert_or<O: Fn(&DataBase, &str, &str) -> Result<(), StoreError>>(
+        db: &DataBase,
+        key: &str,
+        value: &str,
+        or: O,
+    ) -> Result<(), StoreError> {
+        match db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
                 "INSERT INTO {}(key,value) values(:key,:value);",
                 Self::TABLE_NAME
-            ))
-            .unwrap();
-        query
-            .bind::<&[(_, sqlite::Value)]>(&[(":value", value.into()), (":key", key.into())][..])
-            .unwrap();
-        match query.next() {
-            Ok(_) => (),
-            Err(_) => or(db, key, value),
-        };
+            ),
+            &[(":value", value.into()), (":key", key.into())][..],
+        ) {
+            Err(e) if is_constraint_error(&e) => or(db, key, value),
+            r => r,
+        }
     }
 
-    pub fn update(db: &DataBase, key: &str, value: &str) {
-        let mut query = db
-            .prepare(format!(
+    pub fn update(db: &DataBase, key: &str, value: &str) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
                 "UPDATE {} SET value=:value WHERE key=:key;",
                 Self::TABLE_NAME
-            ))
-            .unwrap();
-        query
-            .bind::<&[(_, sqlite::Value)]>(&[(":key", key.into()), (":value", value.into())][..])
-            .unwrap();
-        query.next().unwrap();
+            ),
+            &[(":key", key.into()), (":value", value.into())][..],
+        )
     }
 
-    pub fn get_pairs(db: &DataBase) -> HashSet<KVPair> {
-        Self::get_as_map(db)
+    pub fn get_pairs(db: &DataBase) -> Result<HashSet<KVPair>, StoreError> {
+        Ok(Self::get_as_map(db)?
             .into_iter()
             .map(|pair| pair.into())
-            .collect()
+            .collect())
     }
 
-    pub fn get_pair(db: &DataBase, key: &str) -> Option<KVPair> {
-        Self::get_by_key(db, key).map(|value| (key.to_owned(), value).into())
+    pub fn get_pair(db: &DataBase, key: &str) -> Result<Option<KVPair>, StoreError> {
+        Ok(Self::get_by_key(db, key)?.map(|value| (key.to_owned(), value).into()))
     }
 }
 
 impl StorageTableCommandTrait<DataBase> for KVConfigTable {
     fn init(storage: &DataBase) {
-        <Self as DataBaseTableTrait>::init(storage);
+        <Self as DataBaseTableTrait>::init(storage).unwrap_or_log_default();
     }
     fn uninit(storage: &DataBase) -> bool {
-        !Self::is_existed(storage)
+        !Self::is_existed(storage).unwrap_or_log_default()
     }
     fn clear(storage: &DataBase) {
-        Self::delete(storage);
+        Self::delete(storage).unwrap_or_log_default();
     }
     fn import(storage: &DataBase, content: &str) {
         <Self as DataBaseTableTrait>::import(storage, content);
@@ -198,14 +157,50 @@ impl DataBaseTableTrait for KVConfigTable {
     const TABLE_NAME: &'static str = "kv_config";
 
     fn import(db: &DataBase, data: &str) {
-        db.add_table::<Self>();
         let data = crate::utils::parse::<_, KVPair>(data);
-        for KVPair { key, value } in data {
-            Self::insert_or(db, &key, &value, Self::update)
-        }
+        db.transaction(|db| {
+            db.add_table::<Self>()?;
+            for KVPair { key, value } in data {
+                Self::insert_or(db, &key, &value, Self::update)?
+            }
+            Ok(())
+        })
+        .unwrap_or_log_default()
     }
 
     fn export(db: &DataBase) -> String {
-        crate::utils::to_string(Self::get_pairs(db).into_iter())
+        crate::utils::to_string(Self::get_pairs(db).unwrap_or_log_default().into_iter())
+    }
+}
+impl TableRecordTrait for KVConfigTable {
+    type Record = KVPair;
+
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError> {
+        Ok(Self::get_pairs(db)?.into_iter().collect())
+    }
+
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError> {
+        Self::has_key(db, &record.key)
+    }
+
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError> {
+        Self::insert_or(db, &record.key, &record.value, Self::update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::KVPair;
+
+    #[test]
+    fn test_kv_pair_round_trip() {
+        for (key, value) in [("k", "v"), ("k", ""), ("url", "a=b&c=d")] {
+            let pair = KVPair {
+                key: key.to_owned(),
+                value: value.to_owned(),
+            };
+            assert_eq!(pair.to_string().parse::<KVPair>().unwrap(), pair);
+        }
+        assert!(" = v".parse::<KVPair>().is_err());
     }
 }
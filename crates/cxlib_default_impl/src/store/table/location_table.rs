@@ -1,10 +1,46 @@
-use crate::store::{AliasTable, DataBase, DataBaseTableTrait, StoreError};
+use crate::store::{
+    is_constraint_error, AliasTable, DataBase, DataBaseTableTrait, LocationTagTable,
+    LocationUsageTable, StoreError, TableRecordTrait,
+};
+use cxlib_error::CxlibResultUtils;
 use cxlib_store::StorageTableCommandTrait;
 use cxlib_types::Location;
 use log::{debug, warn};
+use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt::Display, str::FromStr};
 
 pub struct LocationTable;
+/// 位置表中的一行。别名由 [`AliasData`](crate::store::AliasData) 单独导入导出。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LocationData {
+    pub lid: i64,
+    pub course: i64,
+    pub addr: String,
+    pub lon: String,
+    pub lat: String,
+    pub alt: String,
+}
+impl LocationData {
+    pub fn new(lid: i64, course: i64, location: Location) -> Self {
+        let [addr, lon, lat, alt] = location.to_owned_fields();
+        Self {
+            lid,
+            course,
+            addr,
+            lon,
+            lat,
+            alt,
+        }
+    }
+    pub fn to_location(&self) -> Result<Location, StoreError> {
+        Ok(Location::from_owned_fields([
+            self.addr.clone(),
+            self.lon.clone(),
+            self.lat.clone(),
+            self.alt.clone(),
+        ])?)
+    }
+}
 pub struct LocationAndAliasesPair {
     pub course: i64,
     pub location: Location,
@@ -51,167 +87,197 @@ impl Display for LocationAndAliasesPair {
     }
 }
 impl LocationTable {
-    pub fn has_location(db: &DataBase, location_id: i64) -> bool {
-        let mut query = db
-            .prepare(format!(
-                "SELECT count(*) FROM {} WHERE lid=?;",
-                Self::TABLE_NAME
-            ))
-            .unwrap();
-        query.bind((1, location_id)).unwrap();
-        query.next().unwrap();
-        query.read::<i64, _>(0).unwrap() > 0
+    fn read_location(row: &sqlite::Row) -> Result<Location, StoreError> {
+        let [addr, lon, lat, alt] = ["addr", "lon", "lat", "alt"]
+            .map(|column| row.try_read::<&str, _>(column).map(ToOwned::to_owned));
+        Ok(Location::from_owned_fields([addr?, lon?, lat?, alt?])?.to_preprocessed())
     }
-    pub fn add_location_or<O: Fn(&DataBase, i64, i64, &Location)>(
+    /// 读取一行中的位置，无效的行（如旧版本写入的空经纬度）记录警告后跳过，不影响其他行。
+    fn read_valid_location(row: &sqlite::Row, location_id: i64) -> Option<Location> {
+        match Self::read_location(row) {
+            Ok(location) => Some(location),
+            Err(e) => {
+                warn!("位置 `{location_id}` 无效，已跳过：{e}.");
+                None
+            }
+        }
+    }
+    fn location_values(location: &Location) -> [sqlite::Value; 4] {
+        location.clone().to_owned_fields().map(Into::into)
+    }
+    pub fn has_location(db: &DataBase, location_id: i64) -> Result<bool, StoreError> {
+        Ok(db.query_count(
+            &format!("SELECT count(*) FROM {} WHERE lid=?;", Self::TABLE_NAME),
+            (1, location_id),
+        )? > 0)
+    }
+    pub fn add_location_or<O: Fn(&DataBase, i64, i64, &Location) -> Result<(), StoreError>>(
         db: &DataBase,
         location_id: i64,
         course_id: i64,
         location: &Location,
         or: O,
-    ) {
-        let addr = location.get_addr();
-        let lat = location.get_lat();
-        let lon = location.get_lon();
-        let alt = location.get_alt();
-        let mut query =db.prepare(format!("INSERT INTO {}(lid,courseid,addr,lat,lon,alt) values(:lid,:courseid,:addr,:lat,:lon,:alt);",Self::TABLE_NAME)).unwrap();
-        query
-            .bind::<&[(_, sqlite::Value)]>(
-                &[
-                    (":lid", location_id.into()),
-                    (":courseid", course_id.into()),
-                    (":addr", addr.into()),
-                    (":lat", lat.into()),
-                    (":lon", lon.into()),
-                    (":alt", alt.into()),
-                ][..],
-            )
-            .unwrap();
-        match query.next() {
-            Ok(_) => (),
-            Err(_) => or(db, location_id, course_id, location),
+    ) -> Result<(), StoreError> {
+        let [addr, lon, lat, alt] = Self::location_values(location);
+        match db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "INSERT INTO {}(lid,courseid,addr,lat,lon,alt) values(:lid,:courseid,:addr,:lat,:lon,:alt);",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":lid", location_id.into()),
+                (":courseid", course_id.into()),
+                (":addr", addr),
+                (":lat", lat),
+                (":lon", lon),
+                (":alt", alt),
+            ][..],
+        ) {
+            Err(e) if is_constraint_error(&e) => or(db, location_id, course_id, location),
+            r => r,
         }
     }
+    pub fn update_location(
+        db: &DataBase,
+        location_id: i64,
+        course_id: i64,
+        location: &Location,
+    ) -> Result<(), StoreError> {
+        let [addr, lon, lat, alt] = Self::location_values(location);
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "UPDATE {} SET courseid=:courseid,addr=:addr,lat=:lat,lon=:lon,alt=:alt WHERE lid=:lid;",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":lid", location_id.into()),
+                (":courseid", course_id.into()),
+                (":addr", addr),
+                (":lat", lat),
+                (":lon", lon),
+                (":alt", alt),
+            ][..],
+        )
+    }
     /// 添加位置，返回 LocationId.
-    pub fn insert_location(db: &DataBase, course_id: i64, location: &Location) -> i64 {
+    ///
+    /// 分配 ID 与写入在同一事务中完成，多个连接同时添加时不会得到相同的 ID.
+    pub fn insert_location(
+        db: &DataBase,
+        course_id: i64,
+        location: &Location,
+    ) -> Result<i64, StoreError> {
         // 为指定课程添加位置。
-        let mut lid = 0_i64;
-        loop {
-            if Self::has_location(db, lid) {
+        db.transaction(|db| {
+            let mut lid = 0_i64;
+            while Self::has_location(db, lid)? {
                 lid += 1;
-                continue;
             }
-            Self::add_location_or(db, lid, course_id, location, |_, _, _, _| {});
-            break;
-        }
-        lid
+            Self::add_location_or(db, lid, course_id, location, |_, _, _, _| Ok(()))?;
+            Ok(lid)
+        })
     }
-    pub fn delete_location(db: &DataBase, location_id: i64) {
-        db.execute(format!(
-            "DELETE FROM {} WHERE lid={location_id};",
-            Self::TABLE_NAME
-        ))
-        .unwrap();
-        let aliases = AliasTable::get_aliases(db, location_id);
-        for alias in aliases {
-            AliasTable::delete_alias(db, &alias)
-        }
+    /// 删除位置，以及别名与该位置的对应关系、该位置的标签和使用记录。
+    pub fn delete_location(db: &DataBase, location_id: i64) -> Result<(), StoreError> {
+        db.transaction(|db| {
+            db.execute_with(
+                &format!("DELETE FROM {} WHERE lid=?;", Self::TABLE_NAME),
+                (1, location_id),
+            )?;
+            AliasTable::delete_aliases_of(db, location_id)?;
+            if LocationTagTable::is_existed(db)? {
+                LocationTagTable::delete_tags_of(db, location_id)?;
+            }
+            if LocationUsageTable::is_existed(db)? {
+                LocationUsageTable::delete_usage(db, location_id)?;
+            }
+            Ok(())
+        })
     }
     /// location_id, (course_id, location)
-    pub fn get_locations(db: &DataBase) -> HashMap<i64, (i64, Location)> {
-        let mut query = db
-            .prepare(format!("SELECT * FROM {};", Self::TABLE_NAME))
-            .unwrap();
+    pub fn get_locations(db: &DataBase) -> Result<HashMap<i64, (i64, Location)>, StoreError> {
+        let mut query = db.prepare_cached(&format!("SELECT * FROM {};", Self::TABLE_NAME))?;
         let mut location_map = HashMap::new();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let os_id = row.read("lid");
-                let addr = row.read("addr");
-                let lat = row.read("lat");
-                let lon = row.read("lon");
-                let alt = row.read("alt");
-                let course_id = row.read("courseid");
-                location_map.insert(os_id, (course_id, Location::new(addr, lon, lat, alt)));
-            } else {
-                warn!("位置解析行出错：{c:?}.");
+        for row in query.iter() {
+            let row = row?;
+            let location_id = row.try_read("lid")?;
+            let course_id = row.try_read("courseid")?;
+            if let Some(location) = Self::read_valid_location(&row, location_id) {
+                location_map.insert(location_id, (course_id, location));
             }
         }
-        location_map
+        Ok(location_map)
     }
-    pub fn get_location(db: &DataBase, location_id: i64) -> (i64, Location) {
-        let mut query = db
-            .prepare(format!("SELECT * FROM {} WHERE lid=?;", Self::TABLE_NAME))
-            .unwrap();
-        query.bind((1, location_id)).unwrap();
-        let c: Vec<sqlite::Row> = query
-            .iter()
-            .filter_map(|e| if let Ok(e) = e { Some(e) } else { None })
-            .collect();
-        let row = &c[0];
-        let addr = row.read("addr");
-        let lat = row.read("lat");
-        let lon = row.read("lon");
-        let alt = row.read("alt");
-        let course_id = row.read("courseid");
-        (course_id, Location::new(addr, lon, lat, alt))
+    /// course_id, location
+    pub fn get_location(
+        db: &DataBase,
+        location_id: i64,
+    ) -> Result<Option<(i64, Location)>, StoreError> {
+        let mut query =
+            db.prepare_cached(&format!("SELECT * FROM {} WHERE lid=?;", Self::TABLE_NAME))?;
+        query.bind((1, location_id))?;
+        if let Some(row) = query.iter().next() {
+            let row = row?;
+            let course_id = row.try_read("courseid")?;
+            return Ok(Self::read_valid_location(&row, location_id).map(|l| (course_id, l)));
+        }
+        Ok(None)
     }
-    pub fn get_location_by_alias(db: &DataBase, alias: &str) -> Option<Location> {
-        AliasTable::get_location_id(db, alias).map(|id| Self::get_location(db, id).1)
+    pub fn get_location_by_alias(
+        db: &DataBase,
+        alias: &str,
+    ) -> Result<Option<Location>, StoreError> {
+        match AliasTable::get_location_id(db, alias)? {
+            Some(id) => Ok(Self::get_location(db, id)?.map(|(_, location)| location)),
+            None => Ok(None),
+        }
     }
-    pub fn get_location_map_by_course(db: &DataBase, course_id: i64) -> HashMap<i64, Location> {
-        let mut query = db
-            .prepare(format!(
-                "SELECT * FROM {} WHERE courseid=?;",
-                Self::TABLE_NAME
-            ))
-            .unwrap();
-        query.bind((1, course_id)).unwrap();
+    pub fn get_location_map_by_course(
+        db: &DataBase,
+        course_id: i64,
+    ) -> Result<HashMap<i64, Location>, StoreError> {
+        let mut query = db.prepare_cached(&format!(
+            "SELECT * FROM {} WHERE courseid=?;",
+            Self::TABLE_NAME
+        ))?;
+        query.bind((1, course_id))?;
         let mut location_map = HashMap::new();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let location_id = row.read("lid");
-                let addr = row.read("addr");
-                let lon = row.read("lon");
-                let lat = row.read("lat");
-                let alt = row.read("alt");
-                location_map.insert(location_id, Location::new(addr, lon, lat, alt));
-            } else {
-                warn!("位置解析行出错：{c:?}.");
+        for row in query.iter() {
+            let row = row?;
+            let location_id = row.try_read("lid")?;
+            if let Some(location) = Self::read_valid_location(&row, location_id) {
+                location_map.insert(location_id, location);
             }
         }
-        location_map
+        Ok(location_map)
     }
-    pub fn get_location_list_by_course(db: &DataBase, course_id: i64) -> Vec<Location> {
-        let mut query = db
-            .prepare(format!(
-                "SELECT * FROM {} WHERE courseid=?;",
-                Self::TABLE_NAME
-            ))
-            .unwrap();
-        query.bind((1, course_id)).unwrap();
+    pub fn get_location_list_by_course(
+        db: &DataBase,
+        course_id: i64,
+    ) -> Result<Vec<Location>, StoreError> {
+        let mut query = db.prepare_cached(&format!(
+            "SELECT * FROM {} WHERE courseid=?;",
+            Self::TABLE_NAME
+        ))?;
+        query.bind((1, course_id))?;
         let mut location_list = Vec::new();
-        for c in query.iter() {
-            if let Ok(row) = c {
-                let addr = row.read("addr");
-                let lat = row.read("lat");
-                let lon = row.read("lon");
-                let alt = row.read("alt");
-                location_list.push(Location::new(addr, lon, lat, alt));
-            } else {
-                warn!("位置解析行出错：{c:?}.");
-            }
+        for row in query.iter() {
+            let row = row?;
+            let location_id = row.try_read("lid")?;
+            location_list.extend(Self::read_valid_location(&row, location_id));
         }
-        location_list
+        Ok(location_list)
     }
 }
 impl StorageTableCommandTrait<DataBase> for LocationTable {
     fn init(storage: &DataBase) {
-        <Self as DataBaseTableTrait>::init(storage);
+        <Self as DataBaseTableTrait>::init(storage).unwrap_or_log_default();
     }
     fn uninit(storage: &DataBase) -> bool {
-        !Self::is_existed(storage)
+        !Self::is_existed(storage).unwrap_or_log_default()
     }
     fn clear(storage: &DataBase) {
-        Self::delete(storage);
+        Self::delete(storage).unwrap_or_log_default();
     }
     fn import(storage: &DataBase, content: &str) {
         <Self as DataBaseTableTrait>::import(storage, content);
@@ -226,26 +292,31 @@ impl DataBaseTableTrait for LocationTable {
 
     fn import(db: &DataBase, data: &str) {
         let data = crate::utils::parse::<_, LocationAndAliasesPair>(data);
-        for LocationAndAliasesPair {
-            course,
-            location,
-            aliases,
-        } in data
-        {
-            let location_id = Self::insert_location(db, course, &location);
-            for alias in aliases {
-                if !alias.is_empty() {
-                    AliasTable::add_alias_or(db, &alias, location_id, AliasTable::update_alias)
+        db.transaction(|db| {
+            for LocationAndAliasesPair {
+                course,
+                location,
+                aliases,
+            } in data
+            {
+                let location_id = Self::insert_location(db, course, &location)?;
+                for alias in aliases {
+                    if !alias.is_empty() {
+                        AliasTable::add_alias_or(db, &alias, location_id, |_, _, _| Ok(()))?
+                    }
                 }
             }
-        }
+            Ok(())
+        })
+        .unwrap_or_log_default()
     }
 
     fn export(db: &DataBase) -> String {
         let data = Self::get_locations(db)
+            .unwrap_or_log_default()
             .into_iter()
             .map(|(location_id, (course, location))| {
-                let aliases = AliasTable::get_aliases(db, location_id);
+                let aliases = AliasTable::get_aliases(db, location_id).unwrap_or_log_default();
                 LocationAndAliasesPair {
                     course,
                     location,
@@ -255,3 +326,57 @@ impl DataBaseTableTrait for LocationTable {
         crate::utils::to_string(data)
     }
 }
+impl TableRecordTrait for LocationTable {
+    type Record = LocationData;
+
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError> {
+        Ok(Self::get_locations(db)?
+            .into_iter()
+            .map(|(lid, (course, location))| LocationData::new(lid, course, location))
+            .collect())
+    }
+
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError> {
+        Self::has_location(db, record.lid)
+    }
+
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError> {
+        Self::add_location_or(
+            db,
+            record.lid,
+            record.course,
+            &record.to_location()?,
+            Self::update_location,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{DataBase, LocationTable};
+    use cxlib_types::Location;
+
+    #[test]
+    fn test_skip_invalid_rows() {
+        let db = DataBase::in_memory().unwrap();
+        db.add_table::<LocationTable>().unwrap();
+        let location = Location::parse("教室,116.0,40.0,50").unwrap();
+        let lid = LocationTable::insert_location(&db, 1, &location).unwrap();
+        // 旧版本写入的空经纬度。
+        db.execute(
+            "INSERT INTO location(lid,courseid,addr,lon,lat,alt) values(100,1,'','','','');",
+        )
+        .unwrap();
+        let locations = LocationTable::get_locations(&db).unwrap();
+        assert_eq!(locations.len(), 1);
+        assert!(locations.contains_key(&lid));
+        let map = LocationTable::get_location_map_by_course(&db, 1).unwrap();
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![&lid]);
+        assert_eq!(
+            LocationTable::get_location_list_by_course(&db, 1)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
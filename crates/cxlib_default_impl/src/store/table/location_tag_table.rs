@@ -0,0 +1,144 @@
+use crate::store::{
+    DataBase, DataBaseTableTrait, DataFormat, MergeStrategy, StoreError, TableRecordTrait,
+};
+use cxlib_error::CxlibResultUtils;
+use cxlib_store::StorageTableCommandTrait;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+pub struct LocationTagTable;
+/// 位置标签表中的一行。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LocationTagData {
+    pub lid: i64,
+    pub tag: String,
+}
+
+impl LocationTagTable {
+    pub fn has_tag(db: &DataBase, location_id: i64, tag: &str) -> Result<bool, StoreError> {
+        Ok(db.query_count::<&[(_, sqlite::Value)]>(
+            &format!(
+                "SELECT count(*) FROM {} WHERE lid=:lid AND tag=:tag;",
+                Self::TABLE_NAME
+            ),
+            &[(":lid", location_id.into()), (":tag", tag.into())][..],
+        )? > 0)
+    }
+
+    pub fn add_tag(db: &DataBase, location_id: i64, tag: &str) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "INSERT OR IGNORE INTO {}(lid,tag) values(:lid,:tag);",
+                Self::TABLE_NAME
+            ),
+            &[(":lid", location_id.into()), (":tag", tag.into())][..],
+        )
+    }
+
+    pub fn remove_tag(db: &DataBase, location_id: i64, tag: &str) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "DELETE FROM {} WHERE lid=:lid AND tag=:tag;",
+                Self::TABLE_NAME
+            ),
+            &[(":lid", location_id.into()), (":tag", tag.into())][..],
+        )
+    }
+    /// 删除该位置的所有标签。
+    pub fn delete_tags_of(db: &DataBase, location_id: i64) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("DELETE FROM {} WHERE lid=?;", Self::TABLE_NAME),
+            (1, location_id),
+        )
+    }
+
+    pub fn get_tags(db: &DataBase, location_id: i64) -> Result<BTreeSet<String>, StoreError> {
+        let mut query =
+            db.prepare_cached(&format!("SELECT * FROM {} WHERE lid=?;", Self::TABLE_NAME))?;
+        query.bind((1, location_id))?;
+        let mut tags = BTreeSet::new();
+        for row in query.iter() {
+            let tag: &str = row?.try_read("tag")?;
+            tags.insert(tag.to_owned());
+        }
+        Ok(tags)
+    }
+    /// 带有该标签的所有位置 ID.
+    pub fn get_locations_by_tag(db: &DataBase, tag: &str) -> Result<BTreeSet<i64>, StoreError> {
+        let mut query =
+            db.prepare_cached(&format!("SELECT * FROM {} WHERE tag=?;", Self::TABLE_NAME))?;
+        query.bind((1, tag))?;
+        let mut location_ids = BTreeSet::new();
+        for row in query.iter() {
+            location_ids.insert(row?.try_read("lid")?);
+        }
+        Ok(location_ids)
+    }
+
+    pub fn get_all_tags(db: &DataBase) -> Result<Vec<LocationTagData>, StoreError> {
+        let mut query = db.prepare_cached(&format!("SELECT * FROM {};", Self::TABLE_NAME))?;
+        let mut tags = Vec::new();
+        for row in query.iter() {
+            let row = row?;
+            let tag: &str = row.try_read("tag")?;
+            tags.push(LocationTagData {
+                lid: row.try_read("lid")?,
+                tag: tag.to_owned(),
+            });
+        }
+        Ok(tags)
+    }
+}
+
+impl StorageTableCommandTrait<DataBase> for LocationTagTable {
+    fn init(storage: &DataBase) {
+        <Self as DataBaseTableTrait>::init(storage).unwrap_or_log_default();
+    }
+    fn uninit(storage: &DataBase) -> bool {
+        !Self::is_existed(storage).unwrap_or_log_default()
+    }
+    fn clear(storage: &DataBase) {
+        Self::delete(storage).unwrap_or_log_default();
+    }
+    fn import(storage: &DataBase, content: &str) {
+        <Self as DataBaseTableTrait>::import(storage, content);
+    }
+    fn export(storage: &DataBase) -> String {
+        <Self as DataBaseTableTrait>::export(storage)
+    }
+}
+
+impl DataBaseTableTrait for LocationTagTable {
+    const TABLE_ARGS: &'static str = "lid INTEGER NOT NULL,tag CHAR (50) NOT NULL,UNIQUE(lid,tag)";
+    const TABLE_NAME: &'static str = "location_tag";
+
+    fn import(db: &DataBase, data: &str) {
+        match Self::import_as(db, data, DataFormat::Csv, MergeStrategy::Skip) {
+            Ok(summary) => info!("位置标签导入完成：{summary:?}."),
+            Err(e) => warn!("位置标签导入失败：{e}."),
+        }
+    }
+
+    fn export(db: &DataBase) -> String {
+        Self::export_as(db, DataFormat::Csv).unwrap_or_else(|e| {
+            warn!("位置标签导出失败：{e}.");
+            String::new()
+        })
+    }
+}
+impl TableRecordTrait for LocationTagTable {
+    type Record = LocationTagData;
+
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError> {
+        Self::get_all_tags(db)
+    }
+
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError> {
+        Self::has_tag(db, record.lid, &record.tag)
+    }
+
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError> {
+        Self::add_tag(db, record.lid, &record.tag)
+    }
+}
@@ -0,0 +1,156 @@
+use crate::store::{
+    DataBase, DataBaseTableTrait, DataFormat, LocationUsage, MergeStrategy, StoreError,
+    TableRecordTrait,
+};
+use cxlib_error::CxlibResultUtils;
+use cxlib_store::StorageTableCommandTrait;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+pub struct LocationUsageTable;
+/// 位置使用记录表中的一行。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LocationUsageData {
+    pub lid: i64,
+    pub count: u64,
+    pub last_used: u64,
+}
+
+impl LocationUsageTable {
+    pub fn has_usage(db: &DataBase, location_id: i64) -> Result<bool, StoreError> {
+        Ok(db.query_count(
+            &format!("SELECT count(*) FROM {} WHERE lid=?;", Self::TABLE_NAME),
+            (1, location_id),
+        )? > 0)
+    }
+
+    pub fn get_usage(db: &DataBase, location_id: i64) -> Result<Option<LocationUsage>, StoreError> {
+        let mut query =
+            db.prepare_cached(&format!("SELECT * FROM {} WHERE lid=?;", Self::TABLE_NAME))?;
+        query.bind((1, location_id))?;
+        if let Some(row) = query.iter().next() {
+            let row = row?;
+            return Ok(Some(LocationUsage {
+                count: row.try_read::<i64, _>("count")? as u64,
+                last_used_mills: row.try_read::<i64, _>("last_used")? as u64,
+            }));
+        }
+        Ok(None)
+    }
+    /// 记录一次使用，次数加一，并更新最近使用时间。
+    pub fn record_usage(
+        db: &DataBase,
+        location_id: i64,
+        time_mills: u64,
+    ) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "INSERT INTO {}(lid,count,last_used) values(:lid,1,:time) ON CONFLICT(lid) DO UPDATE SET count=count+1,last_used=max(last_used,:time);",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":lid", location_id.into()),
+                (":time", (time_mills as i64).into()),
+            ][..],
+        )
+    }
+    /// 以给定的记录覆盖已有记录。
+    pub fn set_usage(
+        db: &DataBase,
+        location_id: i64,
+        usage: LocationUsage,
+    ) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "INSERT OR REPLACE INTO {}(lid,count,last_used) values(:lid,:count,:time);",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":lid", location_id.into()),
+                (":count", (usage.count as i64).into()),
+                (":time", (usage.last_used_mills as i64).into()),
+            ][..],
+        )
+    }
+
+    pub fn delete_usage(db: &DataBase, location_id: i64) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("DELETE FROM {} WHERE lid=?;", Self::TABLE_NAME),
+            (1, location_id),
+        )
+    }
+
+    pub fn get_all_usages(db: &DataBase) -> Result<Vec<LocationUsageData>, StoreError> {
+        let mut query = db.prepare_cached(&format!("SELECT * FROM {};", Self::TABLE_NAME))?;
+        let mut usages = Vec::new();
+        for row in query.iter() {
+            let row = row?;
+            usages.push(LocationUsageData {
+                lid: row.try_read("lid")?,
+                count: row.try_read::<i64, _>("count")? as u64,
+                last_used: row.try_read::<i64, _>("last_used")? as u64,
+            });
+        }
+        Ok(usages)
+    }
+}
+
+impl StorageTableCommandTrait<DataBase> for LocationUsageTable {
+    fn init(storage: &DataBase) {
+        <Self as DataBaseTableTrait>::init(storage).unwrap_or_log_default();
+    }
+    fn uninit(storage: &DataBase) -> bool {
+        !Self::is_existed(storage).unwrap_or_log_default()
+    }
+    fn clear(storage: &DataBase) {
+        Self::delete(storage).unwrap_or_log_default();
+    }
+    fn import(storage: &DataBase, content: &str) {
+        <Self as DataBaseTableTrait>::import(storage, content);
+    }
+    fn export(storage: &DataBase) -> String {
+        <Self as DataBaseTableTrait>::export(storage)
+    }
+}
+
+impl DataBaseTableTrait for LocationUsageTable {
+    const TABLE_ARGS: &'static str =
+        "lid INTEGER UNIQUE NOT NULL,count INTEGER NOT NULL,last_used INTEGER NOT NULL";
+    const TABLE_NAME: &'static str = "location_usage";
+
+    fn import(db: &DataBase, data: &str) {
+        match Self::import_as(db, data, DataFormat::Csv, MergeStrategy::Overwrite) {
+            Ok(summary) => info!("位置使用记录导入完成：{summary:?}."),
+            Err(e) => warn!("位置使用记录导入失败：{e}."),
+        }
+    }
+
+    fn export(db: &DataBase) -> String {
+        Self::export_as(db, DataFormat::Csv).unwrap_or_else(|e| {
+            warn!("位置使用记录导出失败：{e}.");
+            String::new()
+        })
+    }
+}
+impl TableRecordTrait for LocationUsageTable {
+    type Record = LocationUsageData;
+
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError> {
+        Self::get_all_usages(db)
+    }
+
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError> {
+        Self::has_usage(db, record.lid)
+    }
+
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError> {
+        Self::set_usage(
+            db,
+            record.lid,
+            LocationUsage {
+                count: record.count,
+                last_used_mills: record.last_used,
+            },
+        )
+    }
+}
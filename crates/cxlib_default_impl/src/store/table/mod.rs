@@ -1,12 +1,24 @@
 mod account_table;
+mod activity_snapshot_table;
 mod alias_table;
+mod course_table;
 mod exclude_table;
 mod kv_config_table;
 mod location_table;
+mod location_tag_table;
+mod location_usage_table;
+mod photo_table;
+mod transfer;
 
 pub mod utils;
 pub use account_table::*;
+pub use activity_snapshot_table::*;
 pub use alias_table::*;
+pub use course_table::*;
 pub use exclude_table::*;
 pub use kv_config_table::*;
 pub use location_table::*;
+pub use location_tag_table::*;
+pub use location_usage_table::*;
+pub use photo_table::*;
+pub use transfer::*;
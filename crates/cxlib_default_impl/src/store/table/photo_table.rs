@@ -0,0 +1,163 @@
+use crate::store::{
+    is_constraint_error, DataBase, DataBaseTableTrait, StoreError, TableRecordTrait,
+};
+use cxlib_error::CxlibResultUtils;
+use cxlib_store::StorageTableCommandTrait;
+use serde::{Deserialize, Serialize};
+
+/// 照片缓存表，按用户 uid 及照片内容摘要记录已上传照片的对象 ID.
+///
+/// 参见 [`PhotoCacheTrait`](cxlib_types::PhotoCacheTrait).
+pub struct PhotoTable;
+/// 照片缓存表中的一行。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PhotoData {
+    pub uid: String,
+    pub hash: String,
+    pub object_id: String,
+    pub time: u64,
+}
+
+impl PhotoTable {
+    /// object_id, time
+    pub fn get_photo(
+        db: &DataBase,
+        uid: &str,
+        hash: &str,
+    ) -> Result<Option<(String, u64)>, StoreError> {
+        let mut query = db.prepare_cached(&format!(
+            "SELECT * FROM {} WHERE uid=:uid AND hash=:hash;",
+            Self::TABLE_NAME
+        ))?;
+        query.bind::<&[(_, sqlite::Value)]>(&[(":uid", uid.into()), (":hash", hash.into())][..])?;
+        if let Some(row) = query.iter().next() {
+            let row = row?;
+            let object_id: &str = row.try_read("objectid")?;
+            let time: i64 = row.try_read("time")?;
+            return Ok(Some((object_id.to_owned(), time as u64)));
+        }
+        Ok(None)
+    }
+
+    pub fn add_photo_or<O: Fn(&DataBase, &str, &str, &str, u64) -> Result<(), StoreError>>(
+        db: &DataBase,
+        uid: &str,
+        hash: &str,
+        object_id: &str,
+        time: u64,
+        or: O,
+    ) -> Result<(), StoreError> {
+        match db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "INSERT INTO {}(uid,hash,objectid,time) values(:uid,:hash,:objectid,:time);",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":uid", uid.into()),
+                (":hash", hash.into()),
+                (":objectid", object_id.into()),
+                (":time", (time as i64).into()),
+            ][..],
+        ) {
+            Err(e) if is_constraint_error(&e) => or(db, uid, hash, object_id, time),
+            r => r,
+        }
+    }
+
+    pub fn update_photo(
+        db: &DataBase,
+        uid: &str,
+        hash: &str,
+        object_id: &str,
+        time: u64,
+    ) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "UPDATE {} SET objectid=:objectid,time=:time WHERE uid=:uid AND hash=:hash;",
+                Self::TABLE_NAME
+            ),
+            &[
+                (":uid", uid.into()),
+                (":hash", hash.into()),
+                (":objectid", object_id.into()),
+                (":time", (time as i64).into()),
+            ][..],
+        )
+    }
+
+    pub fn delete_photo(db: &DataBase, uid: &str, hash: &str) -> Result<(), StoreError> {
+        db.execute_with::<&[(_, sqlite::Value)]>(
+            &format!(
+                "DELETE FROM {} WHERE uid=:uid AND hash=:hash;",
+                Self::TABLE_NAME
+            ),
+            &[(":uid", uid.into()), (":hash", hash.into())][..],
+        )
+    }
+
+    /// 所有用户的缓存。
+    pub fn get_all(db: &DataBase) -> Result<Vec<PhotoData>, StoreError> {
+        let mut query = db.prepare_cached(&format!("SELECT * FROM {};", Self::TABLE_NAME))?;
+        let mut data = Vec::new();
+        for row in query.iter() {
+            let row = row?;
+            let uid: &str = row.try_read("uid")?;
+            let hash: &str = row.try_read("hash")?;
+            let object_id: &str = row.try_read("objectid")?;
+            let time: i64 = row.try_read("time")?;
+            data.push(PhotoData {
+                uid: uid.to_owned(),
+                hash: hash.to_owned(),
+                object_id: object_id.to_owned(),
+                time: time as u64,
+            });
+        }
+        Ok(data)
+    }
+
+    /// 删除某用户的所有缓存。
+    pub fn delete_user(db: &DataBase, uid: &str) -> Result<(), StoreError> {
+        db.execute_with(
+            &format!("DELETE FROM {} WHERE uid=?;", Self::TABLE_NAME),
+            (1, uid),
+        )
+    }
+}
+impl StorageTableCommandTrait<DataBase> for PhotoTable {
+    fn init(storage: &DataBase) {
+        <Self as DataBaseTableTrait>::init(storage).unwrap_or_log_default();
+    }
+    fn uninit(storage: &DataBase) -> bool {
+        !Self::is_existed(storage).unwrap_or_log_default()
+    }
+    fn clear(storage: &DataBase) {
+        Self::delete(storage).unwrap_or_log_default();
+    }
+}
+impl DataBaseTableTrait for PhotoTable {
+    const TABLE_ARGS: &'static str =
+        "uid CHAR (50) NOT NULL,hash CHAR (64) NOT NULL,objectid CHAR (64) NOT NULL,time INTEGER NOT NULL,UNIQUE(uid,hash)";
+    const TABLE_NAME: &'static str = "photo";
+}
+impl TableRecordTrait for PhotoTable {
+    type Record = PhotoData;
+
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError> {
+        Self::get_all(db)
+    }
+
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError> {
+        Ok(Self::get_photo(db, &record.uid, &record.hash)?.is_some())
+    }
+
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError> {
+        Self::add_photo_or(
+            db,
+            &record.uid,
+            &record.hash,
+            &record.object_id,
+            record.time,
+            Self::update_photo,
+        )
+    }
+}
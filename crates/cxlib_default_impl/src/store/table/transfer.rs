@@ -0,0 +1,407 @@
+use crate::store::{DataBase, DataBaseTableTrait, StoreError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{path::Path, str::FromStr};
+use ureq::serde_json;
+
+/// 导入导出的数据格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Json,
+    Toml,
+    Csv,
+}
+impl DataFormat {
+    /// 根据扩展名判断格式。
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| e.parse().ok())
+    }
+    /// 解析 JSON 或 TOML, 不支持 CSV.
+    pub fn parse<T: DeserializeOwned>(&self, content: &str) -> Result<T, StoreError> {
+        match self {
+            DataFormat::Json => {
+                serde_json::from_str(content).map_err(|e| StoreError::ParseError(e.to_string()))
+            }
+            DataFormat::Toml => {
+                toml::from_str(content).map_err(|e| StoreError::ParseError(e.to_string()))
+            }
+            DataFormat::Csv => Err(StoreError::ParseError(
+                "CSV 只能用于单个数据表。".to_string(),
+            )),
+        }
+    }
+    /// 序列化为 JSON 或 TOML, 不支持 CSV.
+    pub fn to_string<T: Serialize>(&self, data: &T) -> Result<String, StoreError> {
+        match self {
+            DataFormat::Json => serde_json::to_string_pretty(data)
+                .map_err(|e| StoreError::ParseError(e.to_string())),
+            DataFormat::Toml => {
+                toml::to_string_pretty(data).map_err(|e| StoreError::ParseError(e.to_string()))
+            }
+            DataFormat::Csv => Err(StoreError::ParseError(
+                "CSV 只能用于单个数据表。".to_string(),
+            )),
+        }
+    }
+}
+impl FromStr for DataFormat {
+    type Err = StoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(DataFormat::Json),
+            "toml" => Ok(DataFormat::Toml),
+            "csv" => Ok(DataFormat::Csv),
+            _ => Err(StoreError::ParseError(format!(
+                "不支持的格式：`{s}`, 可选值为 `json`, `toml`, `csv`."
+            ))),
+        }
+    }
+}
+
+/// 导入时遇到已有数据的处理方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// 保留已有数据，跳过导入的记录。
+    #[default]
+    Skip,
+    /// 以导入的记录覆盖已有数据。
+    Overwrite,
+    /// 存在冲突时不导入任何记录并返回错误。
+    Fail,
+}
+impl FromStr for MergeStrategy {
+    type Err = StoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "skip" => Ok(MergeStrategy::Skip),
+            "overwrite" => Ok(MergeStrategy::Overwrite),
+            "fail" => Ok(MergeStrategy::Fail),
+            _ => Err(StoreError::ParseError(format!(
+                "不支持的合并策略：`{s}`, 可选值为 `skip`, `overwrite`, `fail`."
+            ))),
+        }
+    }
+}
+
+/// 导入结果。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// 新增的记录数。
+    pub inserted: usize,
+    /// 覆盖的记录数。
+    pub overwritten: usize,
+    /// 因冲突而跳过的记录数。
+    pub skipped: usize,
+}
+
+/// JSON 及 TOML 格式的导出文件。
+///
+/// CSV 格式中，表名及版本号记录在首行的注释中，如 `# account v1`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableDocument<R> {
+    pub table: String,
+    pub version: u32,
+    #[serde(default = "Vec::new")]
+    pub records: Vec<R>,
+}
+
+/// # TableRecordTrait
+///
+/// 以结构化格式（JSON, TOML, CSV）导入导出数据表。
+///
+/// 每条记录以 [`TableRecordTrait::Record`] 表示，须为扁平结构以便写入 CSV.
+pub trait TableRecordTrait: DataBaseTableTrait {
+    type Record: Serialize + DeserializeOwned + Ord;
+    /// 记录格式的版本号，格式变化时递增。
+    const SCHEMA_VERSION: u32 = 1;
+    fn get_records(db: &DataBase) -> Result<Vec<Self::Record>, StoreError>;
+    /// 是否已有与该记录主键相同的数据。
+    fn has_record(db: &DataBase, record: &Self::Record) -> Result<bool, StoreError>;
+    /// 写入记录，已有数据时覆盖。
+    fn put_record(db: &DataBase, record: &Self::Record) -> Result<(), StoreError>;
+
+    fn export_as(db: &DataBase, format: DataFormat) -> Result<String, StoreError> {
+        let mut records = Self::get_records(db)?;
+        records.sort();
+        match format {
+            DataFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(vec![]);
+                for record in &records {
+                    writer
+                        .serialize(record)
+                        .map_err(|e| StoreError::ParseError(e.to_string()))?;
+                }
+                let data = writer
+                    .into_inner()
+                    .map_err(|e| StoreError::ParseError(e.to_string()))?;
+                let data =
+                    String::from_utf8(data).map_err(|e| StoreError::ParseError(e.to_string()))?;
+                Ok(format!(
+                    "# {} v{}\n{data}",
+                    Self::TABLE_NAME,
+                    Self::SCHEMA_VERSION
+                ))
+            }
+            format => format.to_string(&TableDocument {
+                table: Self::TABLE_NAME.to_string(),
+                version: Self::SCHEMA_VERSION,
+                records,
+            }),
+        }
+    }
+    /// 解析导出的内容，检查表名及版本号。
+    fn parse_records(content: &str, format: DataFormat) -> Result<Vec<Self::Record>, StoreError> {
+        let (table, version, records) = match format {
+            DataFormat::Csv => {
+                // 没有首行注释时视为当前表的当前版本。
+                let (table, version, data) = match content
+                    .split_once('\n')
+                    .and_then(|(header, data)| Some((parse_csv_header(header)?, data)))
+                {
+                    Some(((table, version), data)) => (table, version, data),
+                    None => (Self::TABLE_NAME.to_string(), Self::SCHEMA_VERSION, content),
+                };
+                let mut reader = csv::Reader::from_reader(data.as_bytes());
+                let records = reader
+                    .deserialize()
+                    .enumerate()
+                    .map(|(i, r)| {
+                        r.map_err(|e| {
+                            StoreError::ParseError(format!("第 {} 条记录解析出错：{e}", i + 1))
+                        })
+                    })
+                    .collect::<Result<Vec<Self::Record>, _>>()?;
+                (table, version, records)
+            }
+            format => {
+                let TableDocument {
+                    table,
+                    version,
+                    records,
+                } = format.parse::<TableDocument<Self::Record>>(content)?;
+                (table, version, records)
+            }
+        };
+        if table != Self::TABLE_NAME {
+            Err(StoreError::ParseError(format!(
+                "数据属于表 `{table}`, 而非 `{}`.",
+                Self::TABLE_NAME
+            )))?
+        }
+        if version > Self::SCHEMA_VERSION {
+            Err(StoreError::ParseError(format!(
+                "数据版本 v{version} 高于当前支持的 v{}.",
+                Self::SCHEMA_VERSION
+            )))?
+        }
+        Ok(records)
+    }
+    /// 导入记录，所有记录在同一事务中写入，出错时不会导入任何记录。
+    fn import_as(
+        db: &DataBase,
+        content: &str,
+        format: DataFormat,
+        strategy: MergeStrategy,
+    ) -> Result<ImportSummary, StoreError> {
+        let records = Self::parse_records(content, format)?;
+        db.transaction(|db| {
+            db.add_table::<Self>()?;
+            let mut summary = ImportSummary::default();
+            for record in &records {
+                if Self::has_record(db, record)? {
+                    match strategy {
+                        MergeStrategy::Skip => {
+                            summary.skipped += 1;
+                            continue;
+                        }
+                        MergeStrategy::Overwrite => summary.overwritten += 1,
+                        MergeStrategy::Fail => Err(StoreError::Conflict(format!(
+                            "表 `{}` 中的记录与已有数据冲突",
+                            Self::TABLE_NAME
+                        )))?,
+                    }
+                } else {
+                    summary.inserted += 1;
+                }
+                Self::put_record(db, record)?;
+            }
+            Ok(summary)
+        })
+    }
+}
+fn parse_csv_header(line: &str) -> Option<(String, u32)> {
+    let (table, version) = line.strip_prefix('#')?.trim().split_once(' ')?;
+    let version = version.trim().strip_prefix('v')?.parse().ok()?;
+    Some((table.trim().to_string(), version))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{
+        AccountData, AccountTable, ActivitySnapshotData, ActivitySnapshotTable, AliasData,
+        AliasTable, CourseData, CourseTable, DataBase, DataFormat, ExcludeData, ExcludeTable,
+        KVConfigTable, KVPair, LocationData, LocationTable, LocationTagData, LocationTagTable,
+        LocationUsageData, LocationUsageTable, MergeStrategy, PhotoData, PhotoTable,
+        TableRecordTrait,
+    };
+
+    fn round_trip<T: TableRecordTrait>(records: Vec<T::Record>)
+    where
+        T::Record: std::fmt::Debug,
+    {
+        for format in [DataFormat::Json, DataFormat::Toml, DataFormat::Csv] {
+            let src = DataBase::in_memory().unwrap();
+            src.add_table::<T>().unwrap();
+            for record in &records {
+                T::put_record(&src, record).unwrap();
+            }
+            let content = T::export_as(&src, format).unwrap();
+            let dst = DataBase::in_memory().unwrap();
+            let summary = T::import_as(&dst, &content, format, MergeStrategy::Fail).unwrap();
+            assert_eq!(summary.inserted, records.len(), "{format:?}: {content}");
+            let mut expected = T::get_records(&src).unwrap();
+            expected.sort();
+            let mut actual = T::get_records(&dst).unwrap();
+            actual.sort();
+            assert_eq!(expected, actual, "{format:?}: {content}");
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip::<AccountTable>(vec![AccountData::new(
+            "123".to_owned(),
+            "uname".to_owned(),
+            "enc,pwd".to_owned(),
+            "default".to_owned(),
+        )]);
+        round_trip::<AliasTable>(vec![
+            AliasData {
+                name: "教室".to_owned(),
+                lid: 0,
+            },
+            AliasData {
+                name: "教室".to_owned(),
+                lid: 2,
+            },
+            AliasData {
+                name: "操场".to_owned(),
+                lid: 1,
+            },
+        ]);
+        round_trip::<LocationTagTable>(vec![
+            LocationTagData {
+                lid: 0,
+                tag: "室内".to_owned(),
+            },
+            LocationTagData {
+                lid: 0,
+                tag: "一楼".to_owned(),
+            },
+        ]);
+        round_trip::<LocationUsageTable>(vec![LocationUsageData {
+            lid: 0,
+            count: 3,
+            last_used: 1_700_000_000_000,
+        }]);
+        round_trip::<LocationTable>(vec![LocationData {
+            lid: 3,
+            course: 1,
+            addr: "某地, 某楼".to_owned(),
+            lon: "116.3".to_owned(),
+            lat: "39.9".to_owned(),
+            alt: "50".to_owned(),
+        }]);
+        round_trip::<ExcludeTable>(vec![ExcludeData { id: 1 }, ExcludeData { id: 2 }]);
+        round_trip::<KVConfigTable>(vec![
+            KVPair {
+                key: "k".to_owned(),
+                value: "a = b".to_owned(),
+            },
+            KVPair {
+                key: "empty".to_owned(),
+                value: String::new(),
+            },
+        ]);
+        round_trip::<ActivitySnapshotTable>(vec![ActivitySnapshotData {
+            course: 1,
+            active_id: "abc".to_owned(),
+            status: 1,
+        }]);
+        round_trip::<PhotoTable>(vec![PhotoData {
+            uid: "123".to_owned(),
+            hash: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_owned(),
+            object_id: "obj".to_owned(),
+            time: 1_700_000_000_000,
+        }]);
+        let course = |id: i64, term: Option<&str>, archived: bool| CourseData {
+            uid: "123".to_owned(),
+            id,
+            class_id: 2,
+            teacher: "teacher".to_owned(),
+            image_url: "https://example.com/1.png".to_owned(),
+            name: "高等数学, 上".to_owned(),
+            term: term.map(|t| t.to_owned()),
+            archived,
+            time: 1_700_000_000_000,
+        };
+        round_trip::<CourseTable>(vec![
+            course(1, Some("2024-2025 第一学期"), false),
+            course(3, None, true),
+        ]);
+    }
+
+    #[test]
+    fn test_merge_strategy() {
+        let pair = |value: &str| KVPair {
+            key: "k".to_owned(),
+            value: value.to_owned(),
+        };
+        let db = DataBase::in_memory().unwrap();
+        db.add_table::<KVConfigTable>().unwrap();
+        KVConfigTable::put_record(&db, &pair("old")).unwrap();
+        let src = DataBase::in_memory().unwrap();
+        src.add_table::<KVConfigTable>().unwrap();
+        KVConfigTable::put_record(&src, &pair("new")).unwrap();
+        let content = KVConfigTable::export_as(&src, DataFormat::Json).unwrap();
+
+        assert!(
+            KVConfigTable::import_as(&db, &content, DataFormat::Json, MergeStrategy::Fail).is_err()
+        );
+        let summary =
+            KVConfigTable::import_as(&db, &content, DataFormat::Json, MergeStrategy::Skip).unwrap();
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(
+            KVConfigTable::get_by_key(&db, "k").unwrap().as_deref(),
+            Some("old")
+        );
+        let summary =
+            KVConfigTable::import_as(&db, &content, DataFormat::Json, MergeStrategy::Overwrite)
+                .unwrap();
+        assert_eq!(summary.overwritten, 1);
+        assert_eq!(
+            KVConfigTable::get_by_key(&db, "k").unwrap().as_deref(),
+            Some("new")
+        );
+    }
+
+    #[test]
+    fn test_schema_check() {
+        let db = DataBase::in_memory().unwrap();
+        let content = "# kv_config v1\nkey,value\nk,v\n";
+        assert!(
+            ExcludeTable::import_as(&db, content, DataFormat::Csv, MergeStrategy::Skip).is_err()
+        );
+        let content = "# kv_config v99\nkey,value\nk,v\n";
+        assert!(
+            KVConfigTable::import_as(&db, content, DataFormat::Csv, MergeStrategy::Skip).is_err()
+        );
+        let content = "# kv_config v1\nkey,value\nk,v\n";
+        assert!(
+            KVConfigTable::import_as(&db, content, DataFormat::Csv, MergeStrategy::Skip).is_ok()
+        );
+    }
+}
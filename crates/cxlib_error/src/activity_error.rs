@@ -7,6 +7,8 @@ pub enum ActivityError {
     AgentError(#[from] AgentError),
     #[error(transparent)]
     CourseError(#[from] CourseError),
+    #[error("活动接口返回了意外的响应：`{0}`")]
+    UnexpectedResponse(String),
     // #[error(transparent)]
     // LoginError(#[from] LoginError),
 }
@@ -15,6 +17,7 @@ impl MaybeFatalError for ActivityError {
         match self {
             ActivityError::AgentError(e) => e.is_fatal(),
             ActivityError::CourseError(e) => e.is_fatal(),
+            ActivityError::UnexpectedResponse(_) => true,
         }
     }
 }
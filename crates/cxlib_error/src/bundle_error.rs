@@ -0,0 +1,33 @@
+use crate::{MaybeFatalError, StoreError};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum BundleError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error("备份文件格式错误：`{0}`.")]
+    InvalidBundle(String),
+    #[error("不支持的备份文件版本：v{0}.")]
+    UnsupportedVersion(u32),
+    #[error("文件 `{0}` 校验失败，备份可能已损坏。")]
+    ChecksumMismatch(String),
+    #[error("文件 `{0}` 已存在。")]
+    AlreadyExists(String),
+    #[error("数据库 `{0}` 仍有未写回的日志，请通过数据库连接导出。")]
+    PendingWal(String),
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+}
+impl MaybeFatalError for BundleError {
+    fn is_fatal(&self) -> bool {
+        match self {
+            BundleError::IoError(_) => false,
+            BundleError::InvalidBundle(_) => true,
+            BundleError::UnsupportedVersion(_) => true,
+            BundleError::ChecksumMismatch(_) => true,
+            BundleError::AlreadyExists(_) => false,
+            BundleError::PendingWal(_) => false,
+            BundleError::StoreError(e) => e.is_fatal(),
+        }
+    }
+}
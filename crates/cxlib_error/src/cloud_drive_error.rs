@@ -0,0 +1,30 @@
+use crate::{AgentError, MaybeFatalError};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CloudDriveError {
+    #[error(transparent)]
+    AgentError(#[from] AgentError),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error("网盘返回了意外的响应：`{0}`")]
+    UnexpectedResponse(String),
+    #[error("网盘中未找到：`{0}`")]
+    NotFound(String),
+    #[error("文件大小 {size} 字节超过上传限制 {max} 字节")]
+    TooLarge { size: u64, max: u64 },
+    #[error("上传已取消")]
+    Canceled,
+}
+impl MaybeFatalError for CloudDriveError {
+    fn is_fatal(&self) -> bool {
+        match self {
+            CloudDriveError::AgentError(e) => e.is_fatal(),
+            CloudDriveError::IoError(_) => false,
+            CloudDriveError::UnexpectedResponse(_) => true,
+            CloudDriveError::NotFound(_) => true,
+            CloudDriveError::TooLarge { .. } => true,
+            CloudDriveError::Canceled => true,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{MaybeFatalError, StoreError};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("未知的配置项：`{0}`.")]
+    UnknownKey(String),
+    #[error("配置项 `{key}` 的值 `{value}` 无效：{reason}.")]
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+    #[error("配置文件解析失败：`{0}`.")]
+    ParseError(String),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+}
+impl MaybeFatalError for ConfigError {
+    fn is_fatal(&self) -> bool {
+        match self {
+            ConfigError::UnknownKey(_) => false,
+            ConfigError::InvalidValue { .. } => false,
+            ConfigError::ParseError(_) => false,
+            ConfigError::IoError(_) => false,
+            ConfigError::StoreError(e) => e.is_fatal(),
+        }
+    }
+}
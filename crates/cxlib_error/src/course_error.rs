@@ -7,6 +7,8 @@ pub enum CourseError {
     AgentError(#[from] AgentError),
     #[error(transparent)]
     LoginError(#[from] LoginError),
+    #[error("课程接口返回了意外的响应：`{0}`")]
+    UnexpectedResponse(String),
 }
 /// 是否为致命错误。
 ///
@@ -23,6 +25,7 @@ impl MaybeFatalError for CourseError {
         match self {
             CourseError::AgentError(e) => e.is_fatal(),
             CourseError::LoginError(e) => e.is_fatal(),
+            CourseError::UnexpectedResponse(_) => false,
         }
     }
 }
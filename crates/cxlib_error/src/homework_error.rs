@@ -0,0 +1,18 @@
+use crate::{ActivityError, CloudDriveError, MaybeFatalError};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum HomeworkError {
+    #[error(transparent)]
+    ActivityError(#[from] ActivityError),
+    #[error(transparent)]
+    CloudDriveError(#[from] CloudDriveError),
+}
+impl MaybeFatalError for HomeworkError {
+    fn is_fatal(&self) -> bool {
+        match self {
+            HomeworkError::ActivityError(e) => e.is_fatal(),
+            HomeworkError::CloudDriveError(e) => e.is_fatal(),
+        }
+    }
+}
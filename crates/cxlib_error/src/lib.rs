@@ -1,6 +1,11 @@
 mod activity_error;
+mod bundle_error;
 mod captcha_error;
+mod cloud_drive_error;
+mod config_error;
 mod course_error;
+mod homework_error;
+mod location_error;
 mod login_error;
 mod new_types;
 mod protocol_error;
@@ -8,8 +13,13 @@ mod sign_error;
 mod store_error;
 
 pub use activity_error::*;
+pub use bundle_error::*;
 pub use captcha_error::*;
+pub use cloud_drive_error::*;
+pub use config_error::*;
 pub use course_error::*;
+pub use homework_error::*;
+pub use location_error::*;
 pub use login_error::*;
 pub use new_types::*;
 pub use protocol_error::*;
@@ -0,0 +1,29 @@
+use crate::MaybeFatalError;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LocationParseError {
+    #[error("位置信息格式错误！格式为：`地址,经度,纬度,海拔`, 海拔可以为空。")]
+    InvalidFormat,
+    #[error("{field} `{value}` 不是有效的数字。")]
+    InvalidNumber { field: &'static str, value: String },
+    #[error("{field} `{value}` 超出范围，应在 {min} 到 {max} 之间。")]
+    OutOfRange {
+        field: &'static str,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    #[error("未知的坐标系：`{0}`, 可选值为 `wgs84`, `gcj02`, `bd09`.")]
+    UnknownCoordinateSystem(String),
+}
+impl MaybeFatalError for LocationParseError {
+    fn is_fatal(&self) -> bool {
+        match self {
+            LocationParseError::InvalidFormat => false,
+            LocationParseError::InvalidNumber { .. } => false,
+            LocationParseError::OutOfRange { .. } => false,
+            LocationParseError::UnknownCoordinateSystem(_) => false,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{AgentError, CaptchaError, MaybeFatalError};
+use crate::{ActivityError, AgentError, CaptchaError, CloudDriveError, MaybeFatalError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,8 +6,12 @@ pub enum SignError {
     #[error(transparent)]
     AgentError(#[from] AgentError),
     #[error(transparent)]
+    ActivityError(#[from] ActivityError),
+    #[error(transparent)]
     CaptchaError(#[from] CaptchaError),
     #[error(transparent)]
+    CloudDriveError(#[from] CloudDriveError),
+    #[error(transparent)]
     IoError(#[from] std::io::Error),
     #[error("无法获取位置信息：`{0}`")]
     LocationError(String),
@@ -18,6 +22,7 @@ impl MaybeFatalError for SignError {
     fn is_fatal(&self) -> bool {
         match self {
             SignError::AgentError(e) => e.is_fatal(),
+            SignError::ActivityError(e) => e.is_fatal(),
             SignError::CaptchaError(e) => match e {
                 CaptchaError::AgentError(e) => e.is_fatal(),
                 CaptchaError::VerifyFailed => false,
@@ -25,6 +30,7 @@ impl MaybeFatalError for SignError {
                 CaptchaError::Canceled(_) => false,
                 CaptchaError::RequestRefresh => false,
             },
+            SignError::CloudDriveError(e) => e.is_fatal(),
             SignError::IoError(_) => false,
             SignError::LocationError(_) => true,
             SignError::SignDataNotFound(_) => true,
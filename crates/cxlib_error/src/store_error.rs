@@ -1,19 +1,34 @@
-use crate::{LoginError, MaybeFatalError};
+use crate::{LocationParseError, LoginError, MaybeFatalError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum StoreError {
     #[error("数据解析失败：`{0}`.")]
     ParseError(String),
+    #[error("数据冲突：{0}.")]
+    Conflict(String),
+    #[error("数据不存在：{0}.")]
+    NotFound(String),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error("数据库错误：{0}")]
+    SqliteError(#[from] sqlite::Error),
     #[error(transparent)]
     LoginError(#[from] LoginError),
+    #[error(transparent)]
+    LocationParseError(#[from] LocationParseError),
 }
 
 impl MaybeFatalError for StoreError {
     fn is_fatal(&self) -> bool {
         match self {
             StoreError::ParseError(_) => false,
+            StoreError::Conflict(_) => false,
+            StoreError::NotFound(_) => false,
+            StoreError::IoError(_) => false,
+            StoreError::SqliteError(_) => false,
             StoreError::LoginError(e) => e.is_fatal(),
+            StoreError::LocationParseError(e) => e.is_fatal(),
         }
     }
 }
@@ -1,4 +1,5 @@
 pub mod map;
+mod preprocess;
 
 use crate::map::map_colors;
 use image::buffer::ConvertBuffer;
@@ -8,6 +9,7 @@ use image::{
 };
 use imageproc::contours::find_contours;
 use num_traits::ToPrimitive;
+pub use preprocess::*;
 use std::ops::Add;
 use std::path::Path;
 pub use yapt::point_2d::Point;
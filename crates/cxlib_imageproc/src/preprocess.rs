@@ -0,0 +1,256 @@
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, metadata::Orientation, DynamicImage,
+    ImageDecoder, ImageError, ImageFormat, ImageReader,
+};
+use std::{io::Cursor, path::Path};
+
+/// # PhotoPreprocessor
+///
+/// 上传照片前的预处理：
+///
+/// 1. 去除 EXIF 及其中的 GPS 坐标等元数据；
+/// 2. 按 EXIF 中的方向信息旋转图片；
+/// 3. 可选：将长边缩小至不超过 `max_dimension`;
+/// 4. 可选：以 `jpeg_quality` 重新编码为 JPEG.
+///
+/// 默认只去除元数据：JPEG 与 PNG 图片在无需旋转时会原样保留图像数据，仅删除元数据段；
+/// 其余情况需要重新编码，此时输出 JPEG, 质量默认为 [`DEFAULT_JPEG_QUALITY`](Self::DEFAULT_JPEG_QUALITY).
+/// 重新编码不会写入任何元数据。
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PhotoPreprocessor {
+    auto_orient: bool,
+    max_dimension: Option<u32>,
+    jpeg_quality: Option<u8>,
+}
+impl Default for PhotoPreprocessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl PhotoPreprocessor {
+    /// 需要重新编码而又未设置 JPEG 质量时使用的质量。
+    pub const DEFAULT_JPEG_QUALITY: u8 = 95;
+    /// 默认去除元数据并自动旋转，不缩放，不压缩。
+    pub const fn new() -> Self {
+        Self {
+            auto_orient: true,
+            max_dimension: None,
+            jpeg_quality: None,
+        }
+    }
+    /// 是否按 EXIF 方向信息旋转图片。
+    pub fn with_auto_orient(mut self, auto_orient: bool) -> Self {
+        self.auto_orient = auto_orient;
+        self
+    }
+    /// 设置长边的最大像素数，为 `None` 时不缩放。图片不会被放大。
+    pub fn with_max_dimension(mut self, max_dimension: Option<u32>) -> Self {
+        self.max_dimension = max_dimension;
+        self
+    }
+    /// 设置 JPEG 质量，取值范围为 `1..=100`, 超出范围的值会被截断。为 `None` 时不压缩。
+    ///
+    /// 设置后图片总会被重新编码为 JPEG.
+    pub fn with_jpeg_quality(mut self, jpeg_quality: Option<u8>) -> Self {
+        self.jpeg_quality = jpeg_quality.map(|q| q.clamp(1, 100));
+        self
+    }
+    /// 处理图片数据，返回处理后的图片数据。输出格式参见 [`processed_file_name`](Self::processed_file_name).
+    pub fn process(&self, data: &[u8]) -> Result<Vec<u8>, ImageError> {
+        let reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+        let format = reader.format();
+        let mut decoder = reader.into_decoder()?;
+        let orientation = if self.auto_orient {
+            decoder.orientation()?
+        } else {
+            Orientation::NoTransforms
+        };
+        let (width, height) = decoder.dimensions();
+        let needs_resize = self
+            .max_dimension
+            .is_some_and(|max| width > max || height > max);
+        if orientation == Orientation::NoTransforms && !needs_resize && self.jpeg_quality.is_none()
+        {
+            if let Some(stripped) = format.and_then(|format| strip_metadata(format, data)) {
+                return Ok(stripped);
+            }
+        }
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+        if let Some(max) = self.max_dimension {
+            if needs_resize {
+                image = image.resize(max, max, FilterType::Lanczos3);
+            }
+        }
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(
+            &mut jpeg,
+            self.jpeg_quality.unwrap_or(Self::DEFAULT_JPEG_QUALITY),
+        )
+        .encode_image(&image.to_rgb8())?;
+        Ok(jpeg)
+    }
+    /// 读取并处理图片文件，返回处理后的图片数据。
+    pub fn process_file(&self, path: impl AsRef<Path>) -> Result<Vec<u8>, ImageError> {
+        self.process(&std::fs::read(path)?)
+    }
+    /// 处理后的文件名，即将扩展名替换为 `processed` 的实际格式对应的扩展名。
+    pub fn processed_file_name(file_name: &str, processed: &[u8]) -> String {
+        let extension = image::guess_format(processed)
+            .ok()
+            .and_then(|format| format.extensions_str().first().copied())
+            .unwrap_or("jpg");
+        Path::new(file_name)
+            .with_extension(extension)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// 不重新编码，直接删除 JPEG 或 PNG 中的元数据。格式不受支持或数据有误时返回 `None`.
+fn strip_metadata(format: ImageFormat, data: &[u8]) -> Option<Vec<u8>> {
+    match format {
+        ImageFormat::Jpeg => strip_jpeg_metadata(data),
+        ImageFormat::Png => strip_png_metadata(data),
+        _ => None,
+    }
+}
+/// 删除 APP1(EXIF, XMP), 非 ICC 的 APP2, APP3 至 APP13, APP15 及注释段。
+///
+/// 保留 APP0(JFIF), ICC 配置文件及 APP14(Adobe), 它们会影响图片的解码。
+fn strip_jpeg_metadata(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut stripped = vec![0xFF, 0xD8];
+    let mut i = 2;
+    loop {
+        if *data.get(i)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(i + 1)?;
+        match marker {
+            // 填充字节。
+            0xFF => {
+                i += 1;
+                continue;
+            }
+            // 扫描开始后为图像数据，原样保留。
+            0xDA | 0xD9 => {
+                stripped.extend_from_slice(&data[i..]);
+                return Some(stripped);
+            }
+            0x01 | 0xD0..=0xD7 => {
+                stripped.extend_from_slice(&data[i..i + 2]);
+                i += 2;
+                continue;
+            }
+            _ => (),
+        }
+        let len = u16::from_be_bytes([*data.get(i + 2)?, *data.get(i + 3)?]) as usize;
+        if len < 2 {
+            return None;
+        }
+        let segment = data.get(i..i + 2 + len)?;
+        let payload = &segment[4..];
+        let is_metadata = match marker {
+            0xE1 | 0xE3..=0xED | 0xEF | 0xFE => true,
+            0xE2 => !payload.starts_with(b"ICC_PROFILE\0"),
+            _ => false,
+        };
+        if !is_metadata {
+            stripped.extend_from_slice(segment);
+        }
+        i += 2 + len;
+    }
+}
+/// 删除 eXIf, tEXt, zTXt, iTXt 及 tIME 块。
+fn strip_png_metadata(data: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !data.starts_with(SIGNATURE) {
+        return None;
+    }
+    let mut stripped = SIGNATURE.to_vec();
+    let mut i = SIGNATURE.len();
+    while i < data.len() {
+        let len = u32::from_be_bytes(data.get(i..i + 4)?.try_into().ok()?) as usize;
+        let chunk = data.get(i..i + 12 + len)?;
+        if !matches!(
+            &chunk[4..8],
+            b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME"
+        ) {
+            stripped.extend_from_slice(chunk);
+        }
+        i += 12 + len;
+    }
+    Some(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::preprocess::PhotoPreprocessor;
+    use image::{ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_process() {
+        let mut png = Vec::new();
+        RgbImage::new(3000, 1500)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let jpeg = PhotoPreprocessor::new()
+            .with_max_dimension(Some(1000))
+            .process(&png)
+            .unwrap();
+        assert_eq!(image::guess_format(&jpeg).unwrap(), ImageFormat::Jpeg);
+        let image = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((image.width(), image.height()), (1000, 500));
+        assert_eq!(
+            PhotoPreprocessor::processed_file_name("1.png", &jpeg),
+            "1.jpg"
+        );
+    }
+
+    fn crc32(data: &[u8]) -> u32 {
+        !data.iter().fold(!0u32, |crc, byte| {
+            (0..8).fold(crc ^ *byte as u32, |crc, _| {
+                (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+            })
+        })
+    }
+
+    #[test]
+    fn test_strip_metadata() {
+        let mut jpeg = Vec::new();
+        RgbImage::new(30, 20)
+            .write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+        let exif = b"Exif\0\0GPS 39.9087,116.3975";
+        let mut with_exif = jpeg[..2].to_vec();
+        with_exif.extend_from_slice(&[0xFF, 0xE1]);
+        with_exif.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+        with_exif.extend_from_slice(exif);
+        with_exif.extend_from_slice(&jpeg[2..]);
+        let processed = PhotoPreprocessor::new().process(&with_exif).unwrap();
+        // 不重新编码，只删除元数据段。
+        assert_eq!(processed, jpeg);
+        assert_eq!(
+            PhotoPreprocessor::processed_file_name("1.jpeg", &processed),
+            "1.jpg"
+        );
+
+        let mut png = Vec::new();
+        RgbImage::new(30, 20)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let text = b"Comment\0secret";
+        let mut chunk = (text.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(b"tEXt");
+        chunk.extend_from_slice(text);
+        chunk.extend_from_slice(&crc32(&chunk[4..]).to_be_bytes());
+        let mut with_text = png[..33].to_vec();
+        with_text.extend_from_slice(&chunk);
+        with_text.extend_from_slice(&png[33..]);
+        assert_eq!(PhotoPreprocessor::new().process(&with_text).unwrap(), png);
+    }
+}
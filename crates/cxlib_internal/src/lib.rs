@@ -19,10 +19,18 @@ pub mod error {
         #[error(transparent)]
         ActivityError(#[from] ActivityError),
         #[error(transparent)]
+        BundleError(#[from] BundleError),
+        #[error(transparent)]
         CaptchaError(#[from] CaptchaError),
         #[error(transparent)]
+        CloudDriveError(#[from] CloudDriveError),
+        #[error(transparent)]
+        ConfigError(#[from] ConfigError),
+        #[error(transparent)]
         CourseError(#[from] CourseError),
         #[error(transparent)]
+        HomeworkError(#[from] HomeworkError),
+        #[error(transparent)]
         InitError(#[from] InitError),
         #[error(transparent)]
         LoginError(#[from] LoginError),
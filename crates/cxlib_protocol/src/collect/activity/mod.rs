@@ -1,3 +1,5 @@
 mod active_list;
+mod notice;
 
 pub use active_list::*;
+pub use notice::*;
@@ -0,0 +1,35 @@
+use crate::ProtocolItem;
+use cxlib_error::AgentError;
+use log::debug;
+use ureq::{Agent, Response};
+
+/// 分页查询课程通知，`page` 从 `1` 开始。
+pub fn notice_list(
+    client: &Agent,
+    (course_id, class_id): (i64, i64),
+    page: u32,
+    page_size: u32,
+) -> Result<Response, AgentError> {
+    let url = format!(
+        "{}?courseId={course_id}&classId={class_id}&page={page}&pageSize={page_size}",
+        ProtocolItem::NoticeList,
+    );
+    debug!("{url}");
+    Ok(client.get(&url).call()?)
+}
+
+/// 查询通知详情。
+pub fn notice_detail(client: &Agent, notice_id: &str) -> Result<Response, AgentError> {
+    Ok(client
+        .get(&ProtocolItem::NoticeDetail.to_string())
+        .query("noticeId", notice_id)
+        .call()?)
+}
+
+/// 将通知标记为已读。
+pub fn notice_mark_read(client: &Agent, notice_id: &str) -> Result<Response, AgentError> {
+    Ok(client
+        .post(&ProtocolItem::NoticeMarkRead.to_string())
+        .query("noticeId", notice_id)
+        .call()?)
+}
@@ -0,0 +1,30 @@
+use crate::ProtocolItem;
+use cxlib_error::AgentError;
+use ureq::{Agent, Response};
+
+// 获取班级详情，包含学期、上课时间、学分及班级人数
+pub fn course_detail(client: &Agent, class_id: i64) -> Result<Response, AgentError> {
+    Ok(client
+        .get(&ProtocolItem::GasClazz.to_string())
+        .query("id", &class_id.to_string())
+        .query(
+            "fields",
+            "id,name,studentcount,semester,begindate,enddate,schedule,credit,course.fields(id,name)",
+        )
+        .query("view", "json")
+        .call()?)
+}
+
+// 获取课程章节及章节中的资料
+pub fn course_chapters(client: &Agent, class_id: i64) -> Result<Response, AgentError> {
+    Ok(client
+        .get(&ProtocolItem::GasClazz.to_string())
+        .query("id", &class_id.to_string())
+        .query(
+            "fields",
+            "id,course.fields(id,name,knowledge.fields(id,name,indexorder,parentnodeid,layer,label,\
+             attachment.fields(id,name,type,objectid,extension)))",
+        )
+        .query("view", "json")
+        .call()?)
+}
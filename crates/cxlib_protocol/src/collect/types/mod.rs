@@ -1,7 +1,9 @@
 mod back_clazz_data;
+mod gas_clazz;
 mod get_location_log;
 mod pan;
 
 pub use back_clazz_data::*;
+pub use gas_clazz::*;
 pub use get_location_log::*;
 pub use pan::*;
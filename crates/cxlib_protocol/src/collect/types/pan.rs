@@ -3,7 +3,7 @@ use crate::{
     ProtocolItem,
 };
 use cxlib_error::AgentError;
-use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 use ureq::{Agent, Response};
 
@@ -13,24 +13,97 @@ pub fn pan_chaoxing(client: &Agent) -> Result<Response, AgentError> {
 }
 
 // 网盘列表
-pub fn pan_list(client: &Agent, parent_id: &str, enc: &str) -> Result<Response, AgentError> {
+pub fn pan_list(
+    client: &Agent,
+    parent_id: &str,
+    enc: &str,
+    page: u32,
+    size: u32,
+) -> Result<Response, AgentError> {
     Ok(client
         .post(&format!(
-            "{}?puid=0&shareid=0&parentId={parent_id}&page=1&size=50&enc={enc}",
+            "{}?puid=0&shareid=0&parentId={parent_id}&page={page}&size={size}&enc={enc}",
             ProtocolItem::PanList
         ))
         .call()?)
 }
 
+// 网盘新建文件夹
+pub fn pan_new_folder(
+    client: &Agent,
+    parent_id: &str,
+    name: &str,
+    enc: &str,
+) -> Result<Response, AgentError> {
+    Ok(client
+        .post(&ProtocolItem::PanNewFolder.to_string())
+        .query("parentId", parent_id)
+        .query("name", name)
+        .query("selectDlid", "onlyme")
+        .query("newfileid", "0")
+        .query("puid", "0")
+        .query("enc", enc)
+        .call()?)
+}
+
+// 网盘重命名
+pub fn pan_rename(
+    client: &Agent,
+    res_id: &str,
+    name: &str,
+    enc: &str,
+) -> Result<Response, AgentError> {
+    Ok(client
+        .post(&ProtocolItem::PanRename.to_string())
+        .query("resid", res_id)
+        .query("name", name)
+        .query("puid", "0")
+        .query("enc", enc)
+        .call()?)
+}
+
+// 网盘删除，`res_ids` 以逗号分隔
+pub fn pan_delete(client: &Agent, res_ids: &str, enc: &str) -> Result<Response, AgentError> {
+    Ok(client
+        .post(&ProtocolItem::PanDelete.to_string())
+        .query("resids", res_ids)
+        .query("puid", "0")
+        .query("enc", enc)
+        .call()?)
+}
+
+// 网盘容量信息
+pub fn pan_quota(client: &Agent, uid: &str, token: &str) -> Result<Response, AgentError> {
+    Ok(client
+        .get(&format!(
+            "{}?puid={uid}&_token={token}",
+            ProtocolItem::PanQuota
+        ))
+        .call()?)
+}
+
+// 云盘文件信息
+pub fn pan_file_status(client: &Agent, object_id: &str) -> Result<Response, AgentError> {
+    Ok(client
+        .get(&format!("{}/{object_id}", ProtocolItem::PanFileStatus))
+        .call()?)
+}
+
+// 下载文件
+pub fn pan_download(client: &Agent, url: &str) -> Result<Response, AgentError> {
+    Ok(client.get(url).call()?)
+}
+
 // 获取超星云盘的 token
 pub fn pan_token(client: &Agent) -> Result<Response, AgentError> {
     Ok(client.get(&ProtocolItem::PanToken.to_string()).call()?)
 }
 
-// 网盘上传接口
+// 网盘上传接口，`file_len` 已知时会设置 `Content-Length`
 pub fn pan_upload(
     client: &Agent,
-    file: &File,
+    file: impl Read,
+    file_len: Option<u64>,
     uid: &str,
     token: &str,
     file_name: &str,
@@ -39,10 +112,15 @@ pub fn pan_upload(
     let file_ext = file_ext.extension().and_then(|s| s.to_str()).unwrap_or("");
     let mime = mime_guess::from_ext(file_ext).first_or_octet_stream();
     let mut fields = Vec::<Field>::default();
-    Field::add_stream(&mut fields, "file", file, Some(file_name), Some(mime));
+    match file_len {
+        Some(len) => {
+            Field::add_sized_stream(&mut fields, "file", file, len, Some(file_name), Some(mime))
+        }
+        None => Field::add_stream(&mut fields, "file", file, Some(file_name), Some(mime)),
+    };
     Field::add_text(&mut fields, "puid", uid);
-    let multipart = PreparedFields::from_fields(&mut fields).unwrap();
-    Ok(client
+    let multipart = PreparedFields::from_fields(&mut fields).map_err(ureq::Error::from)?;
+    let mut request = client
         .post(&format!(
             "{}?_from=mobilelearn&_token={token}",
             ProtocolItem::PanUpload,
@@ -50,6 +128,9 @@ pub fn pan_upload(
         .set(
             "Content-Type",
             &format!("multipart/form-data; boundary={}", multipart.get_boundary()),
-        )
-        .send(multipart)?)
+        );
+    if let Some(content_length) = multipart.content_length() {
+        request = request.set("Content-Length", &content_length.to_string());
+    }
+    Ok(request.send(multipart)?)
 }
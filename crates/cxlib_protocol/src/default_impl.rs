@@ -14,6 +14,9 @@ use std::{
 
 pub enum ProtocolItem {
     ActiveList,
+    NoticeList,
+    NoticeDetail,
+    NoticeMarkRead,
     GetCaptcha,
     CheckCaptcha,
     GetServerTime,
@@ -26,12 +29,18 @@ pub enum ProtocolItem {
     PanList,
     PanToken,
     PanUpload,
+    PanNewFolder,
+    PanRename,
+    PanDelete,
+    PanQuota,
+    PanFileStatus,
     Analysis,
     Analysis2,
     GetAttendInfo,
     PptSign,
     PreSign,
     BackClazzData,
+    GasClazz,
     GetLocationLog,
     AccountManage,
     CaptchaId,
@@ -42,6 +51,13 @@ impl ProtocolItem {
     // 查询活动
     pub const ACTIVE_LIST: &'static str =
         "https://mobilelearn.chaoxing.com/v2/apis/active/student/activelist";
+    // 课程通知列表
+    pub const NOTICE_LIST: &'static str = "https://notice.chaoxing.com/apis/notice/course/list";
+    // 课程通知详情，包含正文及附件
+    pub const NOTICE_DETAIL: &'static str = "https://notice.chaoxing.com/apis/notice/course/detail";
+    // 将通知标记为已读
+    pub const NOTICE_MARK_READ: &'static str =
+        "https://notice.chaoxing.com/apis/notice/course/read";
     pub const CAPTCHA_ID: &'static str = "Qt9FIw9o4pwRjOyqM6yizZBh682qN2TU";
     // 获取滑块。
     pub const GET_CAPTCHA: &'static str =
@@ -71,6 +87,16 @@ impl ProtocolItem {
     pub const PAN_TOKEN: &'static str = "https://pan-yz.chaoxing.com/api/token/uservalid";
     // 网盘上传接口
     pub const PAN_UPLOAD: &'static str = "https://pan-yz.chaoxing.com/upload";
+    // 网盘新建文件夹
+    pub const PAN_NEW_FOLDER: &'static str = "https://pan-yz.chaoxing.com/opt/newRootfolder";
+    // 网盘重命名
+    pub const PAN_RENAME: &'static str = "https://pan-yz.chaoxing.com/opt/rename";
+    // 网盘删除
+    pub const PAN_DELETE: &'static str = "https://pan-yz.chaoxing.com/opt/delres";
+    // 网盘容量信息
+    pub const PAN_QUOTA: &'static str = "https://pan-yz.chaoxing.com/api/info";
+    // 云盘文件信息，包含文件名、大小及下载地址
+    pub const PAN_FILE_STATUS: &'static str = "https://mooc1-api.chaoxing.com/ananas/status";
     pub const QRCODE_PAT: &'static str = "https://mobilelearn.chaoxing.com/widget/sign/e";
     // analysis
     pub const ANALYSIS: &'static str = "https://mobilelearn.chaoxing.com/pptSign/analysis";
@@ -87,6 +113,8 @@ impl ProtocolItem {
     // 获取课程
     pub const BACK_CLAZZ_DATA: &'static str =
         "https://mooc1-api.chaoxing.com/mycourse/backclazzdata";
+    // 获取班级及课程详情，包含章节及章节中的资料
+    pub const GAS_CLAZZ: &'static str = "https://mooc1-api.chaoxing.com/gas/clazz";
     // 获取位置信息列表
     pub const GET_LOCATION_LOG: &'static str =
         "https://mobilelearn.chaoxing.com/v2/apis/sign/getLocationLog";
@@ -113,6 +141,9 @@ impl ProtocolItemTrait for ProtocolItem {
     fn get_default(&self) -> String {
         match self {
             Self::ActiveList => Self::ACTIVE_LIST.to_string(),
+            Self::NoticeList => Self::NOTICE_LIST.to_string(),
+            Self::NoticeDetail => Self::NOTICE_DETAIL.to_string(),
+            Self::NoticeMarkRead => Self::NOTICE_MARK_READ.to_string(),
             Self::GetCaptcha => Self::GET_CAPTCHA.to_string(),
             Self::CheckCaptcha => Self::CHECK_CAPTCHA.to_string(),
             Self::GetServerTime => Self::GET_SERVER_TIME.to_string(),
@@ -125,12 +156,18 @@ impl ProtocolItemTrait for ProtocolItem {
             Self::PanList => Self::PAN_LIST.to_string(),
             Self::PanToken => Self::PAN_TOKEN.to_string(),
             Self::PanUpload => Self::PAN_UPLOAD.to_string(),
+            Self::PanNewFolder => Self::PAN_NEW_FOLDER.to_string(),
+            Self::PanRename => Self::PAN_RENAME.to_string(),
+            Self::PanDelete => Self::PAN_DELETE.to_string(),
+            Self::PanQuota => Self::PAN_QUOTA.to_string(),
+            Self::PanFileStatus => Self::PAN_FILE_STATUS.to_string(),
             Self::Analysis => Self::ANALYSIS.to_string(),
             Self::Analysis2 => Self::ANALYSIS2.to_string(),
             Self::GetAttendInfo => Self::GET_ATTEND_INFO.to_string(),
             Self::PptSign => Self::PPT_SIGN.to_string(),
             Self::PreSign => Self::PRE_SIGN.to_string(),
             Self::BackClazzData => Self::BACK_CLAZZ_DATA.to_string(),
+            Self::GasClazz => Self::GAS_CLAZZ.to_string(),
             Self::GetLocationLog => Self::GET_LOCATION_LOG.to_string(),
             Self::AccountManage => Self::ACCOUNT_MANAGE.to_string(),
             Self::CaptchaId => Self::CAPTCHA_ID.to_string(),
@@ -147,6 +184,9 @@ impl Display for ProtocolItem {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ProtocolData {
     active_list: Option<String>,
+    notice_list: Option<String>,
+    notice_detail: Option<String>,
+    notice_mark_read: Option<String>,
     get_captcha: Option<String>,
     check_captcha: Option<String>,
     get_server_time: Option<String>,
@@ -159,12 +199,18 @@ pub struct ProtocolData {
     pan_list: Option<String>,
     pan_token: Option<String>,
     pan_upload: Option<String>,
+    pan_new_folder: Option<String>,
+    pan_rename: Option<String>,
+    pan_delete: Option<String>,
+    pan_quota: Option<String>,
+    pan_file_status: Option<String>,
     analysis: Option<String>,
     analysis2: Option<String>,
     get_attend_info: Option<String>,
     ppt_sign: Option<String>,
     pre_sign: Option<String>,
     back_clazz_data: Option<String>,
+    gas_clazz: Option<String>,
     get_location_log: Option<String>,
     account_manage: Option<String>,
     captcha_id: Option<String>,
@@ -181,6 +227,9 @@ impl ProtocolDataTrait for ProtocolData {
     ) -> T {
         match t {
             ProtocolItem::ActiveList => do_something(&self.active_list),
+            ProtocolItem::NoticeList => do_something(&self.notice_list),
+            ProtocolItem::NoticeDetail => do_something(&self.notice_detail),
+            ProtocolItem::NoticeMarkRead => do_something(&self.notice_mark_read),
             ProtocolItem::GetCaptcha => do_something(&self.get_captcha),
             ProtocolItem::CheckCaptcha => do_something(&self.check_captcha),
             ProtocolItem::GetServerTime => do_something(&self.get_server_time),
@@ -193,12 +242,18 @@ impl ProtocolDataTrait for ProtocolData {
             ProtocolItem::PanList => do_something(&self.pan_list),
             ProtocolItem::PanToken => do_something(&self.pan_token),
             ProtocolItem::PanUpload => do_something(&self.pan_upload),
+            ProtocolItem::PanNewFolder => do_something(&self.pan_new_folder),
+            ProtocolItem::PanRename => do_something(&self.pan_rename),
+            ProtocolItem::PanDelete => do_something(&self.pan_delete),
+            ProtocolItem::PanQuota => do_something(&self.pan_quota),
+            ProtocolItem::PanFileStatus => do_something(&self.pan_file_status),
             ProtocolItem::Analysis => do_something(&self.analysis),
             ProtocolItem::Analysis2 => do_something(&self.analysis2),
             ProtocolItem::GetAttendInfo => do_something(&self.get_attend_info),
             ProtocolItem::PptSign => do_something(&self.ppt_sign),
             ProtocolItem::PreSign => do_something(&self.pre_sign),
             ProtocolItem::BackClazzData => do_something(&self.back_clazz_data),
+            ProtocolItem::GasClazz => do_something(&self.gas_clazz),
             ProtocolItem::GetLocationLog => do_something(&self.get_location_log),
             ProtocolItem::AccountManage => do_something(&self.account_manage),
             ProtocolItem::CaptchaId => do_something(&self.captcha_id),
@@ -213,6 +268,9 @@ impl ProtocolDataTrait for ProtocolData {
     ) -> T {
         match t {
             ProtocolItem::ActiveList => do_something(&mut self.active_list),
+            ProtocolItem::NoticeList => do_something(&mut self.notice_list),
+            ProtocolItem::NoticeDetail => do_something(&mut self.notice_detail),
+            ProtocolItem::NoticeMarkRead => do_something(&mut self.notice_mark_read),
             ProtocolItem::GetCaptcha => do_something(&mut self.get_captcha),
             ProtocolItem::CheckCaptcha => do_something(&mut self.check_captcha),
             ProtocolItem::GetServerTime => do_something(&mut self.get_server_time),
@@ -225,12 +283,18 @@ impl ProtocolDataTrait for ProtocolData {
             ProtocolItem::PanList => do_something(&mut self.pan_list),
             ProtocolItem::PanToken => do_something(&mut self.pan_token),
             ProtocolItem::PanUpload => do_something(&mut self.pan_upload),
+            ProtocolItem::PanNewFolder => do_something(&mut self.pan_new_folder),
+            ProtocolItem::PanRename => do_something(&mut self.pan_rename),
+            ProtocolItem::PanDelete => do_something(&mut self.pan_delete),
+            ProtocolItem::PanQuota => do_something(&mut self.pan_quota),
+            ProtocolItem::PanFileStatus => do_something(&mut self.pan_file_status),
             ProtocolItem::Analysis => do_something(&mut self.analysis),
             ProtocolItem::Analysis2 => do_something(&mut self.analysis2),
             ProtocolItem::GetAttendInfo => do_something(&mut self.get_attend_info),
             ProtocolItem::PptSign => do_something(&mut self.ppt_sign),
             ProtocolItem::PreSign => do_something(&mut self.pre_sign),
             ProtocolItem::BackClazzData => do_something(&mut self.back_clazz_data),
+            ProtocolItem::GasClazz => do_something(&mut self.gas_clazz),
             ProtocolItem::GetLocationLog => do_something(&mut self.get_location_log),
             ProtocolItem::AccountManage => do_something(&mut self.account_manage),
             ProtocolItem::CaptchaId => do_something(&mut self.captcha_id),
@@ -243,6 +307,9 @@ impl Default for ProtocolData {
     fn default() -> Self {
         Self {
             active_list: Some(ProtocolItem::ACTIVE_LIST.to_string()),
+            notice_list: Some(ProtocolItem::NOTICE_LIST.to_string()),
+            notice_detail: Some(ProtocolItem::NOTICE_DETAIL.to_string()),
+            notice_mark_read: Some(ProtocolItem::NOTICE_MARK_READ.to_string()),
             get_captcha: Some(ProtocolItem::GET_CAPTCHA.to_string()),
             check_captcha: Some(ProtocolItem::CHECK_CAPTCHA.to_string()),
             get_server_time: Some(ProtocolItem::GET_SERVER_TIME.to_string()),
@@ -255,12 +322,18 @@ impl Default for ProtocolData {
             pan_list: Some(ProtocolItem::PAN_LIST.to_string()),
             pan_token: Some(ProtocolItem::PAN_TOKEN.to_string()),
             pan_upload: Some(ProtocolItem::PAN_UPLOAD.to_string()),
+            pan_new_folder: Some(ProtocolItem::PAN_NEW_FOLDER.to_string()),
+            pan_rename: Some(ProtocolItem::PAN_RENAME.to_string()),
+            pan_delete: Some(ProtocolItem::PAN_DELETE.to_string()),
+            pan_quota: Some(ProtocolItem::PAN_QUOTA.to_string()),
+            pan_file_status: Some(ProtocolItem::PAN_FILE_STATUS.to_string()),
             analysis: Some(ProtocolItem::ANALYSIS.to_string()),
             analysis2: Some(ProtocolItem::ANALYSIS2.to_string()),
             get_attend_info: Some(ProtocolItem::GET_ATTEND_INFO.to_string()),
             ppt_sign: Some(ProtocolItem::PPT_SIGN.to_string()),
             pre_sign: Some(ProtocolItem::PRE_SIGN.to_string()),
             back_clazz_data: Some(ProtocolItem::BACK_CLAZZ_DATA.to_string()),
+            gas_clazz: Some(ProtocolItem::GAS_CLAZZ.to_string()),
             get_location_log: Some(ProtocolItem::GET_LOCATION_LOG.to_string()),
             account_manage: Some(ProtocolItem::ACCOUNT_MANAGE.to_string()),
             captcha_id: Some(ProtocolItem::CAPTCHA_ID.to_string()),
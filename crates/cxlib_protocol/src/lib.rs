@@ -1,7 +1,7 @@
 pub mod collect;
 mod default_impl;
 #[cfg(feature = "multipart")]
-mod multipart;
+pub mod multipart;
 #[cfg(feature = "ureq")]
 pub mod utils;
 
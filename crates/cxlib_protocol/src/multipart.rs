@@ -1,29 +1,30 @@
-//! fork from [multipart](https://crates.io/crates/multipart)
-//! 此文件存在的意义是为了避免 rust 的警告。
-//! TODO: 需要测试。
+//! `multipart/form-data` 编码器。
+//!
+//! 最初 fork 自 [multipart](https://crates.io/crates/multipart), 现支持文本、内存数据及任意 [`Read`] 数据源，
+//! 可为每个字段附加头部，并在所有数据源长度均已知时给出请求体长度，以便设置 `Content-Length`.
 use log::debug;
 use mime_guess::{mime, Mime};
 use rand::Rng;
 use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::io::{Cursor, Read, Write};
 
 struct PreparedField<'d> {
     header: Cursor<Vec<u8>>,
-    stream: Box<dyn Read + 'd>,
+    body: Box<dyn Read + 'd>,
 }
 pub struct PreparedFields<'d> {
-    text_data: Cursor<Vec<u8>>,
-    streams: Vec<PreparedField<'d>>,
-    end_boundary: Cursor<String>,
+    fields: VecDeque<PreparedField<'d>>,
+    end_boundary: Cursor<Vec<u8>>,
+    boundary: String,
+    content_length: Option<u64>,
 }
 impl Read for PreparedField<'_> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        debug!("PreparedField::read()");
-
         if !cursor_at_end(&self.header) {
             self.header.read(buf)
         } else {
-            self.stream.read(buf)
+            self.body.read(buf)
         }
     }
 }
@@ -39,16 +40,14 @@ impl Read for PreparedFields<'_> {
         while total_read < buf.len() && !cursor_at_end(&self.end_boundary) {
             let buf = &mut buf[total_read..];
 
-            total_read += if !cursor_at_end(&self.text_data) {
-                self.text_data.read(buf)?
-            } else if let Some(mut field) = self.streams.pop() {
-                match field.read(buf) {
-                    Ok(0) => continue,
-                    res => {
-                        self.streams.push(field);
-                        res
+            total_read += if let Some(field) = self.fields.front_mut() {
+                match field.read(buf)? {
+                    0 => {
+                        self.fields.pop_front();
+                        continue;
                     }
-                }?
+                    n => n,
+                }
             } else {
                 self.end_boundary.read(buf)?
             };
@@ -58,131 +57,440 @@ impl Read for PreparedFields<'_> {
     }
 }
 impl<'d> PreparedFields<'d> {
+    /// 生成 boundary 时的最大尝试次数。
+    const MAX_BOUNDARY_ATTEMPTS: usize = 8;
     pub fn get_boundary(&self) -> &str {
-        let boundary = self.end_boundary.get_ref();
-        &boundary[4..boundary.len() - 2]
+        &self.boundary
     }
+    /// 请求体的总字节数。存在长度未知的数据流时返回 `None`.
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+    /// 以随机 boundary 编码所有字段。
+    ///
+    /// boundary 会与文本、内存数据及头部进行比较，若有冲突则重新生成；数据流的内容无法预先检查，
+    /// 但 boundary 足够长，冲突的可能性可以忽略。
     pub fn from_fields<'n>(fields: &mut Vec<Field<'n, 'd>>) -> Result<Self, std::io::Error> {
-        fn from_stream<'d>(
-            name: &str,
-            boundary: &str,
-            content_type: &Mime,
-            filename: Option<&str>,
-            stream: Box<dyn Read + 'd>,
-        ) -> PreparedField<'d> {
+        for _ in 0..Self::MAX_BOUNDARY_ATTEMPTS {
+            let boundary = rand::thread_rng()
+                .sample_iter(&rand::distributions::Alphanumeric)
+                .take(32)
+                .map(|c| c as char)
+                .collect::<String>();
+            if !Field::collides(fields, &boundary) {
+                return Self::from_fields_with_boundary(fields, &boundary);
+            }
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "无法生成与数据不冲突的 boundary.",
+        ))
+    }
+    /// 以指定的 boundary 编码所有字段。若 boundary 与数据冲突，返回错误。
+    pub fn from_fields_with_boundary<'n>(
+        fields: &mut Vec<Field<'n, 'd>>,
+        boundary: &str,
+    ) -> Result<Self, std::io::Error> {
+        if boundary.is_empty() || Field::collides(fields, boundary) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("boundary `{boundary}` 为空或与数据冲突。"),
+            ));
+        }
+        debug!("Field count: {}", fields.len());
+        let mut prepared = VecDeque::with_capacity(fields.len());
+        let mut content_length = Some(0u64);
+        for (index, field) in fields.drain(..).enumerate() {
             let mut header = Vec::new();
-
+            // 除第一个字段外，每个字段的分隔符前都有换行，该换行属于上一个字段的内容之后。
+            if index > 0 {
+                header.extend_from_slice(b"\r\n");
+            }
             write!(
                 header,
-                "{}\r\nContent-Disposition: form-data; name=\"{}\"",
-                boundary, name
-            )
-            .unwrap();
-
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"",
+                escape_quoted(&field.name)
+            )?;
+            let (filename, content_type, body, len): (_, _, Box<dyn Read + 'd>, _) =
+                match field.data {
+                    Data::Text(text) => {
+                        let len = text.len() as u64;
+                        let body = match text {
+                            Cow::Borrowed(text) => Cursor::new(Cow::Borrowed(text.as_bytes())),
+                            Cow::Owned(text) => Cursor::new(Cow::Owned(text.into_bytes())),
+                        };
+                        (None, None, Box::new(body), Some(len))
+                    }
+                    Data::Bytes(bytes) => {
+                        let len = bytes.data.len() as u64;
+                        (
+                            bytes.filename,
+                            Some(bytes.content_type),
+                            Box::new(Cursor::new(bytes.data)),
+                            Some(len),
+                        )
+                    }
+                    Data::Stream(stream) => (
+                        stream.filename,
+                        Some(stream.content_type),
+                        stream.stream,
+                        stream.len,
+                    ),
+                };
             if let Some(filename) = filename {
-                write!(header, "; filename=\"{}\"", filename).unwrap();
+                write!(header, "; filename=\"{}\"", escape_quoted(&filename))?;
             }
-
-            write!(header, "\r\nContent-Type: {}\r\n\r\n", content_type).unwrap();
-
-            PreparedField {
-                header: Cursor::new(header),
-                stream,
+            header.extend_from_slice(b"\r\n");
+            if let Some(content_type) = content_type {
+                write!(header, "Content-Type: {content_type}\r\n")?;
             }
-        }
-        debug!("Field count: {}", fields.len());
-        let mut boundary = format!(
-            "\r\n--{}",
-            rand::thread_rng()
-                .sample_iter(&rand::distributions::Alphanumeric)
-                .take(16)
-                .map(|c| c as char)
-                .collect::<String>()
-        );
-
-        let mut text_data = Vec::new();
-        let mut streams = Vec::new();
-
-        for field in fields.drain(..) {
-            match field.data {
-                Data::Text(text) => write!(
-                    text_data,
-                    "{}\r\nContent-Disposition: form-data; \
-                     name=\"{}\"\r\n\r\n{}",
-                    boundary, field.name, text
-                )
-                .unwrap(),
-                Data::Stream(stream) => {
-                    streams.push(from_stream(
-                        &field.name,
-                        &boundary,
-                        &stream.content_type,
-                        stream.filename.as_deref(),
-                        stream.stream,
-                    ));
-                }
+            for (name, value) in field.headers {
+                write!(header, "{name}: {value}\r\n")?;
             }
+            header.extend_from_slice(b"\r\n");
+            content_length = content_length
+                .zip(len)
+                .map(|(total, len)| total + header.len() as u64 + len);
+            prepared.push_back(PreparedField {
+                header: Cursor::new(header),
+                body,
+            });
         }
-
-        // So we don't write a spurious end boundary
-        if text_data.is_empty() && streams.is_empty() {
-            boundary = String::new();
+        // 没有字段时不写入结束分隔符。
+        let end_boundary = if prepared.is_empty() {
+            Vec::new()
         } else {
-            boundary.push_str("--");
-        }
-
+            format!("\r\n--{boundary}--\r\n").into_bytes()
+        };
+        let content_length = content_length.map(|total| total + end_boundary.len() as u64);
         Ok(PreparedFields {
-            text_data: Cursor::new(text_data),
-            streams,
-            end_boundary: Cursor::new(boundary),
+            fields: prepared,
+            end_boundary: Cursor::new(end_boundary),
+            boundary: boundary.to_owned(),
+            content_length,
         })
     }
 }
 struct Stream<'n, 'd> {
     filename: Option<Cow<'n, str>>,
     content_type: Mime,
+    len: Option<u64>,
     stream: Box<dyn Read + 'd>,
 }
+struct Bytes<'n, 'd> {
+    filename: Option<Cow<'n, str>>,
+    content_type: Mime,
+    data: Cow<'d, [u8]>,
+}
 enum Data<'n, 'd> {
     Text(Cow<'d, str>),
+    Bytes(Bytes<'n, 'd>),
     Stream(Stream<'n, 'd>),
 }
 pub struct Field<'n, 'd> {
     name: Cow<'n, str>,
+    headers: Vec<(Cow<'n, str>, Cow<'n, str>)>,
     data: Data<'n, 'd>,
 }
 fn cursor_at_end<T: AsRef<[u8]>>(cursor: &Cursor<T>) -> bool {
     cursor.position() == (cursor.get_ref().as_ref().len() as u64)
 }
+/// 转义 `Content-Disposition` 中引号内的值。
+fn escape_quoted(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+fn contains_bytes(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
 impl<'n, 'd> Field<'n, 'd> {
+    fn push(fields: &mut Vec<Field<'n, 'd>>, field: Field<'n, 'd>) -> &mut Field<'n, 'd> {
+        let index = fields.len();
+        fields.push(field);
+        &mut fields[index]
+    }
+    /// 检查 boundary 是否出现在文本、内存数据、字段名或头部中。
+    fn collides(fields: &[Field<'n, 'd>], boundary: &str) -> bool {
+        let boundary = boundary.as_bytes();
+        fields.iter().any(|field| {
+            contains_bytes(field.name.as_bytes(), boundary)
+                || field.headers.iter().any(|(name, value)| {
+                    contains_bytes(name.as_bytes(), boundary)
+                        || contains_bytes(value.as_bytes(), boundary)
+                })
+                || match &field.data {
+                    Data::Text(text) => contains_bytes(text.as_bytes(), boundary),
+                    Data::Bytes(bytes) => contains_bytes(&bytes.data, boundary),
+                    Data::Stream(_) => false,
+                }
+        })
+    }
+    /// 添加长度未知的数据流。存在此类字段时，[`PreparedFields::content_length`] 返回 `None`.
     pub fn add_stream<N, R, F>(
         fields: &mut Vec<Field<'n, 'd>>,
         name: N,
         stream: R,
         filename: Option<F>,
         mime: Option<Mime>,
-    ) where
+    ) -> &mut Field<'n, 'd>
+    where
         N: Into<Cow<'n, str>>,
         R: Read + 'd,
         F: Into<Cow<'n, str>>,
     {
-        fields.push(Field {
-            name: name.into(),
-            data: Data::Stream(Stream {
-                content_type: mime.unwrap_or(mime::APPLICATION_OCTET_STREAM),
-                filename: filename.map(|f| f.into()),
-                stream: Box::new(stream),
-            }),
-        });
-    }
-    pub fn add_text<N, T>(fields: &mut Vec<Field<'n, 'd>>, name: N, text: T)
+        Self::push(
+            fields,
+            Field {
+                name: name.into(),
+                headers: Vec::new(),
+                data: Data::Stream(Stream {
+                    content_type: mime.unwrap_or(mime::APPLICATION_OCTET_STREAM),
+                    filename: filename.map(|f| f.into()),
+                    len: None,
+                    stream: Box::new(stream),
+                }),
+            },
+        )
+    }
+    /// 添加长度已知的数据流，`len` 必须与实际读取的字节数一致。
+    pub fn add_sized_stream<N, R, F>(
+        fields: &mut Vec<Field<'n, 'd>>,
+        name: N,
+        stream: R,
+        len: u64,
+        filename: Option<F>,
+        mime: Option<Mime>,
+    ) -> &mut Field<'n, 'd>
+    where
+        N: Into<Cow<'n, str>>,
+        R: Read + 'd,
+        F: Into<Cow<'n, str>>,
+    {
+        Self::push(
+            fields,
+            Field {
+                name: name.into(),
+                headers: Vec::new(),
+                data: Data::Stream(Stream {
+                    content_type: mime.unwrap_or(mime::APPLICATION_OCTET_STREAM),
+                    filename: filename.map(|f| f.into()),
+                    len: Some(len),
+                    stream: Box::new(stream.take(len)),
+                }),
+            },
+        )
+    }
+    /// 添加内存中的数据。
+    pub fn add_bytes<N, B, F>(
+        fields: &mut Vec<Field<'n, 'd>>,
+        name: N,
+        data: B,
+        filename: Option<F>,
+        mime: Option<Mime>,
+    ) -> &mut Field<'n, 'd>
+    where
+        N: Into<Cow<'n, str>>,
+        B: Into<Cow<'d, [u8]>>,
+        F: Into<Cow<'n, str>>,
+    {
+        Self::push(
+            fields,
+            Field {
+                name: name.into(),
+                headers: Vec::new(),
+                data: Data::Bytes(Bytes {
+                    content_type: mime.unwrap_or(mime::APPLICATION_OCTET_STREAM),
+                    filename: filename.map(|f| f.into()),
+                    data: data.into(),
+                }),
+            },
+        )
+    }
+    pub fn add_text<N, T>(fields: &mut Vec<Field<'n, 'd>>, name: N, text: T) -> &mut Field<'n, 'd>
     where
         N: Into<Cow<'n, str>>,
         T: Into<Cow<'d, str>>,
     {
-        fields.push(Field {
-            name: name.into(),
-            data: Data::Text(text.into()),
-        });
+        Self::push(
+            fields,
+            Field {
+                name: name.into(),
+                headers: Vec::new(),
+                data: Data::Text(text.into()),
+            },
+        )
+    }
+    /// 为该字段添加头部。`Content-Disposition` 及 `Content-Type` 由编码器生成，不应通过此方法添加。
+    ///
+    /// # Panics
+    ///
+    /// 名称或值含有 `\r`、`\n`, 或名称为空、含有 `:` 时 panic, 以免注入其他头部或字段。
+    pub fn add_header<N, V>(&mut self, name: N, value: V) -> &mut Self
+    where
+        N: Into<Cow<'n, str>>,
+        V: Into<Cow<'n, str>>,
+    {
+        let (name, value) = (name.into(), value.into());
+        assert!(
+            !name.is_empty() && !name.contains([':', '\r', '\n']),
+            "无效的头部名称：`{name:?}`."
+        );
+        assert!(
+            !value.contains(['\r', '\n']),
+            "头部 `{name}` 的值无效：`{value:?}`."
+        );
+        self.headers.push((name, value));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::multipart::{Field, PreparedFields};
+    use std::io::Read;
+
+    struct Part {
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    }
+    impl Part {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+        }
+    }
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+    /// 按 RFC 7578 解析请求体。
+    fn parse(body: &[u8], boundary: &str) -> Vec<Part> {
+        let delimiter = format!("\r\n--{boundary}").into_bytes();
+        let mut rest = [b"\r\n", body].concat();
+        let mut parts = Vec::new();
+        let start = find(&rest, &delimiter).expect("缺少起始分隔符。");
+        rest.drain(..start + delimiter.len());
+        loop {
+            if rest.starts_with(b"--\r\n") {
+                assert_eq!(rest.len(), 4, "结束分隔符后有多余数据。");
+                break;
+            }
+            assert!(rest.starts_with(b"\r\n"));
+            let end = find(&rest, &delimiter).expect("缺少结束分隔符。");
+            let part = &rest[2..end];
+            let header_end = find(part, b"\r\n\r\n").unwrap();
+            let headers = std::str::from_utf8(&part[..header_end])
+                .unwrap()
+                .split("\r\n")
+                .map(|line| {
+                    let (name, value) = line.split_once(": ").unwrap();
+                    (name.to_owned(), value.to_owned())
+                })
+                .collect();
+            let body = part[header_end + 4..].to_vec();
+            parts.push(Part { headers, body });
+            rest.drain(..end + delimiter.len());
+        }
+        parts
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut fields = Vec::new();
+        Field::add_text(&mut fields, "puid", "12345");
+        Field::add_bytes(
+            &mut fields,
+            "photo",
+            &b"\x89PNG\r\n"[..],
+            Some("1.png"),
+            Some(mime_guess::mime::IMAGE_PNG),
+        )
+        .add_header("X-Extra", "value");
+        Field::add_sized_stream(
+            &mut fields,
+            "file",
+            &b"hello, world"[..],
+            5,
+            Some("a \"b\".txt"),
+            None,
+        );
+        let mut multipart = PreparedFields::from_fields(&mut fields).unwrap();
+        let boundary = multipart.get_boundary().to_owned();
+        let mut body = Vec::new();
+        // 使用较小的缓冲区以覆盖跨字段读取的情况。
+        let mut buf = [0; 7];
+        loop {
+            let n = multipart.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            body.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(multipart.content_length(), Some(body.len() as u64));
+        let parts = parse(&body, &boundary);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(
+            parts[0].header("Content-Disposition"),
+            Some("form-data; name=\"puid\"")
+        );
+        assert_eq!(parts[0].body, b"12345");
+        assert_eq!(parts[1].header("Content-Type"), Some("image/png"));
+        assert_eq!(parts[1].header("X-Extra"), Some("value"));
+        assert_eq!(parts[1].body, b"\x89PNG\r\n");
+        assert_eq!(
+            parts[2].header("Content-Disposition"),
+            Some("form-data; name=\"file\"; filename=\"a %22b%22.txt\"")
+        );
+        assert_eq!(parts[2].body, b"hello");
+    }
+
+    #[test]
+    fn test_unknown_length() {
+        let mut fields = Vec::new();
+        Field::add_stream(&mut fields, "file", &b"data"[..], None::<&str>, None);
+        let mut multipart = PreparedFields::from_fields(&mut fields).unwrap();
+        assert_eq!(multipart.content_length(), None);
+        let mut body = Vec::new();
+        multipart.read_to_end(&mut body).unwrap();
+        let parts = parse(&body, &multipart.get_boundary().to_owned());
+        assert_eq!(parts[0].body, b"data");
+    }
+
+    #[test]
+    fn test_boundary_collision() {
+        let mut fields = Vec::new();
+        Field::add_text(&mut fields, "text", "--abcdef--");
+        assert!(PreparedFields::from_fields_with_boundary(&mut fields, "abcdef").is_err());
+        assert_eq!(fields.len(), 1);
+        assert!(PreparedFields::from_fields_with_boundary(&mut fields, "xyz").is_ok());
+        let mut empty = Vec::new();
+        let mut multipart = PreparedFields::from_fields(&mut empty).unwrap();
+        assert_eq!(multipart.content_length(), Some(0));
+        let mut body = Vec::new();
+        multipart.read_to_end(&mut body).unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn test_add_header_rejects_line_breaks() {
+        for (name, value) in [
+            ("X-Extra", "a\r\nX-Injected: b"),
+            ("X-Extra\r\nX-Injected", "b"),
+            ("X-Extra: a", "b"),
+            ("", "b"),
+        ] {
+            let result = std::panic::catch_unwind(|| {
+                let mut fields = Vec::new();
+                Field::add_text(&mut fields, "puid", "12345").add_header(name, value);
+            });
+            assert!(result.is_err(), "`{name:?}: {value:?}` 应被拒绝。");
+        }
     }
 }
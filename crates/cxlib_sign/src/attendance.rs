@@ -0,0 +1,238 @@
+use crate::{SignError, SignState, SignTrait};
+use cxlib_activity::{Activity, RawSign};
+use cxlib_protocol::ProtocolItem;
+use cxlib_types::Course;
+use cxlib_user::Session;
+use cxlib_utils::{host_of, WorkerPool};
+use log::debug;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime},
+};
+use ureq::serde_json;
+
+/// 单次签到的出勤记录。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AttendanceRecord {
+    pub active_id: String,
+    pub name: String,
+    pub start_time_mills: u64,
+    pub state: SignState,
+}
+impl AttendanceRecord {
+    pub fn new(sign: &RawSign, state: SignState) -> Self {
+        Self {
+            active_id: sign.active_id.clone(),
+            name: sign.name.clone(),
+            start_time_mills: sign.start_time_mills,
+            state,
+        }
+    }
+}
+
+/// 签到是否已经结束。与 [`SignTrait::is_valid`] 相反，但不会因开始时间晚于当前时间而出错。
+///
+/// `validity` 为签到的有效时长，参见 [`Config::sign_validity`](cxlib_store::Config::sign_validity).
+fn is_sign_over(sign: &RawSign, now_mills: u64, validity: Duration) -> bool {
+    sign.status_code != 1 || sign.start_time_mills + validity.as_millis() as u64 <= now_mills
+}
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// # AttendanceReport
+///
+/// 某用户在某课程中的出勤统计。
+///
+/// 通过 [`AttendanceReport::get`] 获取该课程所有已结束的签到及其[签到后状态](SignState)，
+/// 可据此统计各状态的次数、出勤率及缺勤列表，并导出为 CSV 或 JSON.
+#[derive(Debug, Clone)]
+pub struct AttendanceReport {
+    course: Course,
+    records: Vec<AttendanceRecord>,
+}
+impl AttendanceReport {
+    /// 由已有的出勤记录构造统计，记录按签到开始时间排序。
+    pub fn new(course: Course, mut records: Vec<AttendanceRecord>) -> Self {
+        records.sort_by_key(|r| r.start_time_mills);
+        Self { course, records }
+    }
+    /// 获取用户在该课程中所有已结束的签到的状态，并生成统计。
+    ///
+    /// 进行中的签到不计入统计。
+    pub fn get(session: &Session, course: &Course) -> Result<Self, SignError> {
+        let now = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let validity = cxlib_store::Config::get().sign_validity;
+        let signs = Activity::get_list_from_course(session, course)?
+            .into_iter()
+            .filter_map(|activity| match activity {
+                Activity::RawSign(sign) if is_sign_over(&sign, now, validity) => Some(sign),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        debug!(
+            "课程[{}]共有 {} 个已结束的签到。",
+            course.get_name(),
+            signs.len()
+        );
+        let states = WorkerPool::get_global().map(
+            host_of(&ProtocolItem::GetAttendInfo.to_string()),
+            signs.iter(),
+            |sign| sign.get_sign_state(session),
+        );
+        let records = signs
+            .iter()
+            .zip(states)
+            .map(|(sign, state)| state.map(|state| AttendanceRecord::new(sign, state)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(course.clone(), records))
+    }
+    pub fn get_course(&self) -> &Course {
+        &self.course
+    }
+    pub fn get_records(&self) -> &[AttendanceRecord] {
+        &self.records
+    }
+    /// 各签到后状态的次数。
+    pub fn get_counts(&self) -> BTreeMap<SignState, usize> {
+        let mut counts = BTreeMap::new();
+        for record in &self.records {
+            *counts.entry(record.state).or_default() += 1;
+        }
+        counts
+    }
+    /// 出勤率，即出勤次数占（总次数 - 请假次数）的比例。
+    ///
+    /// 参见 [`SignState::is_present`] 与 [`SignState::is_excused`]. 没有需要出勤的签到时返回 `None`.
+    pub fn get_attendance_rate(&self) -> Option<f64> {
+        let present = self.records.iter().filter(|r| r.state.is_present()).count();
+        let excused = self.records.iter().filter(|r| r.state.is_excused()).count();
+        let required = self.records.len() - excused;
+        if required == 0 {
+            None
+        } else {
+            Some(present as f64 / required as f64)
+        }
+    }
+    /// 缺勤的签到。参见 [`SignState::is_absent`].
+    pub fn get_absences(&self) -> Vec<&AttendanceRecord> {
+        self.records
+            .iter()
+            .filter(|r| r.state.is_absent())
+            .collect()
+    }
+    /// 导出为 CSV, 每行一个签到，时间为本地时间。
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("course,active_id,name,start_time,state\n");
+        for record in &self.records {
+            let start = chrono::DateTime::<chrono::Local>::from(
+                std::time::UNIX_EPOCH + Duration::from_millis(record.start_time_mills),
+            );
+            let line = [
+                csv_field(self.course.get_name()),
+                csv_field(&record.active_id),
+                csv_field(&record.name),
+                start.format("%Y-%m-%d %H:%M:%S").to_string(),
+                format!("{:?}", record.state),
+            ]
+            .join(",");
+            csv.push_str(&line);
+            csv.push('\n');
+        }
+        csv
+    }
+    /// 导出为 JSON, 包含统计结果及所有记录。
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        #[derive(Serialize)]
+        struct Report<'a> {
+            course: &'a Course,
+            total: usize,
+            counts: BTreeMap<SignState, usize>,
+            attendance_rate: Option<f64>,
+            absences: Vec<&'a AttendanceRecord>,
+            records: &'a [AttendanceRecord],
+        }
+        serde_json::to_string_pretty(&Report {
+            course: &self.course,
+            total: self.records.len(),
+            counts: self.get_counts(),
+            attendance_rate: self.get_attendance_rate(),
+            absences: self.get_absences(),
+            records: &self.records,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{attendance::is_sign_over, AttendanceRecord, AttendanceReport, SignState};
+    use cxlib_activity::RawSign;
+    use cxlib_types::Course;
+    use std::time::Duration;
+
+    fn record(active_id: &str, start_time_mills: u64, state: SignState) -> AttendanceRecord {
+        AttendanceRecord {
+            active_id: active_id.to_owned(),
+            name: "签到".to_owned(),
+            start_time_mills,
+            state,
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let report = AttendanceReport::new(
+            Course::new(1, 2, "teacher", "", "高等数学"),
+            vec![
+                record("3", 3000, SignState::缺勤),
+                record("1", 1000, SignState::签到成功),
+                record("2", 2000, SignState::迟到),
+                record("4", 4000, SignState::病假),
+                record("5", 5000, SignState::未签),
+            ],
+        );
+        let counts = report.get_counts();
+        assert_eq!(counts[&SignState::签到成功], 1);
+        assert_eq!(counts[&SignState::缺勤], 1);
+        assert_eq!(counts.get(&SignState::教师代签), None);
+        assert_eq!(report.get_attendance_rate(), Some(0.5));
+        let absences = report
+            .get_absences()
+            .into_iter()
+            .map(|r| r.active_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(absences, vec!["3", "5"]);
+        let csv = report.to_csv();
+        assert_eq!(csv.lines().count(), 6);
+        assert!(csv.lines().nth(1).unwrap().ends_with(",签到成功"));
+        let json = report.to_json().unwrap();
+        assert!(json.contains("\"attendance_rate\": 0.5"));
+    }
+
+    #[test]
+    fn test_is_sign_over() {
+        let sign = RawSign {
+            start_time_mills: 1000,
+            active_id: "1".to_owned(),
+            name: "签到".to_owned(),
+            course: Course::new(1, 2, "teacher", "", "高等数学"),
+            other_id: "2".to_owned(),
+            status_code: 1,
+        };
+        assert!(!is_sign_over(&sign, 2000, Duration::from_secs(7200)));
+        assert!(is_sign_over(&sign, 2000, Duration::from_millis(500)));
+        let ended = RawSign {
+            status_code: 2,
+            ..sign
+        };
+        assert!(is_sign_over(&ended, 2000, Duration::from_secs(7200)));
+    }
+}
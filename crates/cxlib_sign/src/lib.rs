@@ -6,11 +6,13 @@ use cxlib_protocol::{collect::sign as protocol, utils::PPTSignHelper};
 use cxlib_types::{Course, Dioption, LocationWithRange};
 use cxlib_user::Session;
 use log::info;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, ops::Add};
 
+pub use attendance::*;
 pub use cxlib_error::SignError;
 
+mod attendance;
 pub mod utils;
 
 /// # [`SignTrait`]
@@ -42,15 +44,16 @@ pub trait SignTrait: Ord {
     /// [`RawSign`] 的各字段均为 `pub`,
     /// 故可以通过本函数获取一些签到通用的信息。
     fn as_inner(&self) -> &RawSign;
-    /// 判断签到活动是否有效（目前认定两小时内未结束的签到为有效签到）。
+    /// 判断签到活动是否有效（认定开始后一定时间内未结束的签到为有效签到，默认为两小时）。
+    ///
+    /// 有效时长参见 [`Config::sign_validity`](cxlib_store::Config::sign_validity).
     fn is_valid(&self) -> bool {
         let time = std::time::Duration::from_millis(self.as_inner().start_time_mills);
-        let two_hours = std::time::Duration::from_secs(7200);
         self.as_inner().status_code == 1
             && std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH.add(time))
                 .log_unwrap()
-                < two_hours
+                < cxlib_store::Config::get().sign_validity
     }
     /// 获取签到后状态。参见返回类型 [`SignState`].
     fn get_sign_state(&self, session: &Session) -> Result<SignState, SignError> {
@@ -223,7 +226,19 @@ impl SignResult {
 }
 //noinspection ALL
 /// 签到后状态。
-#[derive(num_enum::FromPrimitive, num_enum::IntoPrimitive)]
+#[derive(
+    num_enum::FromPrimitive,
+    num_enum::IntoPrimitive,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+)]
 #[repr(i64)]
 pub enum SignState {
     #[default]
@@ -239,6 +254,26 @@ pub enum SignState {
     签到已过期 = 11,
     公假 = 12,
 }
+impl SignState {
+    /// 是否视为出勤（签到成功、教师代签、迟到及早退）。
+    pub fn is_present(&self) -> bool {
+        matches!(
+            self,
+            SignState::签到成功 | SignState::教师代签 | SignState::迟到 | SignState::早退
+        )
+    }
+    /// 是否为请假（含病假、事假及公假）。
+    pub fn is_excused(&self) -> bool {
+        matches!(
+            self,
+            SignState::请假 | SignState::病假 | SignState::事假 | SignState::公假
+        )
+    }
+    /// 是否视为缺勤。对已结束的签到而言，未签和签到已过期也视为缺勤。
+    pub fn is_absent(&self) -> bool {
+        !self.is_present() && !self.is_excused()
+    }
+}
 
 /// 签到以及其他活动的原始类型。不应使用。
 #[derive(Debug)]
@@ -3,6 +3,7 @@ use cxlib_activity::RawSign;
 use cxlib_captcha::{utils::find_captcha, CaptchaId, DEFAULT_CAPTCHA_TYPE};
 use cxlib_error::{CxlibResultUtils, SignError};
 use cxlib_protocol::{utils::PPTSignHelper, ProtocolItem, ProtocolItemTrait};
+use cxlib_store::Config;
 use cxlib_types::{Dioption, LocationWithRange};
 use cxlib_user::Session;
 use log::{debug, trace, warn};
@@ -48,7 +49,7 @@ pub fn analysis_after_presign(
             .expect("Convert response of analysis2 into String failed.")
     );
     // 防止行为检测导致失败。
-    std::thread::sleep(std::time::Duration::from_millis(500));
+    std::thread::sleep(Config::get().presign_delay);
     Ok(PreSignResult::Data {
         url: presign_url,
         data: captcha_id_and_location,
@@ -0,0 +1,316 @@
+use crate::Dir;
+use cxlib_error::BundleError;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression, Crc};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const BUNDLE_MAGIC: &[u8] = b"CXLIB-BUNDLE\n";
+
+/// 备份文件中的单个文件。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleEntry {
+    /// 相对于配置目录的文件名。
+    pub name: String,
+    pub size: u64,
+    /// CRC-32 校验和。
+    pub checksum: u32,
+}
+/// # BundleManifest
+///
+/// 备份文件清单，记录格式版本、创建备份的 cxlib 版本及各文件的校验和。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub cxlib_version: String,
+    /// 创建时间（毫秒时间戳）。
+    pub created_mills: u64,
+    #[serde(default)]
+    pub entries: Vec<BundleEntry>,
+}
+impl BundleManifest {
+    /// 当前的备份文件格式版本。
+    pub const FORMAT_VERSION: u32 = 1;
+}
+fn checksum(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+/// 文件名不能包含路径分隔符，以免还原时写到配置目录之外。
+fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+/// 配置目录的备份与还原。
+///
+/// 备份文件为 gzip 压缩的单个文件，包含数据库、各账号的 Cookies 及 `protocol.toml` 等配置文件，
+/// 可用于迁移到新设备。
+impl Dir {
+    fn get_database_name(&self) -> Option<String> {
+        self.database_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.to_owned())
+    }
+    /// 数据库以 WAL 模式打开时的日志文件，即 `-wal` 及 `-shm` 文件。
+    fn get_database_journal_files(&self) -> Vec<PathBuf> {
+        self.get_database_name()
+            .map(|name| {
+                ["-wal", "-shm"]
+                    .map(|suffix| self.base_dir.join(format!("{name}{suffix}")))
+                    .to_vec()
+            })
+            .unwrap_or_default()
+    }
+    /// 备份时包含的文件：数据库、Cookies (`*.json`) 及配置文件 (`*.toml`)。子目录不会被备份。
+    fn get_bundle_files(&self) -> Result<Vec<String>, BundleError> {
+        let db_name = self.get_database_name();
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&self.base_dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let Some(name) = entry.file_name().to_str().map(|n| n.to_owned()) else {
+                continue;
+            };
+            if Some(&name) == db_name.as_ref() || name.ends_with(".json") || name.ends_with(".toml")
+            {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+    /// 将该配置目录备份到 `writer`, 返回备份文件清单。
+    ///
+    /// 数据库以 WAL 模式打开时，最近的修改可能仍在 `-wal` 文件中，此时直接复制数据库文件会丢失这些修改，
+    /// 因此若 `-wal` 文件不为空则返回 [`BundleError::PendingWal`]. 数据库仍在使用时，
+    /// 请取得数据库的快照并使用 [`Dir::export_bundle_with_database`].
+    pub fn export_bundle(&self, writer: impl Write) -> Result<BundleManifest, BundleError> {
+        if let Some(wal) = self.get_database_journal_files().first() {
+            if wal.metadata().is_ok_and(|m| m.len() > 0) {
+                Err(BundleError::PendingWal(
+                    self.get_database_name().unwrap_or_default(),
+                ))?
+            }
+        }
+        let mut files = Vec::new();
+        for name in self.get_bundle_files()? {
+            let data = std::fs::read(self.base_dir.join(&name))?;
+            files.push((name, data));
+        }
+        Self::write_bundle(writer, files)
+    }
+    /// 同 [`Dir::export_bundle`], 但数据库文件的内容由 `database` 给出，而非读取磁盘上的文件。
+    pub fn export_bundle_with_database(
+        &self,
+        writer: impl Write,
+        database: Vec<u8>,
+    ) -> Result<BundleManifest, BundleError> {
+        let db_name = self
+            .get_database_name()
+            .ok_or_else(|| BundleError::InvalidBundle("数据库路径不合法".to_owned()))?;
+        let mut files = vec![(db_name.clone(), database)];
+        for name in self.get_bundle_files()? {
+            if name != db_name {
+                let data = std::fs::read(self.base_dir.join(&name))?;
+                files.push((name, data));
+            }
+        }
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Self::write_bundle(writer, files)
+    }
+    fn write_bundle(
+        writer: impl Write,
+        files: Vec<(String, Vec<u8>)>,
+    ) -> Result<BundleManifest, BundleError> {
+        let manifest = BundleManifest {
+            format_version: BundleManifest::FORMAT_VERSION,
+            cxlib_version: env!("CARGO_PKG_VERSION").to_owned(),
+            created_mills: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            entries: files
+                .iter()
+                .map(|(name, data)| BundleEntry {
+                    name: name.clone(),
+                    size: data.len() as u64,
+                    checksum: checksum(data),
+                })
+                .collect(),
+        };
+        let manifest_str = toml::to_string_pretty(&manifest)
+            .map_err(|e| BundleError::InvalidBundle(e.to_string()))?;
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        encoder.write_all(BUNDLE_MAGIC)?;
+        encoder.write_all(&(manifest_str.len() as u64).to_le_bytes())?;
+        encoder.write_all(manifest_str.as_bytes())?;
+        for (_, data) in &files {
+            encoder.write_all(data)?;
+        }
+        encoder.finish()?;
+        Ok(manifest)
+    }
+    /// 读取并校验备份文件，返回清单及各文件内容，不写入任何文件。
+    pub fn read_bundle(
+        reader: impl Read,
+    ) -> Result<(BundleManifest, Vec<(String, Vec<u8>)>), BundleError> {
+        let mut decoder = GzDecoder::new(reader);
+        let mut magic = vec![0; BUNDLE_MAGIC.len()];
+        decoder.read_exact(&mut magic)?;
+        if magic != BUNDLE_MAGIC {
+            Err(BundleError::InvalidBundle("不是 cxlib 备份文件".to_owned()))?
+        }
+        let mut len = [0; 8];
+        decoder.read_exact(&mut len)?;
+        let mut manifest = Vec::new();
+        decoder
+            .by_ref()
+            .take(u64::from_le_bytes(len))
+            .read_to_end(&mut manifest)?;
+        let manifest = String::from_utf8(manifest)
+            .map_err(|e| BundleError::InvalidBundle(e.to_string()))
+            .and_then(|m| {
+                toml::from_str::<BundleManifest>(&m)
+                    .map_err(|e| BundleError::InvalidBundle(e.to_string()))
+            })?;
+        if manifest.format_version > BundleManifest::FORMAT_VERSION {
+            Err(BundleError::UnsupportedVersion(manifest.format_version))?
+        }
+        let mut files = Vec::new();
+        for entry in &manifest.entries {
+            if !is_plain_file_name(&entry.name) {
+                Err(BundleError::InvalidBundle(format!(
+                    "非法的文件名 `{}`",
+                    entry.name
+                )))?
+            }
+            let mut data = Vec::new();
+            decoder.by_ref().take(entry.size).read_to_end(&mut data)?;
+            if data.len() as u64 != entry.size || checksum(&data) != entry.checksum {
+                Err(BundleError::ChecksumMismatch(entry.name.clone()))?
+            }
+            files.push((entry.name.clone(), data));
+        }
+        Ok((manifest, files))
+    }
+    /// 将备份还原到该配置目录，目录不存在时将被创建。
+    ///
+    /// 所有文件均校验通过后才会写入。`overwrite` 为 `false` 时，若有同名文件则不写入任何文件并返回错误。
+    ///
+    /// 各文件先写入临时文件再重命名，写入中途出错不会破坏原有文件。还原数据库时会删除原有的 `-wal` 及 `-shm` 文件，
+    /// 以免旧的日志被应用到还原后的数据库上。还原前应关闭该目录中数据库的所有连接。
+    pub fn restore_bundle(
+        &self,
+        reader: impl Read,
+        overwrite: bool,
+    ) -> Result<BundleManifest, BundleError> {
+        let (manifest, files) = Self::read_bundle(reader)?;
+        std::fs::create_dir_all(&self.base_dir)?;
+        if !overwrite {
+            if let Some((name, _)) = files
+                .iter()
+                .find(|(name, _)| self.base_dir.join(name).exists())
+            {
+                Err(BundleError::AlreadyExists(name.clone()))?
+            }
+        }
+        let temp_path = |name: &str| self.base_dir.join(format!(".{name}.restore"));
+        for (i, (name, data)) in files.iter().enumerate() {
+            if let Err(e) = std::fs::write(temp_path(name), data) {
+                for (name, _) in &files[..=i] {
+                    let _ = std::fs::remove_file(temp_path(name));
+                }
+                Err(e)?
+            }
+        }
+        if files
+            .iter()
+            .any(|(name, _)| Some(name) == self.get_database_name().as_ref())
+        {
+            for journal in self.get_database_journal_files() {
+                if journal.exists() {
+                    std::fs::remove_file(journal)?;
+                }
+            }
+        }
+        for (name, _) in &files {
+            std::fs::rename(temp_path(name), self.base_dir.join(name))?;
+        }
+        Ok(manifest)
+    }
+    /// 备份当前配置目录到文件。
+    pub fn export_bundle_to_file(path: &Path) -> Result<BundleManifest, BundleError> {
+        let file = std::fs::File::create(path)?;
+        Self::get_current().export_bundle(std::io::BufWriter::new(file))
+    }
+    /// 从文件还原到当前配置目录。
+    pub fn restore_bundle_from_file(
+        path: &Path,
+        overwrite: bool,
+    ) -> Result<BundleManifest, BundleError> {
+        let file = std::fs::File::open(path)?;
+        Self::get_current().restore_bundle(std::io::BufReader::new(file), overwrite)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Dir;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let root = std::env::temp_dir().join(format!("cxlib_bundle_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let src = root.join("src");
+        std::fs::create_dir_all(src.join("test")).unwrap();
+        std::fs::write(src.join("cx.db"), b"db").unwrap();
+        std::fs::write(src.join("123.json"), b"{}").unwrap();
+        std::fs::write(src.join("protocol.toml"), b"a = 1").unwrap();
+        std::fs::write(src.join("other.txt"), b"skip").unwrap();
+        let mut bundle = Vec::new();
+        let manifest = Dir::new(&src).export_bundle(&mut bundle).unwrap();
+        assert_eq!(
+            manifest
+                .entries
+                .iter()
+                .map(|e| e.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["123.json", "cx.db", "protocol.toml"]
+        );
+
+        let dst = Dir::new(&root.join("dst"));
+        dst.restore_bundle(bundle.as_slice(), false).unwrap();
+        assert_eq!(std::fs::read(root.join("dst/cx.db")).unwrap(), b"db");
+        assert_eq!(std::fs::read(root.join("dst/123.json")).unwrap(), b"{}");
+        assert!(!root.join("dst/other.txt").exists());
+        assert!(dst.restore_bundle(bundle.as_slice(), false).is_err());
+        std::fs::write(root.join("dst/cx.db-wal"), b"stale").unwrap();
+        std::fs::write(root.join("dst/cx.db-shm"), b"stale").unwrap();
+        assert!(dst.restore_bundle(bundle.as_slice(), true).is_ok());
+        assert!(!root.join("dst/cx.db-wal").exists());
+        assert!(!root.join("dst/cx.db-shm").exists());
+        assert!(!root.join("dst/.cx.db.restore").exists());
+
+        // 日志未写回时不能直接复制数据库文件。
+        std::fs::write(src.join("cx.db-wal"), b"wal").unwrap();
+        assert!(Dir::new(&src).export_bundle(&mut Vec::new()).is_err());
+        let manifest = Dir::new(&src)
+            .export_bundle_with_database(&mut Vec::new(), b"snapshot".to_vec())
+            .unwrap();
+        assert_eq!(manifest.entries[1].name, "cx.db");
+        assert_eq!(manifest.entries[1].size, 8);
+
+        let mut broken = bundle.clone();
+        let len = broken.len();
+        broken[len / 2] ^= 0xff;
+        assert!(Dir::read_bundle(broken.as_slice()).is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+use crate::Dir;
+use cxlib_error::ConfigError;
+use log::warn;
+use std::{collections::HashMap, path::Path, sync::RwLock, time::Duration};
+
+/// # ConfigLayerTrait
+///
+/// 配置来源，按键获取字符串形式的值。
+///
+/// 参见 [`Config::load`].
+pub trait ConfigLayerTrait {
+    fn get_config_value(&self, key: &str) -> Option<String>;
+}
+impl ConfigLayerTrait for HashMap<String, String> {
+    fn get_config_value(&self, key: &str) -> Option<String> {
+        self.get(key).cloned()
+    }
+}
+
+/// 环境变量中的配置，变量名为 `CXLIB_` 加大写的键名，如 `CXLIB_PRESIGN_DELAY_MS`.
+pub struct EnvConfigLayer;
+impl EnvConfigLayer {
+    pub const PREFIX: &'static str = "CXLIB_";
+    pub fn get_var_name(key: &str) -> String {
+        format!("{}{}", Self::PREFIX, key.to_ascii_uppercase())
+    }
+}
+impl ConfigLayerTrait for EnvConfigLayer {
+    fn get_config_value(&self, key: &str) -> Option<String> {
+        std::env::var(Self::get_var_name(key)).ok()
+    }
+}
+
+/// 配置文件中的配置，文件为平坦的 TOML 表，如 `presign_delay_ms = 500`.
+#[derive(Debug, Default)]
+pub struct FileConfigLayer(HashMap<String, String>);
+impl FileConfigLayer {
+    /// 配置文件名。
+    pub const FILE_NAME: &'static str = "config.toml";
+    pub fn parse(content: &str) -> Result<Self, ConfigError> {
+        let table = content
+            .parse::<toml::Table>()
+            .map_err(|e| ConfigError::ParseError(e.to_string()))?;
+        let values = table
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let value = match value {
+                    toml::Value::String(s) => s,
+                    toml::Value::Integer(i) => i.to_string(),
+                    toml::Value::Float(f) => f.to_string(),
+                    toml::Value::Boolean(b) => b.to_string(),
+                    _ => {
+                        warn!("配置项 `{key}` 的值不是字符串或数字，已忽略。");
+                        return None;
+                    }
+                };
+                Some((key, value))
+            })
+            .collect();
+        Ok(Self(values))
+    }
+    /// 读取配置文件，文件不存在时为空。
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        if path.exists() {
+            Self::parse(&std::fs::read_to_string(path)?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+    /// 读取当前配置目录中的 [`FileConfigLayer::FILE_NAME`], 出错时警告并视为空。
+    pub fn load_default() -> Self {
+        Self::load(&Dir::get_config_file_path(Self::FILE_NAME)).unwrap_or_else(|e| {
+            warn!("配置文件读取失败，已忽略：{e}");
+            Self::default()
+        })
+    }
+}
+impl ConfigLayerTrait for FileConfigLayer {
+    fn get_config_value(&self, key: &str) -> Option<String> {
+        self.0.get_config_value(key)
+    }
+}
+
+/// 配置项的说明。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigKey {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// 字符串形式的默认值。
+    pub default: &'static str,
+}
+
+static CONFIG: RwLock<Option<Config>> = RwLock::new(None);
+
+/// # Config
+///
+/// 带类型的配置。所有配置项见 [`Config::KEYS`].
+///
+/// 配置按层加载，后者覆盖前者：默认值 < 配置文件 < 数据库中的键值表 < 环境变量。
+/// 无效的值会被忽略并给出警告。
+///
+/// 全局配置通过 [`Config::get`] 获取，未设置时仅加载默认值、配置文件及环境变量。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// 预签到后、签到前的等待时间，用于防止行为检测导致失败。
+    pub presign_delay: Duration,
+    /// 签到活动开始后被视为有效的时长。
+    pub sign_validity: Duration,
+    /// 课程最长不活跃天数，为 `None` 时不按时间排除。
+    pub max_inactive_days: Option<i64>,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            presign_delay: Duration::from_millis(500),
+            sign_validity: Duration::from_secs(7200),
+            max_inactive_days: Some(160),
+        }
+    }
+}
+impl Config {
+    pub const PRESIGN_DELAY_MS: &'static str = "presign_delay_ms";
+    pub const SIGN_VALIDITY_SECS: &'static str = "sign_validity_secs";
+    pub const MAX_INACTIVE_DAYS: &'static str = "exclude_max_inactive_days";
+    pub const KEYS: &'static [ConfigKey] = &[
+        ConfigKey {
+            name: Self::PRESIGN_DELAY_MS,
+            description: "预签到后的等待时间（毫秒）",
+            default: "500",
+        },
+        ConfigKey {
+            name: Self::SIGN_VALIDITY_SECS,
+            description: "签到活动的有效时长（秒），须大于 0",
+            default: "7200",
+        },
+        ConfigKey {
+            name: Self::MAX_INACTIVE_DAYS,
+            description: "课程最长不活跃天数，`none` 表示不按时间排除",
+            default: "160",
+        },
+    ];
+    fn parse_value<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, ConfigError>
+    where
+        T::Err: std::fmt::Display,
+    {
+        value
+            .parse()
+            .map_err(|e: T::Err| ConfigError::InvalidValue {
+                key: key.to_owned(),
+                value: value.to_owned(),
+                reason: e.to_string(),
+            })
+    }
+    /// 以字符串设置某配置项，值无效时返回错误且不做修改。
+    pub fn set_value(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let value = value.trim();
+        match key {
+            Self::PRESIGN_DELAY_MS => {
+                self.presign_delay = Duration::from_millis(Self::parse_value(key, value)?)
+            }
+            Self::SIGN_VALIDITY_SECS => {
+                let secs: u64 = Self::parse_value(key, value)?;
+                if secs == 0 {
+                    Err(ConfigError::InvalidValue {
+                        key: key.to_owned(),
+                        value: value.to_owned(),
+                        reason: "须大于 0".to_owned(),
+                    })?
+                }
+                self.sign_validity = Duration::from_secs(secs)
+            }
+            Self::MAX_INACTIVE_DAYS => {
+                self.max_inactive_days = if value.eq_ignore_ascii_case("none") {
+                    None
+                } else {
+                    let days: i64 = Self::parse_value(key, value)?;
+                    if days < 0 {
+                        Err(ConfigError::InvalidValue {
+                            key: key.to_owned(),
+                            value: value.to_owned(),
+                            reason: "不能为负数".to_owned(),
+                        })?
+                    }
+                    Some(days)
+                }
+            }
+            _ => Err(ConfigError::UnknownKey(key.to_owned()))?,
+        }
+        Ok(())
+    }
+    /// 以字符串获取某配置项。
+    pub fn get_value(&self, key: &str) -> Option<String> {
+        match key {
+            Self::PRESIGN_DELAY_MS => Some(self.presign_delay.as_millis().to_string()),
+            Self::SIGN_VALIDITY_SECS => Some(self.sign_validity.as_secs().to_string()),
+            Self::MAX_INACTIVE_DAYS => Some(
+                self.max_inactive_days
+                    .map(|days| days.to_string())
+                    .unwrap_or_else(|| "none".to_owned()),
+            ),
+            _ => None,
+        }
+    }
+    /// 检查值是否有效。
+    pub fn validate(key: &str, value: &str) -> Result<(), ConfigError> {
+        Self::default().set_value(key, value)
+    }
+    /// 在默认值的基础上依次应用各层配置。
+    pub fn load(layers: &[&dyn ConfigLayerTrait]) -> Self {
+        let mut config = Self::default();
+        for layer in layers {
+            for key in Self::KEYS {
+                if let Some(value) = layer.get_config_value(key.name) {
+                    if let Err(e) = config.set_value(key.name, &value) {
+                        warn!("{e} 已忽略。");
+                    }
+                }
+            }
+        }
+        config
+    }
+    /// 获取全局配置。
+    pub fn get() -> Config {
+        if let Some(config) = CONFIG.read().unwrap().as_ref() {
+            return config.clone();
+        }
+        let config = Self::load(&[&FileConfigLayer::load_default(), &EnvConfigLayer]);
+        CONFIG.write().unwrap().get_or_insert(config).clone()
+    }
+    /// 设置全局配置，可多次调用。
+    pub fn set(config: Config) {
+        *CONFIG.write().unwrap() = Some(config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, FileConfigLayer};
+    use std::{collections::HashMap, time::Duration};
+
+    #[test]
+    fn test_config_layers() {
+        let file = FileConfigLayer::parse(
+            "presign_delay_ms = 800\nsign_validity_secs = 0\nexclude_max_inactive_days = \"none\"",
+        )
+        .unwrap();
+        let db = HashMap::from([("presign_delay_ms".to_owned(), "1000".to_owned())]);
+        let config = Config::load(&[&file, &db]);
+        assert_eq!(config.presign_delay, Duration::from_millis(1000));
+        // 无效值被忽略。
+        assert_eq!(config.sign_validity, Duration::from_secs(7200));
+        assert_eq!(config.max_inactive_days, None);
+        for key in Config::KEYS {
+            let default = Config::default();
+            assert_eq!(default.get_value(key.name).as_deref(), Some(key.default));
+        }
+        assert!(Config::validate("unknown", "1").is_err());
+        assert!(Config::validate(Config::MAX_INACTIVE_DAYS, "-1").is_err());
+    }
+}
@@ -1,10 +1,16 @@
+mod bundle;
+mod config;
 mod store;
 
+pub use bundle::*;
+pub use config::*;
 pub use store::*;
 
-use onceinit::{OnceInit, OnceInitState, StaticDefault};
+use cxlib_error::CxlibResultUtils;
+use onceinit::{OnceInit, StaticDefault};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 
 pub struct AppInfo {
     env_arg: &'static str,
@@ -31,6 +37,21 @@ impl AppInfo {
     pub fn application(&self) -> &'static str {
         self.application
     }
+    /// 用于选择配置的环境变量名，由应用名称转为大写并加上 `_PROFILE` 得到，如 `CXSIGN_PROFILE`.
+    pub fn profile_env_arg(&self) -> String {
+        let application = self
+            .application
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        format!("{application}_PROFILE")
+    }
     pub fn get_instance() -> &'static AppInfo {
         &APP_INFO
     }
@@ -42,25 +63,32 @@ unsafe impl StaticDefault for AppInfo {
 }
 static APP_INFO: OnceInit<AppInfo> = OnceInit::uninit();
 
-static CONFIG_DIR: OnceInit<Dir> = OnceInit::uninit();
-
-fn uninit() -> bool {
-    !matches!(CONFIG_DIR.state(), OnceInitState::INITIALIZED)
-}
+static CONFIG_DIR: RwLock<Option<Dir>> = RwLock::new(None);
 
+/// # Dir
+///
+/// 配置目录，包含数据库、各账号的 Cookies 及 `protocol.toml` 等配置文件。
+///
+/// 支持多个命名配置（profile），各配置拥有独立的目录：
+///
+/// - 默认配置 [`Dir::DEFAULT_PROFILE`] 位于根目录，与旧版本的目录结构相同；
+/// - 其余配置位于根目录下的 `profiles/<name>` 中。
+///
+/// 启动时若设置了环境变量 [`AppInfo::profile_env_arg`], 则使用其指定的配置，也可通过 [`Dir::switch_profile`] 切换。
+/// 该环境变量指定的配置须已通过 [`Dir::create_profile`] 创建。
 #[derive(Clone)]
 pub struct Dir {
     base_dir: PathBuf,
     database_dir: PathBuf,
+    profile: Option<String>,
 }
 impl Dir {
+    /// 默认配置的名称。
+    pub const DEFAULT_PROFILE: &'static str = "default";
+    const PROFILES_DIR_NAME: &'static str = "profiles";
+    /// 自定义目录，不属于任何命名配置。
     pub fn new(base_dir: &Path) -> Self {
-        let base_dir = base_dir.to_path_buf();
-        let database_dir = base_dir.join("cx.db");
-        Self {
-            base_dir,
-            database_dir,
-        }
+        Self::from(base_dir.to_path_buf())
     }
     pub fn set_config_dir_info(
         env_arg: &'static str,
@@ -76,7 +104,10 @@ impl Dir {
         });
         let _ = APP_INFO.init_boxed(data);
     }
-    fn set_default_config_dir() {
+    /// 配置的根目录，即默认配置所在的目录。
+    ///
+    /// 设置了 [`AppInfo::env_arg`] 对应的环境变量时为测试目录。
+    pub fn get_root_dir() -> PathBuf {
         let AppInfo {
             env_arg,
             qualifier,
@@ -85,54 +116,167 @@ impl Dir {
         } = { APP_INFO.deref().to_owned() };
         let is_testing = std::env::var(env_arg).is_ok();
         let binding = directories::ProjectDirs::from(qualifier, organization, application).unwrap();
-        let base_dir = if is_testing {
+        if is_testing {
             binding.config_dir().join("test").to_owned()
         } else {
             binding.config_dir().to_owned()
+        }
+    }
+    fn check_profile_name(name: &str) -> Result<(), std::io::Error> {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("配置名称 `{name}` 不合法。"),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+    /// 某命名配置的目录，不会创建目录。
+    ///
+    /// 不校验名称，调用前须经过 [`check_profile_name`](Self::check_profile_name).
+    /// 外部请使用 [`create_profile`](Self::create_profile)、[`switch_profile`](Self::switch_profile) 或 [`from_env`](Self::from_env).
+    fn for_profile(name: &str) -> Self {
+        let root = Self::get_root_dir();
+        let base_dir = if name == Self::DEFAULT_PROFILE {
+            root
+        } else {
+            root.join(Self::PROFILES_DIR_NAME).join(name)
         };
-        let _ = std::fs::create_dir_all(base_dir.clone());
-        let database_dir = base_dir.join("cx.db");
-        let dir = Box::new(Self {
-            base_dir,
-            database_dir,
-        });
-        Self::set_config_dir(dir);
+        Self {
+            profile: Some(name.to_owned()),
+            ..Self::from(base_dir)
+        }
+    }
+    /// 所有已创建的配置，包含默认配置。
+    pub fn get_profiles() -> Vec<String> {
+        let mut profiles = vec![Self::DEFAULT_PROFILE.to_owned()];
+        if let Ok(entries) = std::fs::read_dir(Self::get_root_dir().join(Self::PROFILES_DIR_NAME)) {
+            let mut names = entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+                .filter_map(|e| e.file_name().to_str().map(|n| n.to_owned()))
+                .filter(|n| Self::check_profile_name(n).is_ok() && n != Self::DEFAULT_PROFILE)
+                .collect::<Vec<_>>();
+            names.sort();
+            profiles.extend(names);
+        }
+        profiles
+    }
+    /// 创建配置。新配置会复制默认配置中的 `protocol.toml`, 之后可单独修改。
+    pub fn create_profile(name: &str) -> Result<Self, std::io::Error> {
+        Self::check_profile_name(name)?;
+        let dir = Self::for_profile(name);
+        std::fs::create_dir_all(&dir.base_dir)?;
+        let protocol = Self::get_root_dir().join("protocol.toml");
+        let target = dir.base_dir.join("protocol.toml");
+        if protocol.is_file() && !target.exists() {
+            std::fs::copy(protocol, target)?;
+        }
+        Ok(dir)
+    }
+    /// 删除配置及其所有数据。不能删除默认配置及当前使用的配置。
+    pub fn delete_profile(name: &str) -> Result<(), std::io::Error> {
+        Self::check_profile_name(name)?;
+        if name == Self::DEFAULT_PROFILE || Self::get_current_profile().as_deref() == Some(name) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("不能删除配置 `{name}`."),
+            ));
+        }
+        std::fs::remove_dir_all(Self::for_profile(name).base_dir)
     }
+    /// 切换到已有的配置。
+    ///
+    /// 切换后获取的路径均指向新配置，但已打开的数据库及已加载的协议不会随之改变，一般应在程序启动时切换。
+    pub fn switch_profile(name: &str) -> Result<(), std::io::Error> {
+        Self::check_profile_name(name)?;
+        let dir = Self::for_profile(name);
+        if !dir.base_dir.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("配置 `{name}` 不存在。"),
+            ));
+        }
+        Self::set_config_dir(Box::new(dir));
+        Ok(())
+    }
+    /// 当前使用的配置名称，使用自定义目录时为 `None`.
+    pub fn get_current_profile() -> Option<String> {
+        Self::get_current().profile
+    }
+    /// 环境变量 [`AppInfo::profile_env_arg`] 指定的配置，未设置时为默认配置。
+    ///
+    /// 配置名称不合法或配置不存在时返回错误，不会创建配置。
+    pub fn from_env() -> Result<Self, std::io::Error> {
+        let env_arg = APP_INFO.deref().profile_env_arg();
+        let profile = match std::env::var(&env_arg) {
+            Ok(profile) => profile.trim().to_owned(),
+            Err(std::env::VarError::NotPresent) => Self::DEFAULT_PROFILE.to_owned(),
+            Err(e) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("环境变量 `{env_arg}` 有误：{e}."),
+                ))
+            }
+        };
+        Self::check_profile_name(&profile)?;
+        let dir = Self::for_profile(&profile);
+        if profile == Self::DEFAULT_PROFILE {
+            std::fs::create_dir_all(&dir.base_dir)?;
+        } else if !dir.base_dir.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("环境变量 `{env_arg}` 指定的配置 `{profile}` 不存在。"),
+            ));
+        }
+        Ok(dir)
+    }
+    fn set_default_config_dir() -> Result<(), std::io::Error> {
+        let dir = Self::from_env()?;
+        let mut config_dir = CONFIG_DIR.write().unwrap();
+        if config_dir.is_none() {
+            *config_dir = Some(dir);
+        }
+        Ok(())
+    }
+    /// 设置配置目录，可多次调用。
     pub fn set_config_dir(dir: Box<Self>) {
-        let _ = CONFIG_DIR.init_boxed(dir);
+        *CONFIG_DIR.write().unwrap() = Some(*dir);
+    }
+    /// 当前使用的配置目录。尚未设置时根据环境变量选择，参见 [`Dir::from_env`].
+    pub fn try_get_current() -> Result<Dir, std::io::Error> {
+        if let Some(dir) = CONFIG_DIR.read().unwrap().as_ref() {
+            return Ok(dir.clone());
+        }
+        Self::set_default_config_dir()?;
+        Self::try_get_current()
     }
-    unsafe fn get_dir_unchecked() -> &'static Dir {
-        CONFIG_DIR.get_unchecked()
+    /// 同 [`Dir::try_get_current`], 出错时 panic. 程序启动时应先调用前者以处理错误。
+    pub fn get_current() -> Dir {
+        Self::try_get_current().log_unwrap()
     }
     pub fn get_config_dir() -> PathBuf {
-        if uninit() {
-            Self::set_default_config_dir()
-        }
-        unsafe { Self::get_dir_unchecked() }.base_dir.to_path_buf()
+        Self::get_current().base_dir
+    }
+    /// 该目录的路径。
+    pub fn get_base_path(&self) -> &Path {
+        &self.base_dir
+    }
+    /// 该目录中数据库的路径。
+    pub fn get_database_path(&self) -> &Path {
+        &self.database_dir
     }
     pub fn get_database_dir() -> PathBuf {
-        if uninit() {
-            Self::set_default_config_dir()
-        }
-        unsafe { Self::get_dir_unchecked() }
-            .database_dir
-            .to_path_buf()
+        Self::get_current().database_dir
     }
     pub fn get_json_file_path(account: &str) -> PathBuf {
-        if uninit() {
-            Self::set_default_config_dir()
-        }
-        unsafe { Self::get_dir_unchecked() }
+        Self::get_current()
             .base_dir
             .join(account.to_string() + ".json")
     }
     pub fn get_config_file_path(file_name: &str) -> PathBuf {
-        if uninit() {
-            Self::set_default_config_dir()
-        }
-        unsafe { Self::get_dir_unchecked() }
-            .base_dir
-            .join(file_name)
+        Self::get_current().base_dir.join(file_name)
     }
 }
 impl From<PathBuf> for Dir {
@@ -141,6 +285,38 @@ impl From<PathBuf> for Dir {
         Self {
             base_dir,
             database_dir,
+            profile: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{AppInfo, Dir};
+
+    #[test]
+    fn test_profile_name() {
+        for name in ["work", "个人", "test-1"] {
+            assert!(Dir::check_profile_name(name).is_ok());
+        }
+        for name in ["", ".", "..", ".hidden", "a/b", "a\\b"] {
+            assert!(Dir::check_profile_name(name).is_err());
+        }
+        let dir = Dir::for_profile("work");
+        assert_eq!(dir.profile.as_deref(), Some("work"));
+        assert!(dir.base_dir.ends_with("profiles/work"));
+        assert_eq!(dir.database_dir, dir.base_dir.join("cx.db"));
+    }
+
+    #[test]
+    fn test_profile_env_arg() {
+        assert_eq!(AppInfo::get_instance().profile_env_arg(), "CXSIGN_PROFILE");
+        let info = AppInfo {
+            env_arg: "TEST_APP",
+            qualifier: "",
+            organization: "",
+            application: "my-app.cli",
+        };
+        assert_eq!(info.profile_env_arg(), "MY_APP_CLI_PROFILE");
+    }
+}
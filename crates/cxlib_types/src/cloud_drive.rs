@@ -0,0 +1,582 @@
+use cxlib_error::{CloudDriveError, MaybeFatalError};
+use cxlib_protocol::collect::types as protocol;
+use cxlib_user::Session;
+use log::{debug, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    cell::Cell,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    ops::ControlFlow,
+    time::Duration,
+};
+use ureq::{serde_json, Response};
+
+pub(crate) fn parse_response<T: DeserializeOwned>(r: Response) -> Result<T, CloudDriveError> {
+    let text = r.into_string()?;
+    serde_json::from_str(&text)
+        .map_err(|e| CloudDriveError::UnexpectedResponse(format!("{e}: {text}")))
+}
+fn check_opt_result(r: Response) -> Result<(), CloudDriveError> {
+    #[derive(Deserialize)]
+    struct OptR {
+        result: bool,
+        #[serde(default)]
+        msg: Option<String>,
+    }
+    let OptR { result, msg } = parse_response(r)?;
+    if result {
+        Ok(())
+    } else {
+        Err(CloudDriveError::UnexpectedResponse(
+            msg.unwrap_or_else(|| "操作失败".to_owned()),
+        ))
+    }
+}
+/// 截取 html 中形如 `{key}"{value}"` 的值。
+fn find_quoted_value<'a>(html: &'a str, key: &str) -> Option<&'a str> {
+    let start = html.find(key)? + key.len();
+    let end = html[start..].find('"')? + start;
+    Some(&html[start..end])
+}
+
+/// 网盘中的文件或文件夹。
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct CloudFile {
+    id: String,
+    name: String,
+    is_folder: bool,
+    size: u64,
+    object_id: Option<String>,
+    modified_time_mills: u64,
+}
+#[derive(Deserialize)]
+struct CloudFileRaw {
+    #[serde(default)]
+    residstr: Option<String>,
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    name: String,
+    /// 缺少该字段时视为文件，以免将其当作文件夹递归列出。
+    #[serde(default)]
+    isfile: Option<bool>,
+    #[serde(default, rename = "objectId")]
+    object_id: Option<String>,
+    #[serde(default)]
+    filesize: Option<u64>,
+    #[serde(default, rename = "modifyDate")]
+    modify_date: Option<u64>,
+}
+impl TryFrom<CloudFileRaw> for CloudFile {
+    type Error = CloudDriveError;
+
+    fn try_from(raw: CloudFileRaw) -> Result<Self, Self::Error> {
+        let id = match (raw.residstr, raw.id) {
+            (Some(id), _) if !id.is_empty() => id,
+            (_, Some(serde_json::Value::String(id))) => id,
+            (_, Some(serde_json::Value::Number(id))) => id.to_string(),
+            _ => {
+                return Err(CloudDriveError::UnexpectedResponse(format!(
+                    "文件 `{}` 缺少 id.",
+                    raw.name
+                )))
+            }
+        };
+        Ok(Self {
+            id,
+            name: raw.name,
+            is_folder: raw.isfile == Some(false),
+            size: raw.filesize.unwrap_or_default(),
+            object_id: raw.object_id.filter(|o| !o.is_empty()),
+            modified_time_mills: raw.modify_date.unwrap_or_default(),
+        })
+    }
+}
+impl CloudFile {
+    /// 网盘中的资源 ID, 用于列出、重命名及删除。
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    pub fn is_folder(&self) -> bool {
+        self.is_folder
+    }
+    /// 文件大小（字节），文件夹为 `0`.
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+    /// 云盘对象 ID, 用于下载及拍照签到。文件夹没有该值。
+    pub fn get_object_id(&self) -> Option<&str> {
+        self.object_id.as_deref()
+    }
+    pub fn get_modified_time_mills(&self) -> u64 {
+        self.modified_time_mills
+    }
+}
+
+/// 网盘容量信息，单位为字节。
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct CloudDriveQuota {
+    #[serde(rename = "usedsize")]
+    pub used: u64,
+    #[serde(rename = "disksize")]
+    pub total: u64,
+}
+impl CloudDriveQuota {
+    pub fn get_available(&self) -> u64 {
+        self.total.saturating_sub(self.used)
+    }
+}
+
+/// 云盘对象的元数据。
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CloudFileMetadata {
+    #[serde(rename = "objectid")]
+    pub object_id: String,
+    #[serde(rename = "filename")]
+    pub file_name: String,
+    #[serde(default)]
+    pub length: u64,
+    #[serde(default)]
+    pub crc: Option<String>,
+    #[serde(default)]
+    pub download: Option<String>,
+}
+
+/// 上传进度。
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct UploadProgress {
+    /// 已发送的文件字节数。
+    pub sent: u64,
+    /// 文件总字节数。
+    pub total: u64,
+    /// 当前为第几次重试，首次上传为 `0`.
+    pub attempt: u32,
+}
+impl UploadProgress {
+    /// 已发送的比例，介于 `0.0` 与 `1.0` 之间。
+    pub fn get_fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.sent as f64 / self.total as f64
+        }
+    }
+}
+/// 在读取时报告进度的包装，进度回调要求取消时返回错误以中断请求。
+struct ProgressReader<'a, R, F> {
+    inner: R,
+    progress: UploadProgress,
+    on_progress: &'a mut F,
+    canceled: &'a Cell<bool>,
+}
+impl<R: Read, F: FnMut(UploadProgress) -> ControlFlow<()>> Read for ProgressReader<'_, R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.sent += n as u64;
+        if (self.on_progress)(self.progress).is_break() {
+            self.canceled.set(true);
+            return Err(std::io::Error::other("上传已取消"));
+        }
+        Ok(n)
+    }
+}
+
+/// # CloudDrive
+///
+/// 超星网盘客户端。
+///
+/// 通过 [`CloudDrive::open`] 获取网盘根目录及列表所需的 `enc`, 之后可列出、遍历、新建、重命名、删除、上传及下载文件。
+pub struct CloudDrive<'s> {
+    session: &'s Session,
+    enc: String,
+    root_id: String,
+    retries: u32,
+    retry_delay: Duration,
+    max_upload_size: Option<u64>,
+}
+/// 依次获取各页（从 `1` 开始）并拼接，参见 [`CloudDrive::list`].
+fn collect_pages(
+    page_size: u32,
+    max_pages: u32,
+    mut fetch: impl FnMut(u32) -> Result<Vec<CloudFile>, CloudDriveError>,
+) -> Result<Vec<CloudFile>, CloudDriveError> {
+    let mut files = Vec::new();
+    let mut last_first_id = None;
+    for page in 1..=max_pages {
+        let mut page_files = fetch(page)?;
+        let first_id = page_files.first().map(|f| f.id.clone());
+        if first_id.is_some() && first_id == last_first_id {
+            warn!("网盘第 {page} 页与上一页重复，停止翻页。");
+            return Ok(files);
+        }
+        let is_last_page = page_files.len() < page_size as usize;
+        files.append(&mut page_files);
+        if is_last_page {
+            return Ok(files);
+        }
+        last_first_id = first_id;
+    }
+    Err(CloudDriveError::UnexpectedResponse(format!(
+        "文件夹超过 {max_pages} 页仍未列完。"
+    )))
+}
+impl<'s> CloudDrive<'s> {
+    /// 分页列出时每页的条目数。
+    pub const PAGE_SIZE: u32 = 100;
+    /// 列出文件夹时最多请求的页数。
+    pub const MAX_PAGES: u32 = 1000;
+    pub fn open(session: &'s Session) -> Result<Self, CloudDriveError> {
+        let html = protocol::pan_chaoxing(session)?.into_string()?;
+        let enc = find_quoted_value(&html, "enc =\"")
+            .ok_or_else(|| CloudDriveError::UnexpectedResponse("网盘页面中没有 `enc`.".to_owned()))?
+            .to_owned();
+        let root_id = find_quoted_value(&html, "_rootdir = \"")
+            .ok_or_else(|| {
+                CloudDriveError::UnexpectedResponse("网盘页面中没有 `_rootdir`.".to_owned())
+            })?
+            .to_owned();
+        Ok(Self {
+            session,
+            enc,
+            root_id,
+            retries: 2,
+            retry_delay: Duration::from_secs(1),
+            max_upload_size: None,
+        })
+    }
+    /// 设置上传失败时的最大重试次数，默认为 2 次。
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+    /// 设置首次重试前的等待时间，之后每次翻倍。默认为 1 秒。
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+    /// 设置上传文件的大小上限，超过该大小的文件不会上传。默认不限制。
+    pub fn with_max_upload_size(mut self, max_upload_size: Option<u64>) -> Self {
+        self.max_upload_size = max_upload_size;
+        self
+    }
+    pub fn get_session(&self) -> &'s Session {
+        self.session
+    }
+    /// 根目录的资源 ID.
+    pub fn get_root_id(&self) -> &str {
+        &self.root_id
+    }
+    /// 获取网盘 token, 上传及查询容量时需要。
+    pub fn get_token(&self) -> Result<String, CloudDriveError> {
+        #[derive(Deserialize)]
+        struct Tmp {
+            #[serde(rename = "_token")]
+            token: String,
+        }
+        let Tmp { token } = parse_response(protocol::pan_token(self.session)?)?;
+        Ok(token)
+    }
+    /// 列出文件夹中第 `page` 页（从 `1` 开始）的内容。
+    pub fn list_page(
+        &self,
+        folder_id: &str,
+        page: u32,
+        size: u32,
+    ) -> Result<Vec<CloudFile>, CloudDriveError> {
+        #[derive(Deserialize)]
+        struct ListR {
+            #[serde(default)]
+            list: Vec<CloudFileRaw>,
+        }
+        let r = protocol::pan_list(self.session, folder_id, &self.enc, page, size)?;
+        let ListR { list } = parse_response(r)?;
+        list.into_iter().map(CloudFile::try_from).collect()
+    }
+    /// 列出文件夹中的全部内容。
+    ///
+    /// 若某页与上一页的第一个条目相同（即服务端忽略了页码），则停止翻页；
+    /// 超过 [`MAX_PAGES`](Self::MAX_PAGES) 页仍未结束时返回错误。
+    pub fn list(&self, folder_id: &str) -> Result<Vec<CloudFile>, CloudDriveError> {
+        collect_pages(Self::PAGE_SIZE, Self::MAX_PAGES, |page| {
+            self.list_page(folder_id, page, Self::PAGE_SIZE)
+        })
+    }
+    /// 递归列出文件夹中的全部内容，返回值中的路径以 `/` 分隔，相对于 `folder_id`.
+    pub fn list_recursive(
+        &self,
+        folder_id: &str,
+    ) -> Result<Vec<(String, CloudFile)>, CloudDriveError> {
+        let mut result = Vec::new();
+        let mut folders = vec![(String::new(), folder_id.to_owned())];
+        while let Some((prefix, folder_id)) = folders.pop() {
+            for file in self.list(&folder_id)? {
+                let path = if prefix.is_empty() {
+                    file.name.clone()
+                } else {
+                    format!("{prefix}/{}", file.name)
+                };
+                if file.is_folder {
+                    folders.push((path.clone(), file.id.clone()));
+                }
+                result.push((path, file));
+            }
+        }
+        Ok(result)
+    }
+    /// 在文件夹中查找第一个满足条件的条目（不递归）。
+    pub fn find(
+        &self,
+        folder_id: &str,
+        p: impl Fn(&CloudFile) -> bool,
+    ) -> Result<Option<CloudFile>, CloudDriveError> {
+        Ok(self.list(folder_id)?.into_iter().find(p))
+    }
+    /// 新建文件夹，返回新文件夹。
+    pub fn mkdir(&self, parent_id: &str, name: &str) -> Result<CloudFile, CloudDriveError> {
+        #[derive(Deserialize)]
+        struct MkdirR {
+            result: bool,
+            #[serde(default)]
+            msg: Option<String>,
+            data: Option<CloudFileRaw>,
+        }
+        let r = protocol::pan_new_folder(self.session, parent_id, name, &self.enc)?;
+        match parse_response(r)? {
+            MkdirR {
+                result: true,
+                data: Some(mut data),
+                ..
+            } => {
+                data.isfile = Some(false);
+                debug!("已在网盘中新建文件夹 `{name}`.");
+                data.try_into()
+            }
+            MkdirR { msg, .. } => Err(CloudDriveError::UnexpectedResponse(
+                msg.unwrap_or_else(|| format!("无法新建文件夹 `{name}`.")),
+            )),
+        }
+    }
+    pub fn rename(&self, file: &CloudFile, name: &str) -> Result<(), CloudDriveError> {
+        check_opt_result(protocol::pan_rename(
+            self.session,
+            &file.id,
+            name,
+            &self.enc,
+        )?)
+    }
+    /// 删除文件或文件夹。
+    pub fn delete<'a>(
+        &self,
+        files: impl IntoIterator<Item = &'a CloudFile>,
+    ) -> Result<(), CloudDriveError> {
+        let res_ids = files
+            .into_iter()
+            .map(|f| f.id.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        if res_ids.is_empty() {
+            return Ok(());
+        }
+        check_opt_result(protocol::pan_delete(self.session, &res_ids, &self.enc)?)
+    }
+    /// 上传文件至网盘，返回云盘对象 ID.
+    pub fn upload(&self, file: &File, file_name: &str) -> Result<String, CloudDriveError> {
+        self.upload_with_progress(file, file_name, |_| ControlFlow::Continue(()))
+    }
+    /// 上传文件至网盘，返回云盘对象 ID.
+    ///
+    /// 每读取一块数据后会调用 `on_progress`, 若其返回 [`ControlFlow::Break`], 则取消上传并返回 [`CloudDriveError::Canceled`].
+    ///
+    /// 网盘上传接口不支持分片续传，故遇到非致命错误时会将 `source` 移至开头并整体重传，
+    /// 最多重试 [`with_retries`](Self::with_retries) 所设置的次数。
+    pub fn upload_with_progress<R, F>(
+        &self,
+        mut source: R,
+        file_name: &str,
+        mut on_progress: F,
+    ) -> Result<String, CloudDriveError>
+    where
+        R: Read + Seek,
+        F: FnMut(UploadProgress) -> ControlFlow<()>,
+    {
+        let total = source.seek(SeekFrom::End(0))?;
+        if let Some(max) = self.max_upload_size {
+            if total > max {
+                return Err(CloudDriveError::TooLarge { size: total, max });
+            }
+        }
+        let token = self.get_token()?;
+        let mut delay = self.retry_delay;
+        let mut attempt = 0;
+        loop {
+            source.seek(SeekFrom::Start(0))?;
+            let canceled = Cell::new(false);
+            let reader = ProgressReader {
+                inner: &mut source,
+                progress: UploadProgress {
+                    sent: 0,
+                    total,
+                    attempt,
+                },
+                on_progress: &mut on_progress,
+                canceled: &canceled,
+            };
+            let r = protocol::pan_upload(
+                self.session,
+                reader,
+                Some(total),
+                self.session.get_uid(),
+                &token,
+                file_name,
+            )
+            .map_err(CloudDriveError::from)
+            .and_then(|r| {
+                #[derive(Deserialize)]
+                struct Tmp {
+                    #[serde(rename = "objectId")]
+                    object_id: String,
+                }
+                parse_response::<Tmp>(r).map(|Tmp { object_id }| object_id)
+            });
+            match r {
+                _ if canceled.get() => return Err(CloudDriveError::Canceled),
+                Err(e) if !e.is_fatal() && attempt < self.retries => {
+                    attempt += 1;
+                    warn!(
+                        "上传文件 `{file_name}` 失败，将在 {} 毫秒后进行第 {attempt} 次重试。错误信息：{e}.",
+                        delay.as_millis()
+                    );
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                }
+                r => return r,
+            }
+        }
+    }
+    pub fn get_quota(&self) -> Result<CloudDriveQuota, CloudDriveError> {
+        #[derive(Deserialize)]
+        struct QuotaR {
+            data: CloudDriveQuota,
+        }
+        let token = self.get_token()?;
+        let QuotaR { data } = parse_response(protocol::pan_quota(
+            self.session,
+            self.session.get_uid(),
+            &token,
+        )?)?;
+        Ok(data)
+    }
+    /// 获取云盘对象的元数据。
+    pub fn get_metadata(&self, object_id: &str) -> Result<CloudFileMetadata, CloudDriveError> {
+        parse_response(protocol::pan_file_status(self.session, object_id)?)
+    }
+    /// 下载文件并写入 `writer`, 返回写入的字节数。
+    pub fn download(
+        &self,
+        file: &CloudFile,
+        writer: &mut impl Write,
+    ) -> Result<u64, CloudDriveError> {
+        let object_id = file
+            .get_object_id()
+            .ok_or_else(|| CloudDriveError::NotFound(format!("文件 `{}` 的对象 ID", file.name)))?;
+        let url = self
+            .get_metadata(object_id)?
+            .download
+            .ok_or_else(|| CloudDriveError::NotFound(format!("文件 `{}` 的下载地址", file.name)))?;
+        let r = protocol::pan_download(self.session, &url)?;
+        Ok(std::io::copy(&mut r.into_reader(), writer)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cloud_drive::{collect_pages, find_quoted_value, CloudFileRaw, ProgressReader},
+        CloudFile, UploadProgress,
+    };
+    use std::{cell::Cell, io::Read, ops::ControlFlow};
+    use ureq::serde_json;
+
+    #[test]
+    fn test_progress_reader() {
+        let canceled = Cell::new(false);
+        let mut reported = Vec::new();
+        let mut on_progress = |p: UploadProgress| {
+            reported.push(p.sent);
+            if p.sent >= 8 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        let mut reader = ProgressReader {
+            inner: &[0u8; 10][..],
+            progress: UploadProgress {
+                sent: 0,
+                total: 10,
+                attempt: 0,
+            },
+            on_progress: &mut on_progress,
+            canceled: &canceled,
+        };
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert!(reader.read(&mut buf).is_err());
+        assert!(canceled.get());
+        assert_eq!(reported, vec![4, 8]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let html = r#"var enc ="abcdef"; var _rootdir = "123456";"#;
+        assert_eq!(find_quoted_value(html, "enc =\""), Some("abcdef"));
+        assert_eq!(find_quoted_value(html, "_rootdir = \""), Some("123456"));
+        let raw: CloudFileRaw = serde_json::from_str(
+            r#"{"id":42,"name":"1.png","isfile":true,"objectId":"obj","filesize":1024}"#,
+        )
+        .unwrap();
+        let file = CloudFile::try_from(raw).unwrap();
+        assert_eq!(file.get_id(), "42");
+        assert!(!file.is_folder());
+        assert_eq!(file.get_object_id(), Some("obj"));
+        assert_eq!(file.get_size(), 1024);
+        let raw: CloudFileRaw = serde_json::from_str(r#"{"id":43,"name":"2.png"}"#).unwrap();
+        assert!(!CloudFile::try_from(raw).unwrap().is_folder());
+        let raw: CloudFileRaw =
+            serde_json::from_str(r#"{"id":44,"name":"dir","isfile":false}"#).unwrap();
+        assert!(CloudFile::try_from(raw).unwrap().is_folder());
+    }
+
+    #[test]
+    fn test_collect_pages() {
+        let file = |id: u32| CloudFile {
+            id: id.to_string(),
+            name: id.to_string(),
+            is_folder: false,
+            size: 0,
+            object_id: None,
+            modified_time_mills: 0,
+        };
+        // 服务端忽略页码，总是返回第一页。
+        let files = collect_pages(2, 10, |_| Ok(vec![file(1), file(2)])).unwrap();
+        assert_eq!(files.len(), 2);
+        let files = collect_pages(2, 10, |page| {
+            Ok(match page {
+                1 => vec![file(1), file(2)],
+                2 => vec![file(3)],
+                _ => unreachable!(),
+            })
+        })
+        .unwrap();
+        assert_eq!(files.len(), 3);
+        let mut next = 0;
+        assert!(collect_pages(2, 10, |_| {
+            next += 2;
+            Ok(vec![file(next), file(next + 1)])
+        })
+        .is_err());
+    }
+}
@@ -1,25 +1,59 @@
 use cxlib_error::{CxlibResultUtils, MaybeFatalError};
-use cxlib_protocol::collect::types as protocol;
+use cxlib_protocol::{collect::types as protocol, ProtocolItem};
 use cxlib_user::LoginError;
 use cxlib_user::Session;
+use cxlib_utils::{host_of, WorkerPool};
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use std::{
+    cmp::Ordering,
     collections::{hash_map::Entry, HashMap},
     fmt::Display,
+    hash::{Hash, Hasher},
     ops::Deref,
+    sync::Mutex,
+    time::{Duration, SystemTime},
 };
 use ureq::serde_json;
 
 pub use cxlib_error::CourseError;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+/// 课程。
+///
+/// 相等、哈希及排序仅由课程号和班级号决定，学期、归档状态等信息变化时仍视为同一课程。
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Course {
     id: i64,
     class_id: i64,
     teacher: String,
     image_url: String,
     name: String,
+    #[serde(default)]
+    term: Option<String>,
+    #[serde(default)]
+    archived: bool,
+}
+
+impl PartialEq for Course {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+impl Eq for Course {}
+impl Hash for Course {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+impl PartialOrd for Course {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Course {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
 }
 
 impl Display for Course {
@@ -36,17 +70,12 @@ impl Course {
     pub fn get_courses<'a, Sessions: Iterator<Item = &'a Session>>(
         sessions: Sessions,
     ) -> Result<HashMap<Course, Vec<Session>>, CourseError> {
-        let mut handles = Vec::new();
-        for session in sessions {
-            let session_ = session.clone();
-            let handle = std::thread::spawn(move || -> Result<Vec<Course>, CourseError> {
-                Course::get_session_courses(&session_)
-            });
-            handles.push((handle, session));
-        }
+        let host = ProtocolItem::BackClazzData.to_string();
+        let results = WorkerPool::get_global().map(host_of(&host), sessions, |session| {
+            (Course::get_session_courses(session), session)
+        });
         let mut courses = HashMap::<_, Vec<_>>::new();
-        for (handle, session) in handles {
-            let r = handle.join().unwrap();
+        for (r, session) in results {
             let courses_ = match r {
                 Ok(c) => c,
                 Err(e) => {
@@ -89,13 +118,17 @@ impl Course {
                 if let Some(data) = c.content.course {
                     for course in data.data {
                         if c.id.is_i64() {
-                            arr.push(Course::new(
-                                course.id,
-                                c.id.as_i64().unwrap(),
-                                course.teacher.as_str(),
-                                course.image_url.unwrap_or("".into()).as_str(),
-                                course.name.as_str(),
-                            ))
+                            arr.push(
+                                Course::new(
+                                    course.id,
+                                    c.id.as_i64().unwrap(),
+                                    course.teacher.as_str(),
+                                    course.image_url.unwrap_or("".into()).as_str(),
+                                    course.name.as_str(),
+                                )
+                                .with_term(c.content.semester.clone().filter(|s| !s.is_empty()))
+                                .with_archived(c.content.is_filed != 0 || c.content.state != 0),
+                            )
                         }
                     }
                 }
@@ -115,8 +148,20 @@ impl Course {
             teacher: teacher.into(),
             image_url: image_url.into(),
             name: name.into(),
+            term: None,
+            archived: false,
         }
     }
+    /// 设置课程所属学期。
+    pub fn with_term(mut self, term: Option<String>) -> Self {
+        self.term = term;
+        self
+    }
+    /// 设置课程是否已结课或归档。
+    pub fn with_archived(mut self, archived: bool) -> Self {
+        self.archived = archived;
+        self
+    }
     // fn from_raw(raw: &CourseRaw, class_id: i64) -> Course {
     //     Self {
     //         id: raw.id,
@@ -141,6 +186,201 @@ impl Course {
     pub fn get_name(&self) -> &str {
         &self.name
     }
+    /// 课程所属学期，如 `2023-2024-1`, 未知时为 `None`.
+    pub fn get_term(&self) -> Option<&str> {
+        self.term.as_deref()
+    }
+    /// 课程是否已结课或归档。
+    pub fn is_archived(&self) -> bool {
+        self.archived
+    }
+    fn key(&self) -> (i64, i64) {
+        (self.id, self.class_id)
+    }
+}
+
+/// # CourseFilter
+///
+/// 课程过滤条件，各条件为 `None` 时不做限制。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CourseFilter {
+    term: Option<String>,
+    archived: Option<bool>,
+}
+impl CourseFilter {
+    /// 不做任何限制。
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// 仅保留某学期的课程。学期未知的课程不会被保留。
+    pub fn with_term(mut self, term: &str) -> Self {
+        self.term = Some(term.to_owned());
+        self
+    }
+    /// 仅保留已归档（`true`）或未归档（`false`）的课程。
+    pub fn with_archived(mut self, archived: bool) -> Self {
+        self.archived = Some(archived);
+        self
+    }
+    pub fn matches(&self, course: &Course) -> bool {
+        self.term
+            .as_deref()
+            .is_none_or(|term| course.get_term() == Some(term))
+            && self.archived.is_none_or(|a| course.is_archived() == a)
+    }
+    /// 过滤课程列表。
+    pub fn filter<'a, I: IntoIterator<Item = &'a Course>>(&self, courses: I) -> Vec<Course> {
+        courses
+            .into_iter()
+            .filter(|c| self.matches(c))
+            .cloned()
+            .collect()
+    }
+}
+
+/// # CourseCacheTrait
+/// 课程缓存特型。按用户 uid 缓存课程列表，使仅需课程名等信息的操作无需联网。
+///
+/// 参见 [`Course::get_session_courses_cached`].
+pub trait CourseCacheTrait {
+    /// 获取缓存的课程列表及缓存时间（毫秒时间戳）。
+    fn get_cached_courses(&self, uid: &str) -> Option<(Vec<Course>, u64)>;
+    /// 缓存课程列表，覆盖原有缓存。
+    fn cache_courses(&self, uid: &str, courses: &[Course], time_mills: u64);
+    fn remove_cached_courses(&self, uid: &str);
+}
+impl CourseCacheTrait for Mutex<HashMap<String, (Vec<Course>, u64)>> {
+    fn get_cached_courses(&self, uid: &str) -> Option<(Vec<Course>, u64)> {
+        self.lock().unwrap().get(uid).cloned()
+    }
+
+    fn cache_courses(&self, uid: &str, courses: &[Course], time_mills: u64) {
+        self.lock()
+            .unwrap()
+            .insert(uid.to_owned(), (courses.to_vec(), time_mills));
+    }
+
+    fn remove_cached_courses(&self, uid: &str) {
+        self.lock().unwrap().remove(uid);
+    }
+}
+fn now_mills() -> u64 {
+    SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+impl Course {
+    /// 课程缓存的默认有效期。
+    pub const CACHE_TTL: Duration = Duration::from_secs(24 * 3600);
+    /// 获取用户的课程，并按 `filter` 过滤。
+    pub fn get_session_courses_filtered(
+        session: &Session,
+        filter: &CourseFilter,
+    ) -> Result<Vec<Course>, CourseError> {
+        Ok(filter.filter(&Course::get_session_courses(session)?))
+    }
+    fn is_cache_expired(time: u64, ttl: Duration) -> bool {
+        now_mills().saturating_sub(time) > ttl.as_millis() as u64
+    }
+    /// 联网获取用户的课程并更新缓存。
+    pub fn refresh_session_courses(
+        session: &Session,
+        cache: &impl CourseCacheTrait,
+    ) -> Result<Vec<Course>, CourseError> {
+        Self::update_cache(session, cache, None, Course::get_session_courses(session))
+    }
+    /// 将联网获取的课程写入缓存。获取失败时，若有旧的缓存且错误非致命，则给出警告并返回旧的缓存。
+    fn update_cache(
+        session: &Session,
+        cache: &impl CourseCacheTrait,
+        cached: Option<Vec<Course>>,
+        fetched: Result<Vec<Course>, CourseError>,
+    ) -> Result<Vec<Course>, CourseError> {
+        match (fetched, cached) {
+            (Ok(courses), _) => {
+                cache.cache_courses(session.get_uid(), &courses, now_mills());
+                Ok(courses)
+            }
+            (Err(e), Some(cached)) if !e.is_fatal() => {
+                warn!(
+                    "未能刷新用户[{}]的课程，将使用过期的缓存。错误信息：{e}.",
+                    session.get_stu_name()
+                );
+                Ok(cached)
+            }
+            (Err(e), _) => Err(e),
+        }
+    }
+    /// 优先从缓存中获取用户的课程。
+    ///
+    /// - 没有缓存时联网获取并写入缓存；
+    /// - 缓存未超过 `ttl` 时直接返回；
+    /// - 缓存超过 `ttl` 时联网刷新并更新缓存，离线等非致命错误导致刷新失败时仍返回缓存。
+    ///
+    /// `ttl` 为 [`Duration::MAX`] 时，有缓存便不会联网刷新，需要时可调用 [`Course::refresh_session_courses`].
+    pub fn get_session_courses_cached(
+        session: &Session,
+        cache: &impl CourseCacheTrait,
+        ttl: Duration,
+    ) -> Result<Vec<Course>, CourseError> {
+        match cache.get_cached_courses(session.get_uid()) {
+            Some((courses, time)) if !Self::is_cache_expired(time, ttl) => Ok(courses),
+            cached => {
+                if cached.is_some() {
+                    info!("用户[{}]的课程缓存已过期，将刷新。", session.get_stu_name());
+                }
+                let fetched = Course::get_session_courses(session);
+                Self::update_cache(session, cache, cached.map(|(c, _)| c), fetched)
+            }
+        }
+    }
+    /// 获取多个用户的课程，优先使用缓存，并按 `filter` 过滤。
+    ///
+    /// 缓存的使用及刷新同 [`Course::get_session_courses_cached`], 需要联网的用户在线程池中并发获取。
+    pub fn get_courses_cached<'a, Sessions: Iterator<Item = &'a Session>>(
+        sessions: Sessions,
+        cache: &impl CourseCacheTrait,
+        filter: &CourseFilter,
+        ttl: Duration,
+    ) -> Result<HashMap<Course, Vec<Session>>, CourseError> {
+        let mut results = Vec::new();
+        let mut pending = Vec::new();
+        for session in sessions {
+            match cache.get_cached_courses(session.get_uid()) {
+                Some((courses, time)) if !Self::is_cache_expired(time, ttl) => {
+                    results.push((Ok(courses), session))
+                }
+                cached => pending.push((session, cached.map(|(c, _)| c))),
+            }
+        }
+        let host = ProtocolItem::BackClazzData.to_string();
+        let fetched = WorkerPool::get_global().map(host_of(&host), pending, |(session, cached)| {
+            (Course::get_session_courses(session), session, cached)
+        });
+        for (r, session, cached) in fetched {
+            results.push((Self::update_cache(session, cache, cached, r), session));
+        }
+        let mut courses = HashMap::<_, Vec<_>>::new();
+        for (r, session) in results {
+            let courses_ = match r {
+                Ok(c) => c,
+                Err(e) if !e.is_fatal() => {
+                    warn!(
+                        "未能获取用户[{}]的课程，错误信息：{e}.",
+                        session.get_stu_name()
+                    );
+                    Default::default()
+                }
+                Err(e) => return Err(e),
+            };
+            for course in filter.filter(&courses_) {
+                courses.entry(course).or_default().push(session.clone());
+            }
+        }
+        Ok(courses)
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -161,6 +401,12 @@ struct Courses {
 #[derive(Deserialize, Serialize, Debug)]
 struct CourseContent {
     course: Option<Courses>,
+    #[serde(default)]
+    semester: Option<String>,
+    #[serde(default)]
+    state: i32,
+    #[serde(default, rename = "isFiled")]
+    is_filed: i32,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -175,3 +421,53 @@ struct GetCoursesR {
     #[serde(rename = "channelList")]
     channel_list: Option<Vec<ClassRaw>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Course, CourseFilter};
+
+    #[test]
+    fn test_course_filter() {
+        let courses = [
+            Course::new(1, 1, "teacher", "", "高等数学").with_term(Some("2023-2024-1".to_owned())),
+            Course::new(2, 2, "teacher", "", "线性代数")
+                .with_term(Some("2023-2024-1".to_owned()))
+                .with_archived(true),
+            Course::new(3, 3, "teacher", "", "大学物理"),
+        ];
+        assert_eq!(CourseFilter::new().filter(&courses).len(), 3);
+        let names = |filter: CourseFilter| {
+            filter
+                .filter(&courses)
+                .into_iter()
+                .map(|c| c.get_name().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(CourseFilter::new().with_archived(false)),
+            vec!["高等数学", "大学物理"]
+        );
+        assert_eq!(
+            names(
+                CourseFilter::new()
+                    .with_term("2023-2024-1")
+                    .with_archived(true)
+            ),
+            vec!["线性代数"]
+        );
+    }
+
+    #[test]
+    fn test_course_identity() {
+        use std::collections::HashMap;
+        let course = Course::new(1, 2, "teacher", "", "高等数学");
+        let updated = course
+            .clone()
+            .with_term(Some("2023-2024-1".to_owned()))
+            .with_archived(true);
+        assert_eq!(course, updated);
+        let map = HashMap::from([(course, 1)]);
+        assert_eq!(map.get(&updated), Some(&1));
+        assert_ne!(updated, Course::new(1, 3, "teacher", "", "高等数学"));
+    }
+}
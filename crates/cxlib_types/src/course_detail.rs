@@ -0,0 +1,327 @@
+use crate::{cloud_drive::parse_response, CloudFileMetadata, Course};
+use cxlib_error::{CloudDriveError, CourseError};
+use cxlib_protocol::collect::types as protocol;
+use cxlib_user::Session;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::HashMap, io::Write};
+use ureq::serde_json;
+
+/// `gas/clazz` 接口中的列表均形如 `{"data":[...]}`.
+#[derive(Deserialize)]
+struct DataList<T> {
+    #[serde(default = "Vec::new")]
+    data: Vec<T>,
+}
+fn parse_clazz<T: DeserializeOwned>(r: ureq::Response) -> Result<T, CourseError> {
+    let text = r
+        .into_string()
+        .map_err(|e| CourseError::UnexpectedResponse(e.to_string()))?;
+    let DataList { data } = serde_json::from_str::<DataList<T>>(&text)
+        .map_err(|e| CourseError::UnexpectedResponse(format!("{e}: {text}")))?;
+    data.into_iter()
+        .next()
+        .ok_or(CourseError::UnexpectedResponse(text))
+}
+
+/// # CourseDetail
+///
+/// 课程详情，包含学期、上课时间、学分及班级人数等 [`Course`] 中没有的信息。
+///
+/// 各字段均可能未被设置，此时为 `None`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CourseDetail {
+    course: Course,
+    class_name: String,
+    term: Option<String>,
+    begin_date_mills: Option<u64>,
+    end_date_mills: Option<u64>,
+    schedule: Option<String>,
+    credits: Option<f64>,
+    classmates_count: Option<u32>,
+}
+#[derive(Deserialize)]
+struct CourseDetailRaw {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    semester: Option<String>,
+    #[serde(default)]
+    begindate: Option<u64>,
+    #[serde(default)]
+    enddate: Option<u64>,
+    #[serde(default)]
+    schedule: Option<String>,
+    #[serde(default)]
+    credit: Option<f64>,
+    #[serde(default)]
+    studentcount: Option<u32>,
+}
+impl CourseDetail {
+    fn from_raw(raw: CourseDetailRaw, course: &Course) -> Self {
+        Self {
+            course: course.clone(),
+            class_name: raw.name,
+            term: raw.semester.filter(|s| !s.is_empty()),
+            begin_date_mills: raw.begindate.filter(|t| *t > 0),
+            end_date_mills: raw.enddate.filter(|t| *t > 0),
+            schedule: raw.schedule.filter(|s| !s.is_empty()),
+            credits: raw.credit,
+            classmates_count: raw.studentcount,
+        }
+    }
+    pub fn get_course(&self) -> &Course {
+        &self.course
+    }
+    /// 班级名称。
+    pub fn get_class_name(&self) -> &str {
+        &self.class_name
+    }
+    /// 学期，如 `2023-2024-1`.
+    pub fn get_term(&self) -> Option<&str> {
+        self.term.as_deref()
+    }
+    /// 开课时间（毫秒时间戳）。
+    pub fn get_begin_date_mills(&self) -> Option<u64> {
+        self.begin_date_mills
+    }
+    /// 结课时间（毫秒时间戳）。
+    pub fn get_end_date_mills(&self) -> Option<u64> {
+        self.end_date_mills
+    }
+    /// 上课时间，为教师填写的文本。
+    pub fn get_schedule(&self) -> Option<&str> {
+        self.schedule.as_deref()
+    }
+    pub fn get_credits(&self) -> Option<f64> {
+        self.credits
+    }
+    /// 班级人数。
+    pub fn get_classmates_count(&self) -> Option<u32> {
+        self.classmates_count
+    }
+}
+
+/// 章节中的资料。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CourseMaterial {
+    object_id: String,
+    name: String,
+    kind: String,
+    extension: String,
+}
+#[derive(Deserialize)]
+struct CourseMaterialRaw {
+    #[serde(default)]
+    objectid: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    extension: String,
+}
+impl CourseMaterial {
+    /// 云盘对象 ID.
+    pub fn get_object_id(&self) -> &str {
+        &self.object_id
+    }
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    /// 资料类型，如 `video`, `document`.
+    pub fn get_kind(&self) -> &str {
+        &self.kind
+    }
+    /// 扩展名，不含 `.`.
+    pub fn get_extension(&self) -> &str {
+        &self.extension
+    }
+    /// 获取资料的元数据，其中包含下载地址。
+    pub fn get_metadata(&self, session: &Session) -> Result<CloudFileMetadata, CloudDriveError> {
+        parse_response(protocol::pan_file_status(session, &self.object_id)?)
+    }
+    /// 下载资料并写入 `writer`, 返回写入的字节数。
+    pub fn download(
+        &self,
+        session: &Session,
+        writer: &mut impl Write,
+    ) -> Result<u64, CloudDriveError> {
+        let url = self
+            .get_metadata(session)?
+            .download
+            .ok_or_else(|| CloudDriveError::NotFound(format!("资料 `{}` 的下载地址", self.name)))?;
+        let r = protocol::pan_download(session, &url)?;
+        Ok(std::io::copy(&mut r.into_reader(), writer)?)
+    }
+}
+
+/// # CourseChapter
+///
+/// 课程章节，可通过 [`Course::get_chapters`] 获取章节树。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CourseChapter {
+    id: i64,
+    label: String,
+    name: String,
+    materials: Vec<CourseMaterial>,
+    children: Vec<CourseChapter>,
+}
+#[derive(Deserialize)]
+struct CourseChapterRaw {
+    id: i64,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    label: String,
+    #[serde(default)]
+    indexorder: i64,
+    #[serde(default)]
+    parentnodeid: i64,
+    #[serde(default)]
+    attachment: Option<DataList<CourseMaterialRaw>>,
+}
+impl CourseChapter {
+    /// 由扁平的章节列表按 `parentnodeid` 构建章节树，同级章节按 `indexorder` 排序。
+    ///
+    /// 父章节不存在的章节视为顶层章节。
+    fn build_tree(raws: Vec<CourseChapterRaw>) -> Vec<Self> {
+        let ids = raws.iter().map(|r| r.id).collect::<Vec<_>>();
+        let mut children = HashMap::<i64, Vec<CourseChapterRaw>>::new();
+        for raw in raws {
+            let parent = if ids.contains(&raw.parentnodeid) && raw.parentnodeid != raw.id {
+                raw.parentnodeid
+            } else {
+                0
+            };
+            children.entry(parent).or_default().push(raw);
+        }
+        fn build(
+            parent: i64,
+            children: &mut HashMap<i64, Vec<CourseChapterRaw>>,
+        ) -> Vec<CourseChapter> {
+            let mut raws = children.remove(&parent).unwrap_or_default();
+            raws.sort_by_key(|r| r.indexorder);
+            raws.into_iter()
+                .map(|raw| {
+                    let materials = raw
+                        .attachment
+                        .map(|a| a.data)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|m| !m.objectid.is_empty())
+                        .map(|m| CourseMaterial {
+                            object_id: m.objectid,
+                            name: m.name,
+                            kind: m.kind,
+                            extension: m.extension,
+                        })
+                        .collect();
+                    CourseChapter {
+                        id: raw.id,
+                        label: raw.label,
+                        name: raw.name,
+                        materials,
+                        children: build(raw.id, children),
+                    }
+                })
+                .collect()
+        }
+        build(0, &mut children)
+    }
+    pub fn get_id(&self) -> i64 {
+        self.id
+    }
+    /// 章节序号，如 `1.2`.
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    /// 本章节中的资料，不含子章节。
+    pub fn get_materials(&self) -> &[CourseMaterial] {
+        &self.materials
+    }
+    pub fn get_children(&self) -> &[CourseChapter] {
+        &self.children
+    }
+    /// 按先序遍历本章节及所有子章节。
+    pub fn walk(&self) -> Vec<&CourseChapter> {
+        let mut chapters = vec![self];
+        for child in &self.children {
+            chapters.extend(child.walk());
+        }
+        chapters
+    }
+    /// 本章节及所有子章节中的资料。
+    pub fn get_all_materials(&self) -> Vec<&CourseMaterial> {
+        self.walk()
+            .into_iter()
+            .flat_map(|c| c.materials.iter())
+            .collect()
+    }
+}
+
+impl Course {
+    /// 获取课程详情。
+    pub fn get_detail(&self, session: &Session) -> Result<CourseDetail, CourseError> {
+        let raw: CourseDetailRaw =
+            parse_clazz(protocol::course_detail(session, self.get_class_id())?)?;
+        Ok(CourseDetail::from_raw(raw, self))
+    }
+    /// 获取课程的章节树。
+    pub fn get_chapters(&self, session: &Session) -> Result<Vec<CourseChapter>, CourseError> {
+        #[derive(Deserialize)]
+        struct KnowledgeRaw {
+            #[serde(default)]
+            knowledge: Option<DataList<CourseChapterRaw>>,
+        }
+        #[derive(Deserialize)]
+        struct ClazzRaw {
+            course: DataList<KnowledgeRaw>,
+        }
+        let raw: ClazzRaw = parse_clazz(protocol::course_chapters(session, self.get_class_id())?)?;
+        let chapters = raw
+            .course
+            .data
+            .into_iter()
+            .flat_map(|k| k.knowledge.map(|k| k.data).unwrap_or_default())
+            .collect();
+        Ok(CourseChapter::build_tree(chapters))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::course_detail::{CourseChapter, CourseChapterRaw};
+    use ureq::serde_json;
+
+    #[test]
+    fn test_build_tree() {
+        let raws: Vec<CourseChapterRaw> = serde_json::from_str(
+            r#"[
+                {"id":3,"name":"极限","label":"1.1","indexorder":1,"parentnodeid":1,
+                 "attachment":{"data":[{"objectid":"abc","name":"讲义.pdf","type":"document","extension":"pdf"}]}},
+                {"id":1,"name":"函数与极限","label":"1","indexorder":1,"parentnodeid":0},
+                {"id":2,"name":"函数","label":"1.0","indexorder":0,"parentnodeid":1},
+                {"id":4,"name":"导数","label":"2","indexorder":2,"parentnodeid":0},
+                {"id":5,"name":"孤立章节","label":"3","indexorder":3,"parentnodeid":99}
+            ]"#,
+        )
+        .unwrap();
+        let tree = CourseChapter::build_tree(raws);
+        assert_eq!(
+            tree.iter().map(|c| c.get_id()).collect::<Vec<_>>(),
+            vec![1, 4, 5]
+        );
+        let labels = tree[0]
+            .walk()
+            .into_iter()
+            .map(|c| c.get_label())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["1", "1.0", "1.1"]);
+        let materials = tree[0].get_all_materials();
+        assert_eq!(materials.len(), 1);
+        assert_eq!(materials[0].get_object_id(), "abc");
+    }
+}
@@ -0,0 +1,174 @@
+//! 地理计算：球面距离及 WGS-84, GCJ-02, BD-09 坐标系之间的转换。
+//!
+//! 坐标均以 `(经度, 纬度)` 表示，单位为度。
+use cxlib_error::LocationParseError;
+use std::{f64::consts::PI, str::FromStr};
+
+/// 地球平均半径，单位为米。
+pub const EARTH_RADIUS_M: f64 = 6371393.0;
+
+/// 两点间的大圆距离（半正矢公式），单位为米。
+pub fn distance((lon1, lat1): (f64, f64), (lon2, lat2): (f64, f64)) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (lon2 - lon1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().min(1.0).asin()
+}
+
+/// 坐标系。
+///
+/// 不同地图应用给出的坐标所在的坐标系不同，比较前应先转换到同一坐标系：
+///
+/// - [`Wgs84`](CoordinateSystem::Wgs84): GPS 及国外地图使用；
+/// - [`Gcj02`](CoordinateSystem::Gcj02): 高德、腾讯等国内地图使用；
+/// - [`Bd09`](CoordinateSystem::Bd09): 百度地图使用。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CoordinateSystem {
+    #[default]
+    Wgs84,
+    Gcj02,
+    Bd09,
+}
+impl CoordinateSystem {
+    /// 将坐标从当前坐标系转换到 `to`.
+    ///
+    /// GCJ-02 到 WGS-84 的转换为近似逆变换，误差在米级以内。国外的坐标在 WGS-84 与 GCJ-02 之间不做转换。
+    pub fn convert(self, to: CoordinateSystem, coordinate: (f64, f64)) -> (f64, f64) {
+        use CoordinateSystem::*;
+        match (self, to) {
+            (Wgs84, Wgs84) | (Gcj02, Gcj02) | (Bd09, Bd09) => coordinate,
+            (Wgs84, Gcj02) => wgs84_to_gcj02(coordinate),
+            (Gcj02, Wgs84) => gcj02_to_wgs84(coordinate),
+            (Gcj02, Bd09) => gcj02_to_bd09(coordinate),
+            (Bd09, Gcj02) => bd09_to_gcj02(coordinate),
+            (Wgs84, Bd09) => gcj02_to_bd09(wgs84_to_gcj02(coordinate)),
+            (Bd09, Wgs84) => gcj02_to_wgs84(bd09_to_gcj02(coordinate)),
+        }
+    }
+}
+impl FromStr for CoordinateSystem {
+    type Err = LocationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s
+            .trim()
+            .to_ascii_lowercase()
+            .replace(['-', '_'], "")
+            .as_str()
+        {
+            "wgs84" => Ok(CoordinateSystem::Wgs84),
+            "gcj02" => Ok(CoordinateSystem::Gcj02),
+            "bd09" => Ok(CoordinateSystem::Bd09),
+            _ => Err(LocationParseError::UnknownCoordinateSystem(s.to_owned())),
+        }
+    }
+}
+
+// 克拉索夫斯基椭球参数。
+const KRASOVSKY_A: f64 = 6378245.0;
+const KRASOVSKY_EE: f64 = 0.006_693_421_622_965_943;
+const BD_X_PI: f64 = PI * 3000.0 / 180.0;
+
+/// 粗略判断是否在国内，国外的坐标不做偏移。
+fn is_in_china((lon, lat): (f64, f64)) -> bool {
+    (72.004..=137.8347).contains(&lon) && (0.8293..=55.8271).contains(&lat)
+}
+fn transform_lat(x: f64, y: f64) -> f64 {
+    let mut r = -100.0 + 2.0 * x + 3.0 * y + 0.2 * y * y + 0.1 * x * y + 0.2 * x.abs().sqrt();
+    r += (20.0 * (6.0 * x * PI).sin() + 20.0 * (2.0 * x * PI).sin()) * 2.0 / 3.0;
+    r += (20.0 * (y * PI).sin() + 40.0 * (y / 3.0 * PI).sin()) * 2.0 / 3.0;
+    r += (160.0 * (y / 12.0 * PI).sin() + 320.0 * (y * PI / 30.0).sin()) * 2.0 / 3.0;
+    r
+}
+fn transform_lon(x: f64, y: f64) -> f64 {
+    let mut r = 300.0 + x + 2.0 * y + 0.1 * x * x + 0.1 * x * y + 0.1 * x.abs().sqrt();
+    r += (20.0 * (6.0 * x * PI).sin() + 20.0 * (2.0 * x * PI).sin()) * 2.0 / 3.0;
+    r += (20.0 * (x * PI).sin() + 40.0 * (x / 3.0 * PI).sin()) * 2.0 / 3.0;
+    r += (150.0 * (x / 12.0 * PI).sin() + 300.0 * (x / 30.0 * PI).sin()) * 2.0 / 3.0;
+    r
+}
+/// WGS-84 坐标在 GCJ-02 中的偏移量。
+fn gcj02_offset((lon, lat): (f64, f64)) -> (f64, f64) {
+    let d_lat = transform_lat(lon - 105.0, lat - 35.0);
+    let d_lon = transform_lon(lon - 105.0, lat - 35.0);
+    let rad_lat = lat.to_radians();
+    let magic = 1.0 - KRASOVSKY_EE * rad_lat.sin().powi(2);
+    let sqrt_magic = magic.sqrt();
+    (
+        (d_lon * 180.0) / (KRASOVSKY_A / sqrt_magic * rad_lat.cos() * PI),
+        (d_lat * 180.0) / ((KRASOVSKY_A * (1.0 - KRASOVSKY_EE)) / (magic * sqrt_magic) * PI),
+    )
+}
+fn wgs84_to_gcj02(coordinate: (f64, f64)) -> (f64, f64) {
+    if !is_in_china(coordinate) {
+        return coordinate;
+    }
+    let (d_lon, d_lat) = gcj02_offset(coordinate);
+    (coordinate.0 + d_lon, coordinate.1 + d_lat)
+}
+fn gcj02_to_wgs84(coordinate: (f64, f64)) -> (f64, f64) {
+    if !is_in_china(coordinate) {
+        return coordinate;
+    }
+    let (d_lon, d_lat) = gcj02_offset(coordinate);
+    (coordinate.0 - d_lon, coordinate.1 - d_lat)
+}
+fn gcj02_to_bd09((lon, lat): (f64, f64)) -> (f64, f64) {
+    let z = (lon * lon + lat * lat).sqrt() + 0.00002 * (lat * BD_X_PI).sin();
+    let theta = lat.atan2(lon) + 0.000003 * (lon * BD_X_PI).cos();
+    (z * theta.cos() + 0.0065, z * theta.sin() + 0.006)
+}
+fn bd09_to_gcj02((lon, lat): (f64, f64)) -> (f64, f64) {
+    let (x, y) = (lon - 0.0065, lat - 0.006);
+    let z = (x * x + y * y).sqrt() - 0.00002 * (y * BD_X_PI).sin();
+    let theta = y.atan2(x) - 0.000003 * (x * BD_X_PI).cos();
+    (z * theta.cos(), z * theta.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{distance, CoordinateSystem, Location, LocationWithRange};
+
+    #[test]
+    fn test_distance() {
+        // 天安门到上海人民广场约 1067 公里。
+        let d = distance((116.3975, 39.9087), (121.4737, 31.2304));
+        assert!((d - 1_067_000.0).abs() < 5_000.0, "{d}");
+        assert_eq!(distance((116.3975, 39.9087), (116.3975, 39.9087)), 0.0);
+    }
+
+    #[test]
+    fn test_convert() {
+        use CoordinateSystem::*;
+        let wgs = (116.3975, 39.9087);
+        for system in [Gcj02, Bd09] {
+            let converted = Wgs84.convert(system, wgs);
+            // 国内偏移在数百米量级。
+            assert!(distance(wgs, converted) > 100.0);
+            let back = system.convert(Wgs84, converted);
+            assert!(distance(wgs, back) < 5.0, "{system:?}: {back:?}");
+        }
+        // 国外不偏移。
+        let paris = (2.3522, 48.8566);
+        assert_eq!(Wgs84.convert(Gcj02, paris), paris);
+        assert_eq!("GCJ-02".parse::<CoordinateSystem>().unwrap(), Gcj02);
+        assert!("cgcs2000".parse::<CoordinateSystem>().is_err());
+    }
+
+    #[test]
+    fn test_contains() {
+        let range: LocationWithRange = ureq::serde_json::from_str(
+            r#"{"address":"教室","longitude":"116.3975","latitude":"39.9087","locationrange":100}"#,
+        )
+        .unwrap();
+        let near = Location::new("教室", 116.3980, 39.9090, None).unwrap();
+        let far = Location::new("操场", 116.4100, 39.9087, None).unwrap();
+        assert!(range.contains(&near));
+        assert!(!range.contains(&far));
+        for _ in 0..20 {
+            let shifted = range.to_shifted_location();
+            assert!(range.contains(&shifted), "{shifted}");
+        }
+    }
+}
@@ -1,11 +1,17 @@
+mod cloud_drive;
 mod course;
+mod course_detail;
 mod dioption;
+mod geo;
 mod location;
 mod photo;
 mod triple;
 
+pub use cloud_drive::*;
 pub use course::*;
+pub use course_detail::*;
 pub use dioption::*;
+pub use geo::*;
 pub use location::*;
 pub use photo::*;
 pub use triple::*;
@@ -1,11 +1,19 @@
-use crate::Course;
-use cxlib_error::{AgentError, InitError};
+use crate::{distance, CoordinateSystem, Course, EARTH_RADIUS_M};
+use cxlib_error::{AgentError, InitError, LocationParseError};
 use cxlib_protocol::collect::types as protocol;
 use cxlib_user::Session;
+use log::warn;
 use onceinit::{OnceInit, StaticDefault};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, f64::consts::PI, ops::Deref, str::FromStr};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    f64::consts::PI,
+    hash::{Hash, Hasher},
+    ops::{Deref, RangeInclusive},
+    str::FromStr,
+};
 
 pub trait LocationPreprocessorTrait: Send + Sync {
     fn do_preprocess(&self, location: Location) -> Location;
@@ -24,13 +32,130 @@ unsafe impl StaticDefault for dyn LocationPreprocessorTrait {
 }
 
 static LOCATION_PREPROCESSOR: OnceInit<dyn LocationPreprocessorTrait> = OnceInit::uninit();
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
-pub struct Location {
+/// 坐标数值。已校验为有限值，因此可以比较与哈希。
+#[derive(Debug, Clone, Copy)]
+struct Coordinate(f64);
+impl PartialEq for Coordinate {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+impl Eq for Coordinate {}
+impl PartialOrd for Coordinate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Coordinate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+impl Hash for Coordinate {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state)
+    }
+}
+impl Coordinate {
+    fn check(
+        field: &'static str,
+        value: f64,
+        range: RangeInclusive<f64>,
+    ) -> Result<Self, LocationParseError> {
+        if !value.is_finite() {
+            Err(LocationParseError::InvalidNumber {
+                field,
+                value: value.to_string(),
+            })
+        } else if !range.contains(&value) {
+            Err(LocationParseError::OutOfRange {
+                field,
+                value,
+                min: *range.start(),
+                max: *range.end(),
+            })
+        } else {
+            Ok(Coordinate(value))
+        }
+    }
+    fn parse(
+        field: &'static str,
+        value: &str,
+        range: RangeInclusive<f64>,
+    ) -> Result<Self, LocationParseError> {
+        let v = value
+            .trim()
+            .parse()
+            .map_err(|_| LocationParseError::InvalidNumber {
+                field,
+                value: value.to_owned(),
+            })?;
+        Self::check(field, v, range)
+    }
+    /// 超出范围时取最近的边界值，用于计算得到的坐标。
+    fn clamped(value: f64, range: RangeInclusive<f64>) -> Self {
+        Coordinate(value.clamp(*range.start(), *range.end()))
+    }
+    fn lon(value: f64) -> Result<Self, LocationParseError> {
+        Self::check("经度", value, LON_RANGE)
+    }
+    fn lat(value: f64) -> Result<Self, LocationParseError> {
+        Self::check("纬度", value, LAT_RANGE)
+    }
+    fn alt(value: f64) -> Result<Self, LocationParseError> {
+        Self::check("海拔", value, f64::MIN..=f64::MAX)
+    }
+}
+/// 经度的取值范围。
+pub const LON_RANGE: RangeInclusive<f64> = -180.0..=180.0;
+/// 纬度的取值范围。
+pub const LAT_RANGE: RangeInclusive<f64> = -90.0..=90.0;
+
+/// 序列化时的格式，各字段均为字符串，与旧版本保持一致。
+#[derive(Serialize, Deserialize)]
+struct LocationFields {
     addr: String,
     lon: String,
     lat: String,
     alt: String,
 }
+impl TryFrom<LocationFields> for Location {
+    type Error = LocationParseError;
+
+    fn try_from(
+        LocationFields {
+            addr,
+            lon,
+            lat,
+            alt,
+        }: LocationFields,
+    ) -> Result<Self, Self::Error> {
+        Location::from_owned_fields([addr, lon, lat, alt])
+    }
+}
+impl From<Location> for LocationFields {
+    fn from(location: Location) -> Self {
+        let [addr, lon, lat, alt] = location.to_owned_fields();
+        LocationFields {
+            addr,
+            lon,
+            lat,
+            alt,
+        }
+    }
+}
+
+/// 位置，经纬度均已校验，海拔可以为空。
+///
+/// 文本格式为 `地址,经度,纬度,海拔`, 序列化时经纬度与海拔均为字符串。
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
+#[serde(try_from = "LocationFields", into = "LocationFields")]
+pub struct Location {
+    addr: String,
+    lon: Coordinate,
+    lat: Coordinate,
+    alt: Option<Coordinate>,
+}
 impl Location {
     pub fn get_location_preprocessor() -> &'static dyn LocationPreprocessorTrait {
         LOCATION_PREPROCESSOR.deref()
@@ -49,6 +174,7 @@ impl Location {
     ) -> Result<(), InitError> {
         Ok(LOCATION_PREPROCESSOR.init_boxed(preprocessor)?)
     }
+    /// 地址、经度、纬度、海拔的文本形式，海拔为空时为空字符串。
     pub fn to_owned_fields(self) -> [String; 4] {
         let Location {
             addr,
@@ -56,87 +182,127 @@ impl Location {
             lat,
             alt,
         } = self;
-        [addr, lon, lat, alt]
+        [
+            addr,
+            lon.0.to_string(),
+            lat.0.to_string(),
+            alt.map(|alt| alt.0.to_string()).unwrap_or_default(),
+        ]
     }
-    pub fn from_owned_fields([addr, lon, lat, alt]: [String; 4]) -> Self {
-        Location {
+    /// 由 [`to_owned_fields`](Self::to_owned_fields) 的结果还原，不经过预处理。
+    pub fn from_owned_fields(
+        [addr, lon, lat, alt]: [String; 4],
+    ) -> Result<Self, LocationParseError> {
+        let alt = alt.trim();
+        Ok(Location {
+            lon: Coordinate::parse("经度", &lon, LON_RANGE)?,
+            lat: Coordinate::parse("纬度", &lat, LAT_RANGE)?,
+            alt: if alt.is_empty() {
+                None
+            } else {
+                Some(Coordinate::parse("海拔", alt, f64::MIN..=f64::MAX)?)
+            },
             addr,
-            lon,
-            lat,
-            alt,
-        }
+        })
     }
+    /// 地址为空、经纬度为零且没有海拔的位置。
     pub fn get_none_location() -> Self {
         Location {
             addr: "".to_string(),
-            lon: "".to_string(),
-            lat: "".to_string(),
-            alt: "".to_string(),
+            lon: Coordinate(0.0),
+            lat: Coordinate(0.0),
+            alt: None,
         }
     }
-    pub fn parse(location_str: &str) -> Result<Self, String> {
+    pub fn parse(location_str: &str) -> Result<Self, LocationParseError> {
         let location_str: Vec<&str> = location_str.split(',').map(|item| item.trim()).collect();
         if location_str.len() == 4 {
-            Ok(Self::new(
-                location_str[0],
-                location_str[1],
-                location_str[2],
-                location_str[3],
-            ))
+            let [addr, lon, lat, alt] = [0, 1, 2, 3].map(|i| location_str[i].to_owned());
+            Ok(Self::from_owned_fields([addr, lon, lat, alt])?.to_preprocessed())
         } else {
-            Err("位置信息格式错误！格式为：`地址,经度,纬度,海拔`.".to_string())
+            Err(LocationParseError::InvalidFormat)
         }
     }
-    pub fn new(addr: &str, lon: &str, lat: &str, alt: &str) -> Location {
+    /// 校验经纬度及海拔后创建位置，并经过预处理。
+    pub fn new(
+        addr: &str,
+        lon: f64,
+        lat: f64,
+        alt: Option<f64>,
+    ) -> Result<Location, LocationParseError> {
         let location = Location {
             addr: addr.into(),
-            lon: lon.into(),
-            lat: lat.into(),
-            alt: alt.into(),
+            lon: Coordinate::lon(lon)?,
+            lat: Coordinate::lat(lat)?,
+            alt: alt.map(Coordinate::alt).transpose()?,
         };
-        location.to_preprocessed()
+        Ok(location.to_preprocessed())
     }
     /// 地址。
     pub fn get_addr(&self) -> &str {
         &self.addr
     }
     /// 经度。
-    pub fn get_lon(&self) -> &str {
-        &self.lon
+    pub fn get_lon(&self) -> f64 {
+        self.lon.0
     }
     /// 纬度。
-    pub fn get_lat(&self) -> &str {
-        &self.lat
+    pub fn get_lat(&self) -> f64 {
+        self.lat.0
     }
     /// 海拔。
-    pub fn get_alt(&self) -> &str {
-        &self.alt
+    pub fn get_alt(&self) -> Option<f64> {
+        self.alt.map(|alt| alt.0)
+    }
+    /// 到另一位置的球面距离，单位为米。两者应在同一坐标系中。
+    pub fn distance_to(&self, other: &Location) -> f64 {
+        distance(
+            (self.get_lon(), self.get_lat()),
+            (other.get_lon(), other.get_lat()),
+        )
+    }
+    /// 将经纬度从坐标系 `from` 转换到 `to`, 地址与海拔不变。
+    pub fn convert(&self, from: CoordinateSystem, to: CoordinateSystem) -> Location {
+        let (lon, lat) = from.convert(to, (self.get_lon(), self.get_lat()));
+        Location {
+            addr: self.addr.clone(),
+            lon: Coordinate::clamped(lon, LON_RANGE),
+            lat: Coordinate::clamped(lat, LAT_RANGE),
+            alt: self.alt,
+        }
     }
     /// 地址。
     pub fn set_addr(&mut self, addr: &str) {
         addr.clone_into(&mut self.addr)
     }
     /// 经度。
-    pub fn set_lon(&mut self, lon: &str) {
-        lon.clone_into(&mut self.lon)
+    pub fn set_lon(&mut self, lon: f64) -> Result<(), LocationParseError> {
+        self.lon = Coordinate::lon(lon)?;
+        Ok(())
     }
     /// 纬度。
-    pub fn set_lat(&mut self, lat: &str) {
-        lat.clone_into(&mut self.lat)
+    pub fn set_lat(&mut self, lat: f64) -> Result<(), LocationParseError> {
+        self.lat = Coordinate::lat(lat)?;
+        Ok(())
     }
     /// 海拔。
-    pub fn set_alt(&mut self, alt: &str) {
-        alt.clone_into(&mut self.alt)
+    pub fn set_alt(&mut self, alt: Option<f64>) -> Result<(), LocationParseError> {
+        self.alt = alt.map(Coordinate::alt).transpose()?;
+        Ok(())
     }
 }
 
 impl std::fmt::Display for Location {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{},{},{},{}", self.addr, self.lon, self.lat, self.alt)
+        write!(f, "{},{},{},", self.addr, self.lon.0, self.lat.0)?;
+        if let Some(alt) = self.alt {
+            write!(f, "{}", alt.0)?;
+        }
+        Ok(())
     }
 }
 impl FromStr for Location {
-    type Err = String;
+    type Err = LocationParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Location::parse(s)
@@ -159,8 +325,9 @@ impl FromStr for Location {
 //     位置id
 // }
 
-#[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Hash, Clone, Serialize, Deserialize)]
-pub struct LocationWithRange {
+/// 序列化时的格式，经纬度为字符串，与旧版本保持一致。
+#[derive(Serialize, Deserialize)]
+struct LocationWithRangeFields {
     #[serde(rename = "address")]
     addr: String,
     #[serde(rename = "longitude")]
@@ -170,6 +337,52 @@ pub struct LocationWithRange {
     #[serde(rename = "locationrange")]
     range: u32,
 }
+impl TryFrom<LocationWithRangeFields> for LocationWithRange {
+    type Error = LocationParseError;
+
+    fn try_from(
+        LocationWithRangeFields {
+            addr,
+            lon,
+            lat,
+            range,
+        }: LocationWithRangeFields,
+    ) -> Result<Self, Self::Error> {
+        Ok(LocationWithRange {
+            addr,
+            lon: Coordinate::parse("经度", &lon, LON_RANGE)?,
+            lat: Coordinate::parse("纬度", &lat, LAT_RANGE)?,
+            range,
+        })
+    }
+}
+impl From<LocationWithRange> for LocationWithRangeFields {
+    fn from(
+        LocationWithRange {
+            addr,
+            lon,
+            lat,
+            range,
+        }: LocationWithRange,
+    ) -> Self {
+        LocationWithRangeFields {
+            addr,
+            lon: lon.0.to_string(),
+            lat: lat.0.to_string(),
+            range,
+        }
+    }
+}
+
+/// 教师设置的签到位置及范围（米）。
+#[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Hash, Clone, Serialize, Deserialize)]
+#[serde(try_from = "LocationWithRangeFields", into = "LocationWithRangeFields")]
+pub struct LocationWithRange {
+    addr: String,
+    lon: Coordinate,
+    lat: Coordinate,
+    range: u32,
+}
 
 impl LocationWithRange {
     pub fn from_log(
@@ -190,13 +403,13 @@ impl LocationWithRange {
             range: String,
         }
         impl LocationWithRangeAndActiveId {
-            pub fn to_location_with_range(&self) -> LocationWithRange {
-                LocationWithRange {
+            pub fn to_location_with_range(&self) -> Result<LocationWithRange, LocationParseError> {
+                Ok(LocationWithRange {
                     addr: self.addr.clone(),
-                    lon: self.lon.to_string(),
-                    lat: self.lat.to_string(),
+                    lon: Coordinate::lon(self.lon)?,
+                    lat: Coordinate::lat(self.lat)?,
                     range: self.range.trim().parse().unwrap_or(100),
-                }
+                })
             }
         }
         #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -208,7 +421,12 @@ impl LocationWithRange {
         let data: Data = r.into_json().unwrap();
         let mut map = HashMap::new();
         for l in data.data {
-            map.insert(l.active_id.to_string(), l.to_location_with_range());
+            match l.to_location_with_range() {
+                Ok(location) => {
+                    map.insert(l.active_id.to_string(), location);
+                }
+                Err(e) => warn!("活动 {} 的预设位置无效，已忽略：{e}", l.active_id),
+            }
         }
         Ok(map)
     }
@@ -253,8 +471,8 @@ impl LocationWithRange {
         }
         Some(LocationWithRange {
             addr: results3[0].to_owned(),
-            lon: results3[1].to_owned(),
-            lat: results3[2].to_owned(),
+            lon: Coordinate::parse("经度", results3[1], LON_RANGE).ok()?,
+            lat: Coordinate::parse("纬度", results3[2], LAT_RANGE).ok()?,
             range: if let Ok(s) = results3[3].trim_end_matches('米').parse() {
                 s
             } else {
@@ -263,40 +481,103 @@ impl LocationWithRange {
         })
     }
     pub fn to_shifted_location(&self) -> Location {
-        const R: f64 = 6371393.0;
+        const R: f64 = EARTH_RADIUS_M;
         let LocationWithRange {
             addr,
             lon,
             lat,
             range,
         } = self;
-        let lat: f64 = lat.parse().unwrap();
-        let lon: f64 = lon.parse().unwrap();
+        let (lat, lon) = (lat.0, lon.0);
         let mut r = rand::thread_rng().gen_range(0..range * 3) as f64 / (*range as f64) / 60.0;
         let theta = rand::thread_rng().gen_range(0..360) as f64 * PI / 180.0;
         r *= (*range as f64)
             / R
             / (1.0 - theta.cos().powi(2) * (lat * PI / 180.0).sin().powi(2)).sqrt();
-        let lat = format!("{:.6}", ((lat * PI / 180.0) + r * theta.sin()) / PI * 180.0);
-        let lon = format!("{:.6}", (lon * PI / 180.0 + r * theta.cos()) / PI * 180.0);
-        Location::new(addr, &lon, &lat, "1108")
+        let round = |v: f64| (v * 1e6).round() / 1e6;
+        let lat = round(((lat * PI / 180.0) + r * theta.sin()) / PI * 180.0);
+        let lon = round((lon * PI / 180.0 + r * theta.cos()) / PI * 180.0);
+        Location {
+            addr: addr.clone(),
+            lon: Coordinate::clamped(lon, LON_RANGE),
+            lat: Coordinate::clamped(lat, LAT_RANGE),
+            alt: Some(Coordinate(1108.0)),
+        }
+        .to_preprocessed()
     }
     pub fn get_range(&self) -> u32 {
         self.range
     }
+    /// 地址。
+    pub fn get_addr(&self) -> &str {
+        &self.addr
+    }
+    /// 经度。
+    pub fn get_lon(&self) -> f64 {
+        self.lon.0
+    }
+    /// 纬度。
+    pub fn get_lat(&self) -> f64 {
+        self.lat.0
+    }
+    /// 位置到签到中心的距离，单位为米。两者应在同一坐标系中。
+    pub fn distance_to(&self, location: &Location) -> f64 {
+        distance(
+            (self.get_lon(), self.get_lat()),
+            (location.get_lon(), location.get_lat()),
+        )
+    }
+    /// 位置是否在签到范围内。两者应在同一坐标系中。
+    pub fn contains(&self, location: &Location) -> bool {
+        self.distance_to(location) <= self.range as f64
+    }
 }
 #[cfg(test)]
 mod tests {
-    use crate::LocationWithRange;
+    use crate::{Location, LocationWithRange};
+    use cxlib_error::LocationParseError;
 
     #[test]
     fn a() {
-        let l = LocationWithRange {
-            addr: "addr".into(),
-            lon: "108.840053".into(),
-            lat: "34.129522".into(),
-            range: 100,
-        };
+        let l: LocationWithRange = ureq::serde_json::from_str(
+            r#"{"address":"addr","longitude":"108.840053","latitude":"34.129522","locationrange":100}"#,
+        )
+        .unwrap();
         println!("{}", l.to_shifted_location())
     }
+
+    #[test]
+    fn test_parse_location() {
+        let l: Location = "教室,116.3,39.90,50".parse().unwrap();
+        assert_eq!(
+            (l.get_lon(), l.get_lat(), l.get_alt()),
+            (116.3, 39.9, Some(50.0))
+        );
+        assert_eq!(l.to_string(), "教室,116.3,39.9,50");
+        let l: Location = "教室,116.3,39.9,".parse().unwrap();
+        assert_eq!(l.get_alt(), None);
+        assert_eq!(l.to_string().parse::<Location>().unwrap(), l);
+        assert_eq!(
+            "教室,116.3".parse::<Location>(),
+            Err(LocationParseError::InvalidFormat)
+        );
+        assert!(matches!(
+            "教室,东经,39.9,".parse::<Location>(),
+            Err(LocationParseError::InvalidNumber { .. })
+        ));
+        assert!(matches!(
+            "教室,116.3,91,".parse::<Location>(),
+            Err(LocationParseError::OutOfRange { .. })
+        ));
+        assert!("教室,NaN,39.9,".parse::<Location>().is_err());
+    }
+
+    #[test]
+    fn test_location_serde() {
+        let json = r#"{"addr":"教室","lon":"116.3","lat":"39.9","alt":""}"#;
+        let l: Location = ureq::serde_json::from_str(json).unwrap();
+        assert_eq!(ureq::serde_json::to_string(&l).unwrap(), json);
+        let json = r#"{"addr":"教室","lon":"116.3","lat":"-99","alt":""}"#;
+        assert!(ureq::serde_json::from_str::<Location>(json).is_err());
+    }
 }
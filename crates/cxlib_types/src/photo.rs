@@ -1,82 +1,211 @@
-use cxlib_error::AgentError;
+use crate::{cloud_drive::parse_response, CloudDrive, CloudFile, UploadProgress};
+use cxlib_error::CloudDriveError;
 use cxlib_protocol::collect::types as protocol;
 use cxlib_user::Session;
+use cxlib_utils::content_hash;
+use log::debug;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::ops::ControlFlow;
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// # PhotoCacheTrait
+/// 照片缓存特型。按用户 uid 及照片内容摘要记录已上传照片的对象 ID, 避免重复上传。
+///
+/// 参见 [`Photo::upload_cached`].
+pub trait PhotoCacheTrait {
+    /// 获取缓存的对象 ID 及记录时间（毫秒时间戳）。
+    fn get_cached_photo(&self, uid: &str, hash: &str) -> Option<(String, u64)>;
+    /// 记录对象 ID, 已有记录时覆盖。
+    fn cache_photo(&self, uid: &str, hash: &str, object_id: &str, time_mills: u64);
+    fn remove_cached_photo(&self, uid: &str, hash: &str);
+}
+impl PhotoCacheTrait for Mutex<HashMap<(String, String), (String, u64)>> {
+    fn get_cached_photo(&self, uid: &str, hash: &str) -> Option<(String, u64)> {
+        self.lock()
+            .unwrap()
+            .get(&(uid.to_owned(), hash.to_owned()))
+            .cloned()
+    }
+
+    fn cache_photo(&self, uid: &str, hash: &str, object_id: &str, time_mills: u64) {
+        self.lock().unwrap().insert(
+            (uid.to_owned(), hash.to_owned()),
+            (object_id.to_owned(), time_mills),
+        );
+    }
+
+    fn remove_cached_photo(&self, uid: &str, hash: &str) {
+        self.lock()
+            .unwrap()
+            .remove(&(uid.to_owned(), hash.to_owned()));
+    }
+}
+fn now_mills() -> u64 {
+    SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+fn is_default_photo_name(name: &str) -> bool {
+    name == "1.png" || name == "1.jpg"
+}
 
-// TODO: 删除 unwrap
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
 pub struct Photo {
     object_id: String,
 }
 
 impl Photo {
-    pub fn get_pan_token(session: &Session) -> Result<String, AgentError> {
+    /// 缓存的有效期，超过该时间的缓存在使用前会在网盘中校验对象是否仍然存在。
+    pub const CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 3600);
+    /// [`Photo::default_cached`] 在缓存中使用的键。
+    pub const DEFAULT_PHOTO_CACHE_KEY: &'static str = "default";
+    pub fn get_pan_token(session: &Session) -> Result<String, CloudDriveError> {
         let r = protocol::pan_token(session)?;
         #[derive(Deserialize)]
         struct Tmp {
             #[serde(rename = "_token")]
             token: String,
         }
-        let r: Tmp = r.into_json().unwrap();
+        let r: Tmp = parse_response(r)?;
         Ok(r.token)
     }
 
-    pub fn new(session: &Session, file: &File, file_name: &str) -> Result<Self, AgentError> {
+    pub fn new(session: &Session, file: &File, file_name: &str) -> Result<Self, CloudDriveError> {
         let token = Self::get_pan_token(session)?;
-        let r = protocol::pan_upload(session, file, session.get_uid(), &token, file_name)?;
+        let file_len = file.metadata().ok().map(|m| m.len());
+        let r = protocol::pan_upload(
+            session,
+            file,
+            file_len,
+            session.get_uid(),
+            &token,
+            file_name,
+        )?;
         #[derive(Deserialize)]
         struct Tmp {
             #[serde(rename = "objectId")]
             object_id: String,
         }
-        let tmp: Tmp = r.into_json().unwrap();
+        let tmp: Tmp = parse_response(r)?;
         Ok(Self {
             object_id: tmp.object_id,
         })
     }
+    /// 上传图片，并通过 `on_progress` 报告进度。参见 [`CloudDrive::upload_with_progress`].
+    pub fn upload_with_progress(
+        session: &Session,
+        source: impl Read + Seek,
+        file_name: &str,
+        on_progress: impl FnMut(UploadProgress) -> ControlFlow<()>,
+    ) -> Result<Self, CloudDriveError> {
+        let object_id =
+            CloudDrive::open(session)?.upload_with_progress(source, file_name, on_progress)?;
+        Ok(Self { object_id })
+    }
     pub fn get_object_id(&self) -> &str {
         &self.object_id
     }
-    pub fn default(session: &Session) -> Option<Self> {
-        Self::find_in_cxpan(session, |a| a == "1.png" || a == "1.jpg").unwrap()
+    /// 在网盘根目录中查找名为 `1.png` 或 `1.jpg` 的图片。
+    pub fn default(session: &Session) -> Result<Option<Self>, CloudDriveError> {
+        Self::find_in_cxpan(session, is_default_photo_name)
     }
-    pub fn find_in_cxpan(
+    /// 从缓存中获取照片。缓存过期时会在网盘根目录中查找该对象，找到则刷新记录时间，否则删除缓存。
+    fn get_cached(
         session: &Session,
-        p: impl Fn(&str) -> bool,
-    ) -> Result<Option<Self>, AgentError> {
-        let r = protocol::pan_chaoxing(session)?;
-        let r_text = r.into_string().unwrap();
-        let start_of_enc = r_text.find("enc =\"").unwrap() + 6;
-        let end_of_enc = r_text[start_of_enc..r_text.len()].find('"').unwrap() + start_of_enc;
-        let enc = &r_text[start_of_enc..end_of_enc];
-        let start_of_root_dir = r_text.find("_rootdir = \"").unwrap() + 12;
-        let end_of_root_dir =
-            r_text[start_of_root_dir..r_text.len()].find('"').unwrap() + start_of_root_dir;
-        let parent_id = &r_text[start_of_root_dir..end_of_root_dir];
-        let r = protocol::pan_list(session, parent_id, enc)?;
-        #[derive(Deserialize)]
-        struct CloudFile {
-            name: String,
-            #[serde(rename = "objectId")]
-            object_id: Option<String>,
+        cache: &(impl PhotoCacheTrait + ?Sized),
+        hash: &str,
+    ) -> Result<Option<Self>, CloudDriveError> {
+        let uid = session.get_uid();
+        let Some((object_id, time_mills)) = cache.get_cached_photo(uid, hash) else {
+            return Ok(None);
+        };
+        let now = now_mills();
+        if now.saturating_sub(time_mills) < Self::CACHE_TTL.as_millis() as u64 {
+            return Ok(Some(Self { object_id }));
         }
-        #[derive(Deserialize)]
-        struct TmpR {
-            list: Vec<CloudFile>,
+        let drive = CloudDrive::open(session)?;
+        let file = drive.find(drive.get_root_id(), |f| {
+            f.get_object_id() == Some(object_id.as_str())
+        })?;
+        if file.is_some() {
+            cache.cache_photo(uid, hash, &object_id, now);
+            Ok(Some(Self { object_id }))
+        } else {
+            debug!("缓存的照片 `{object_id}` 已不在网盘中，将重新获取。");
+            cache.remove_cached_photo(uid, hash);
+            Ok(None)
+        }
+    }
+    /// 上传照片，若相同内容的照片已由该用户上传过，则直接使用缓存的对象 ID.
+    pub fn upload_cached(
+        session: &Session,
+        data: &[u8],
+        file_name: &str,
+        cache: &(impl PhotoCacheTrait + ?Sized),
+    ) -> Result<Self, CloudDriveError> {
+        let hash = content_hash(data);
+        if let Some(photo) = Self::get_cached(session, cache, &hash)? {
+            return Ok(photo);
+        }
+        let photo = Self::upload_with_progress(session, Cursor::new(data), file_name, |_| {
+            ControlFlow::Continue(())
+        })?;
+        cache.cache_photo(session.get_uid(), &hash, &photo.object_id, now_mills());
+        Ok(photo)
+    }
+    /// 同 [`Photo::default`], 但优先使用缓存。
+    pub fn default_cached(
+        session: &Session,
+        cache: &(impl PhotoCacheTrait + ?Sized),
+    ) -> Result<Option<Self>, CloudDriveError> {
+        if let Some(photo) = Self::get_cached(session, cache, Self::DEFAULT_PHOTO_CACHE_KEY)? {
+            return Ok(Some(photo));
         }
-        let r: TmpR = r.into_json().map_err(ureq::Error::from)?;
-        for item in r.list {
-            if p(&item.name) {
-                return Ok(item.object_id.map(|object_id| Self { object_id }));
-            }
+        let photo = Self::find_in_cxpan(session, is_default_photo_name)?;
+        if let Some(photo) = &photo {
+            cache.cache_photo(
+                session.get_uid(),
+                Self::DEFAULT_PHOTO_CACHE_KEY,
+                &photo.object_id,
+                now_mills(),
+            );
         }
-        Ok(None)
+        Ok(photo)
     }
-    pub fn get_from_file(session: &Session, file_path: impl AsRef<Path>) -> Self {
-        let f = File::open(&file_path).unwrap();
-        let file_name = file_path.as_ref().file_name().unwrap().to_str().unwrap();
-        Self::new(session, &f, file_name).unwrap()
+    /// 在网盘根目录中查找文件名满足条件的图片。
+    pub fn find_in_cxpan(
+        session: &Session,
+        p: impl Fn(&str) -> bool,
+    ) -> Result<Option<Self>, CloudDriveError> {
+        let drive = CloudDrive::open(session)?;
+        let file = drive.find(drive.get_root_id(), |f| !f.is_folder() && p(f.get_name()))?;
+        Ok(file.as_ref().and_then(Self::from_cloud_file))
+    }
+    /// 由网盘中的文件构造，文件夹等没有对象 ID 的条目返回 `None`.
+    pub fn from_cloud_file(file: &CloudFile) -> Option<Self> {
+        file.get_object_id().map(|object_id| Self {
+            object_id: object_id.to_owned(),
+        })
+    }
+    /// 上传本地图片文件。
+    pub fn get_from_file(
+        session: &Session,
+        file_path: impl AsRef<Path>,
+    ) -> Result<Self, CloudDriveError> {
+        let file_path = file_path.as_ref();
+        let f = File::open(file_path)?;
+        let file_name = file_path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy())
+            .ok_or_else(|| {
+                CloudDriveError::NotFound(format!("路径 `{}` 中的文件名", file_path.display()))
+            })?;
+        Self::new(session, &f, &file_name)
     }
 }
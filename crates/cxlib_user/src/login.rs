@@ -127,6 +127,10 @@ impl LoginSolvers {
             .insert(solver.login_type().to_string(), solver);
         Ok(())
     }
+    /// 是否已注册该登录协议。
+    pub fn is_registered(login_type: &str) -> bool {
+        LOGIN_SOLVERS.0.read().unwrap().contains_key(login_type)
+    }
 }
 static LOGIN_SOLVERS: OnceInit<LoginSolvers> = OnceInit::uninit();
 unsafe impl StaticDefault for LoginSolvers {
@@ -1,9 +1,12 @@
 pub use crypto::*;
 pub use debug::*;
+pub use hash::*;
 pub use interact::*;
+pub use pool::*;
 pub use ureq::*;
 mod debug;
 mod interact;
+mod pool;
 mod ureq {
     pub fn ureq_get_bytes(
         agent: &ureq::Agent,
@@ -42,3 +45,29 @@ mod crypto {
         r
     }
 }
+
+mod hash {
+    use sha2::{Digest, Sha256};
+
+    /// 计算数据的内容摘要（SHA-256, 小写十六进制），用于识别重复的文件。
+    pub fn content_hash(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+    #[cfg(test)]
+    mod tests {
+        use crate::content_hash;
+
+        #[test]
+        fn test_content_hash() {
+            assert_eq!(
+                content_hash(b""),
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+            );
+            assert_eq!(
+                content_hash(b"abc"),
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+            );
+            assert_ne!(content_hash(b"ab"), content_hash(b"ba"));
+        }
+    }
+}
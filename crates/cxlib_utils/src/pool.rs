@@ -0,0 +1,159 @@
+use onceinit::{OnceInit, OnceInitError, StaticDefault};
+use std::{
+    ops::Deref,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// # WorkerPool
+///
+/// 有界工作线程池，用于并发地发送请求。
+///
+/// 同一时刻最多有 `parallelism` 个任务在运行，且对同一主机的两次请求之间至少间隔 `min_interval`.
+///
+/// 全局实例可通过 [`WorkerPool::get_global`] 获取，并可通过 [`WorkerPool::set_global`] 设置（仅一次）。
+pub struct WorkerPool {
+    parallelism: usize,
+    min_interval: Duration,
+    next_request_time: Mutex<Vec<(String, Instant)>>,
+}
+
+static WORKER_POOL: OnceInit<WorkerPool> = OnceInit::uninit();
+
+unsafe impl StaticDefault for WorkerPool {
+    fn static_default() -> &'static Self {
+        static DEFAULT_WORKER_POOL: WorkerPool = WorkerPool::new(
+            WorkerPool::DEFAULT_PARALLELISM,
+            WorkerPool::DEFAULT_MIN_INTERVAL,
+        );
+        &DEFAULT_WORKER_POOL
+    }
+}
+
+impl WorkerPool {
+    pub const DEFAULT_PARALLELISM: usize = 8;
+    pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_millis(50);
+    /// `parallelism` 为 `0` 时视为 `1`; `min_interval` 为零时不限速。
+    pub const fn new(parallelism: usize, min_interval: Duration) -> Self {
+        Self {
+            parallelism,
+            min_interval,
+            next_request_time: Mutex::new(Vec::new()),
+        }
+    }
+    pub fn get_global() -> &'static WorkerPool {
+        WORKER_POOL.deref()
+    }
+    pub fn set_global(pool: Box<WorkerPool>) -> Result<(), OnceInitError> {
+        WORKER_POOL.init_boxed(pool)
+    }
+    pub fn parallelism(&self) -> usize {
+        self.parallelism
+    }
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+    /// 等待直至可以向 `host` 发送下一个请求。
+    pub fn wait_for_host(&self, host: &str) {
+        if self.min_interval.is_zero() {
+            return;
+        }
+        let wait = {
+            let mut next_request_time = self.next_request_time.lock().unwrap();
+            let now = Instant::now();
+            match next_request_time.iter_mut().find(|(h, _)| h == host) {
+                Some((_, next)) => {
+                    let start = (*next).max(now);
+                    *next = start + self.min_interval;
+                    start - now
+                }
+                None => {
+                    next_request_time.push((host.to_owned(), now + self.min_interval));
+                    Duration::ZERO
+                }
+            }
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait)
+        }
+    }
+    /// 并发地对 `items` 中的每一项调用 `f`, 结果顺序与 `items` 一致。
+    ///
+    /// 每个任务开始前会调用 [`wait_for_host`](Self::wait_for_host) 以限制对 `host` 的请求频率。
+    pub fn map<T, R, I, F>(&self, host: &str, items: I, f: F) -> Vec<R>
+    where
+        I: IntoIterator<Item = T>,
+        T: Send,
+        R: Send,
+        F: Fn(T) -> R + Sync,
+    {
+        let items = items.into_iter().enumerate().collect::<Vec<_>>();
+        let len = items.len();
+        let workers = self.parallelism.clamp(1, len.max(1));
+        let queue = Mutex::new(items.into_iter());
+        let results = Mutex::new(Vec::with_capacity(len));
+        std::thread::scope(|s| {
+            for _ in 0..workers {
+                s.spawn(|| loop {
+                    let next = queue.lock().unwrap().next();
+                    let Some((index, item)) = next else {
+                        break;
+                    };
+                    self.wait_for_host(host);
+                    let r = f(item);
+                    results.lock().unwrap().push((index, r));
+                });
+            }
+        });
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, r)| r).collect()
+    }
+}
+/// 获取 url 中的主机名部分，用于 [`WorkerPool`] 的限速。
+pub fn host_of(url: &str) -> &str {
+    let url = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    url.split(['/', '?', '#']).next().unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{host_of, WorkerPool};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn test_map() {
+        let pool = WorkerPool::new(3, Duration::ZERO);
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let r = pool.map("host", 0..20, |i| {
+            let n = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(n, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(5));
+            running.fetch_sub(1, Ordering::SeqCst);
+            i * 2
+        });
+        assert_eq!(r, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+        assert!(max_running.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let pool = WorkerPool::new(4, Duration::from_millis(20));
+        let start = Instant::now();
+        pool.map("host", 0..5, |_| ());
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn test_host_of() {
+        assert_eq!(
+            host_of("https://mobilelearn.chaoxing.com/v2/apis/active/student/activelist"),
+            "mobilelearn.chaoxing.com"
+        );
+        assert_eq!(host_of("pan-yz.chaoxing.com?a=b"), "pan-yz.chaoxing.com");
+    }
+}